@@ -0,0 +1,29 @@
+//! Compares one batched FFI call against a loop of single calls.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use go_rust_ffi::CircleLibrary;
+use std::hint::black_box;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn batch_areas(c: &mut Criterion) {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let radii: Vec<f64> = (0..1000).map(f64::from).collect();
+    let mut group = c.benchmark_group("circle_areas_1000");
+    group.bench_function("batch", |b| {
+        b.iter(|| circle_lib.calculate_circle_areas(black_box(&radii)))
+    });
+    group.bench_function("loop", |b| {
+        b.iter(|| {
+            black_box(&radii)
+                .iter()
+                .map(|&radius| circle_lib.calculate_circle_area(radius).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, batch_areas);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! Compares registering a closure on every call against a persistent default callback.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use go_rust_ffi::CircleLibrary;
+use std::hint::black_box;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn default_callback(c: &mut Criterion) {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    circle_lib.set_default_callback(|val| val * 2.0);
+    let mut group = c.benchmark_group("callback_1000");
+    group.bench_function("call_callback_with", |b| {
+        b.iter(|| {
+            (0..1000)
+                .map(|i| circle_lib.call_callback_with(black_box(f64::from(i)), |val| val * 2.0))
+                .sum::<f64>()
+        })
+    });
+    group.bench_function("call_callback_default", |b| {
+        b.iter(|| {
+            (0..1000)
+                .map(|i| {
+                    circle_lib
+                        .call_callback_default(black_box(f64::from(i)))
+                        .unwrap()
+                })
+                .sum::<f64>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, default_callback);
+criterion_main!(benches);
@@ -6,39 +6,145 @@ package main
 // Define a callback type that takes a double and returns a double.
 typedef double (*callback_t)(double);
 
+// Define a callback type that takes an int and returns an int.
+typedef int (*int_callback_t)(int);
+
+// Define a callback type that also receives the name of the callback slot being invoked,
+// so a single callback can dispatch to several registered handlers.
+typedef double (*named_callback_t)(double val, const char* name);
+
+// A helper function that calls the provided named callback.
+static double call_named_callback(named_callback_t cb, double val, const char* name) {
+    return cb(val, name);
+}
+
+// Define a callback type that also receives a user data pointer, so the caller can
+// route its own context through to the callback.
+typedef double (*context_callback_t)(double val, void* userData);
+
 // Define an async callback type that takes a double result and a user data pointer.
-// Returns true if more callbacks are expected, false if this is the last callback.
-typedef _Bool (*async_callback_t)(double result, void* userData);
+// Returns non-zero if the caller wants more callbacks, zero to stop producing results.
+// Flags cross the boundary as unsigned char rather than _Bool, so any non-zero byte is
+// read as true on both sides.
+typedef unsigned char (*async_callback_t)(double result, void* userData);
+
+// Define a completion callback type that is called exactly once when a multi-shot
+// computation has finished producing results.
+typedef void (*done_callback_t)(void* userData);
+
+// Define a progress callback type that receives the completed fraction (0.0 to 1.0) of a
+// long-running computation.
+typedef void (*progress_callback_t)(float fraction, void* userData);
+
+// Define a request callback type that blocks until the caller wants the next value of a
+// pull-based computation. Returns non-zero to produce it, zero to stop.
+typedef unsigned char (*request_callback_t)(void* userData);
+
+// Define an error handler type that receives a message describing a Go-side failure.
+typedef void (*error_handler_t)(const char* message, void* userData);
 
 // A helper function that calls the provided synchronous callback.
 static double call_callback(callback_t cb, double val) {
     return cb(val);
 }
 
+// A helper function that calls the provided integer callback.
+static int call_int_callback(int_callback_t cb, int val) {
+    return cb(val);
+}
+
+// A helper function that calls the provided context callback.
+static double call_context_callback(context_callback_t cb, double val, void* userData) {
+    return cb(val, userData);
+}
+
 // A helper function that calls the provided async callback.
-// Returns true if more callbacks are expected, false if this is the last callback.
-static _Bool call_async_callback(async_callback_t cb, double result, void* userData) {
+// Returns non-zero if the caller wants more callbacks, zero to stop producing results.
+static unsigned char call_async_callback(async_callback_t cb, double result, void* userData) {
     return cb(result, userData);
 }
 
+// A helper function that calls the provided completion callback.
+static void call_done_callback(done_callback_t cb, void* userData) {
+    cb(userData);
+}
+
+// A helper function that calls the provided progress callback.
+static void call_progress_callback(progress_callback_t cb, float fraction, void* userData) {
+    cb(fraction, userData);
+}
+
+// A helper function that calls the provided request callback.
+static unsigned char call_request_callback(request_callback_t cb, void* userData) {
+    return cb(userData);
+}
+
+// A helper function that calls the provided error handler.
+static void call_error_handler(error_handler_t cb, const char* message, void* userData) {
+    cb(message, userData);
+}
+
 // Define a Circle struct with a radius field.
 typedef struct {
     double radius;
 } Circle;
 
+// Define a CircleDetails struct with the derived measurements of a circle.
+typedef struct {
+    double area;
+    double circumference;
+    double diameter;
+} CircleDetails;
+
+// Define an AreaMeta struct describing how the library computes areas.
+typedef struct {
+    int precision_bits; // bits of floating-point precision areas are computed with
+    int unit;           // unit code; 0 means the squared unit of the input dimensions
+} AreaMeta;
+
+// Define a NextResult struct returning a generated number together with whether one was
+// produced. Declaring the struct here, instead of relying on the struct cgo generates for
+// a multi-value return, gives it a layout the caller can match.
+typedef struct {
+    int value;
+    unsigned char ok;
+} NextResult;
+
+// Define a DoubleArray struct owning a C-allocated buffer of doubles.
+typedef struct {
+    double* data;
+    size_t len;
+} DoubleArray;
+
+// Define a StringArray struct owning a C-allocated array of C strings.
+typedef struct {
+    char** data;
+    size_t len;
+} StringArray;
+
 // Define Shape enum type and values
 typedef enum {
     SHAPE_CIRCLE = 0,
     SHAPE_SQUARE = 1,
-    SHAPE_TRIANGLE = 2
+    SHAPE_TRIANGLE = 2,
+    SHAPE_RECTANGLE = 3,
+    SHAPE_ELLIPSE = 4
 } ShapeType;
 
 // Define a Shape struct that includes the type and dimensions
 typedef struct {
     ShapeType shape_type;
-    double dimension1; // radius for circle, side for square, base for triangle
-    double dimension2; // unused for circle/square, height for triangle
+    double dimension1; // radius for circle, side for square, base for triangle, width for rectangle, semi-axis a for ellipse
+    double dimension2; // unused for circle/square, height for triangle/rectangle, semi-axis b for ellipse
 } Shape;
+
+// Define a callback type that receives a shape by value and returns a transformed one.
+typedef Shape (*shape_callback_t)(Shape shape);
+
+// A helper function that calls the provided shape callback.
+static Shape call_shape_callback(shape_callback_t cb, Shape shape) {
+    return cb(shape);
+}
 */
 import "C"
 import (
@@ -49,11 +155,182 @@ import (
 	"unsafe"
 )
 
+// abiVersion identifies the layout of the shared structs and exported signatures.
+// Bump it together with EXPECTED_ABI_VERSION on the Rust side on incompatible changes.
+const abiVersion = 2
+
+var (
+	errorHandlerMutex sync.Mutex
+	errorHandler      C.error_handler_t
+	errorHandlerData  unsafe.Pointer
+)
+
+//export SetErrorHandler
+func SetErrorHandler(cb C.error_handler_t, userData unsafe.Pointer) {
+	errorHandlerMutex.Lock()
+	defer errorHandlerMutex.Unlock()
+	errorHandler = cb
+	errorHandlerData = userData
+}
+
+// reportError forwards a formatted message to the registered error handler, if any.
+func reportError(format string, args ...interface{}) {
+	errorHandlerMutex.Lock()
+	cb, userData := errorHandler, errorHandlerData
+	errorHandlerMutex.Unlock()
+	if cb == nil {
+		return
+	}
+	msg := C.CString(fmt.Sprintf(format, args...))
+	defer C.free(unsafe.Pointer(msg))
+	C.call_error_handler(cb, msg, userData)
+}
+
+var (
+	runtimeMutex sync.Mutex
+	runtimeUsers int
+)
+
+// InitLibrary is called by every Rust handle after loading the library. Calls are counted,
+// so a library shared by several handles is only shut down by the last ShutdownLibrary.
+//
+//export InitLibrary
+func InitLibrary() {
+	runtimeMutex.Lock()
+	defer runtimeMutex.Unlock()
+	runtimeUsers++
+}
+
+// ShutdownLibrary is called before a Rust handle releases the library. Once no handle is
+// left, every number generator still running is stopped so its goroutine exits.
+//
+//export ShutdownLibrary
+func ShutdownLibrary() {
+	runtimeMutex.Lock()
+	defer runtimeMutex.Unlock()
+	if runtimeUsers > 0 {
+		runtimeUsers--
+	}
+	if runtimeUsers > 0 {
+		return
+	}
+
+	genMutex.Lock()
+	defer genMutex.Unlock()
+	for id, gen := range generators {
+		gen.stop()
+		delete(generators, id)
+	}
+}
+
+//export LibraryAbiVersion
+func LibraryAbiVersion() C.int {
+	return C.int(abiVersion)
+}
+
+// NativeByteOrder reports the byte order the library was built for: 0 for little-endian,
+// 1 for big-endian.
+//
+//export NativeByteOrder
+func NativeByteOrder() C.int {
+	probe := uint16(1)
+	if *(*byte)(unsafe.Pointer(&probe)) == 1 {
+		return 0
+	}
+	return 1
+}
+
+//export SizeOfShape
+func SizeOfShape() C.size_t {
+	return C.size_t(unsafe.Sizeof(C.Shape{}))
+}
+
+//export SizeOfCircle
+func SizeOfCircle() C.size_t {
+	return C.size_t(unsafe.Sizeof(C.Circle{}))
+}
+
+//export SizeOfCircleDetails
+func SizeOfCircleDetails() C.size_t {
+	return C.size_t(unsafe.Sizeof(C.CircleDetails{}))
+}
+
+//export SizeOfAreaMeta
+func SizeOfAreaMeta() C.size_t {
+	return C.size_t(unsafe.Sizeof(C.AreaMeta{}))
+}
+
+//export SizeOfNextResult
+func SizeOfNextResult() C.size_t {
+	return C.size_t(unsafe.Sizeof(C.NextResult{}))
+}
+
+//export LibraryPi
+func LibraryPi() C.double {
+	return C.double(math.Pi)
+}
+
+//export AreaMetadata
+func AreaMetadata() C.AreaMeta {
+	// Areas are computed in float64 and carry no unit of their own.
+	return C.AreaMeta{precision_bits: 64, unit: 0}
+}
+
 //export CalculateCircleArea
 func CalculateCircleArea(radius C.double) C.double {
 	return C.double(math.Pi * float64(radius) * float64(radius))
 }
 
+//export CalculateCircleAreaF32
+func CalculateCircleAreaF32(radius C.float) C.float {
+	// Compute in single precision to match the float32 entry point.
+	r := float32(radius)
+	return C.float(float32(math.Pi) * r * r)
+}
+
+//export CalculateCircumference
+func CalculateCircumference(radius C.double) C.double {
+	return C.double(2 * math.Pi * float64(radius))
+}
+
+//export CalculateDiameter
+func CalculateDiameter(radius C.double) C.double {
+	return C.double(2 * float64(radius))
+}
+
+//export AddInts
+func AddInts(a, b C.int) C.int {
+	// C int arithmetic wraps around on overflow; the caller detects it.
+	return C.int(int32(a) + int32(b))
+}
+
+//export MultiplyInts
+func MultiplyInts(a, b C.int) C.int {
+	return C.int(int32(a) * int32(b))
+}
+
+//export CalculateCircleAreasBatch
+func CalculateCircleAreasBatch(radii *C.double, n int, out *C.double) {
+	// View the caller's buffers as Go slices without copying.
+	in := unsafe.Slice(radii, n)
+	areas := unsafe.Slice(out, n)
+	for i, r := range in {
+		areas[i] = CalculateCircleArea(r)
+	}
+}
+
+// SumAreas adds up n areas passed as a pointer and count, the C stand-in for variadic
+// arguments.
+//
+//export SumAreas
+func SumAreas(areas *C.double, n C.int) C.double {
+	var sum C.double
+	for _, area := range unsafe.Slice(areas, int(n)) {
+		sum += area
+	}
+	return sum
+}
+
 //export CalculateCircleStructArea
 func CalculateCircleStructArea(c C.Circle) C.double {
 	// Convert the C.double field to a Go float64.
@@ -69,44 +346,324 @@ func FormatCircleInfo(radius C.double) *C.char {
 	return C.CString(result)
 }
 
+// FormatCircleInfoPrec formats the circle information like FormatCircleInfo, with the
+// given number of decimal places instead of two.
+//
+//export FormatCircleInfoPrec
+func FormatCircleInfoPrec(radius C.double, decimals C.int) *C.char {
+	area := CalculateCircleArea(radius)
+	prec := int(decimals)
+	if prec < 0 {
+		prec = 0
+	}
+	result := fmt.Sprintf("Circle with radius %.*f has area %.*f", prec, radius, prec, area)
+	return C.CString(result)
+}
+
+//export FormatCircleInfoLabeled
+func FormatCircleInfoLabeled(label *C.char, radius C.double) *C.char {
+	// Copy the label into Go memory; the caller owns the original buffer.
+	goLabel := C.GoString(label)
+	area := CalculateCircleArea(radius)
+	result := fmt.Sprintf("%s: Circle with radius %.2f has area %.2f", goLabel, radius, area)
+	return C.CString(result)
+}
+
+//export StringIsOwned
+func StringIsOwned() C.uchar {
+	// Strings are allocated with C.CString and must be released through FreeString.
+	return 1
+}
+
 //export FreeString
 func FreeString(str *C.char) {
 	C.free(unsafe.Pointer(str))
 }
 
+//export GetCircleDetails
+func GetCircleDetails(radius C.double) *C.CircleDetails {
+	// Allocate with C.malloc so the caller can release it through FreeCircleDetails.
+	details := (*C.CircleDetails)(C.malloc(C.size_t(unsafe.Sizeof(C.CircleDetails{}))))
+	if details == nil {
+		return nil
+	}
+	r := float64(radius)
+	details.area = C.double(math.Pi * r * r)
+	details.circumference = C.double(2 * math.Pi * r)
+	details.diameter = C.double(2 * r)
+	return details
+}
+
+//export FreeCircleDetails
+func FreeCircleDetails(details *C.CircleDetails) {
+	C.free(unsafe.Pointer(details))
+}
+
+//export MapRadii
+func MapRadii(radii *C.double, n int) *C.DoubleArray {
+	// Allocate with C.malloc so the caller can release it through FreeDoubleArray.
+	arr := (*C.DoubleArray)(C.malloc(C.size_t(unsafe.Sizeof(C.DoubleArray{}))))
+	if arr == nil {
+		return nil
+	}
+	arr.data = nil
+	arr.len = 0
+	if n <= 0 {
+		return arr
+	}
+	arr.data = (*C.double)(C.malloc(C.size_t(n) * C.size_t(unsafe.Sizeof(C.double(0)))))
+	if arr.data == nil {
+		C.free(unsafe.Pointer(arr))
+		return nil
+	}
+	arr.len = C.size_t(n)
+	in := unsafe.Slice(radii, n)
+	out := unsafe.Slice(arr.data, n)
+	// Map each radius to the running total of the circle areas seen so far.
+	total := 0.0
+	for i, r := range in {
+		total += math.Pi * float64(r) * float64(r)
+		out[i] = C.double(total)
+	}
+	return arr
+}
+
+// FormatCircleInfos formats the circle information for every radius, like
+// FormatCircleInfo. The array and its strings are allocated with C.malloc and must be
+// released through FreeStringArray.
+//
+//export FormatCircleInfos
+func FormatCircleInfos(radii *C.double, n int) *C.StringArray {
+	arr := (*C.StringArray)(C.malloc(C.size_t(unsafe.Sizeof(C.StringArray{}))))
+	if arr == nil {
+		return nil
+	}
+	arr.data = nil
+	arr.len = 0
+	if n <= 0 {
+		return arr
+	}
+	arr.data = (**C.char)(C.malloc(C.size_t(n) * C.size_t(unsafe.Sizeof((*C.char)(nil)))))
+	if arr.data == nil {
+		C.free(unsafe.Pointer(arr))
+		return nil
+	}
+	arr.len = C.size_t(n)
+	in := unsafe.Slice(radii, n)
+	out := unsafe.Slice(arr.data, n)
+	for i, r := range in {
+		out[i] = FormatCircleInfo(r)
+	}
+	return arr
+}
+
+//export FreeStringArray
+func FreeStringArray(arr *C.StringArray) {
+	if arr == nil {
+		return
+	}
+	for _, str := range unsafe.Slice(arr.data, int(arr.len)) {
+		C.free(unsafe.Pointer(str))
+	}
+	C.free(unsafe.Pointer(arr.data))
+	C.free(unsafe.Pointer(arr))
+}
+
+// maxGeneratedShapes caps how many shapes a single GenerateShapes call produces.
+const maxGeneratedShapes = 64
+
+// shapeTypeCount is the number of values of the C ShapeType enum.
+const shapeTypeCount = 5
+
+// GenerateShapes produces up to count shapes, cycling through the shape types, and writes
+// how many it produced through produced. The array is allocated with C.malloc and must be
+// released through FreeShapes.
+//
+//export GenerateShapes
+func GenerateShapes(count int, produced *int) *C.Shape {
+	*produced = 0
+	n := min(count, maxGeneratedShapes)
+	if n <= 0 {
+		return nil
+	}
+	shapes := (*C.Shape)(C.malloc(C.size_t(n) * C.size_t(unsafe.Sizeof(C.Shape{}))))
+	if shapes == nil {
+		return nil
+	}
+	out := unsafe.Slice(shapes, n)
+	for i := range out {
+		size := C.double(i + 1)
+		out[i] = C.Shape{
+			shape_type: C.ShapeType(i % shapeTypeCount),
+			dimension1: size,
+			dimension2: size / 2,
+		}
+	}
+	*produced = n
+	return shapes
+}
+
+//export FreeShapes
+func FreeShapes(shapes *C.Shape, n int) {
+	C.free(unsafe.Pointer(shapes))
+}
+
+//export FreeDoubleArray
+func FreeDoubleArray(arr *C.DoubleArray) {
+	if arr == nil {
+		return
+	}
+	C.free(unsafe.Pointer(arr.data))
+	C.free(unsafe.Pointer(arr))
+}
+
 //export CallCallback
 func CallCallback(val C.double, cb C.callback_t) C.double {
 	return C.call_callback(cb, val)
 }
 
+//export CallIntCallback
+func CallIntCallback(val C.int, cb C.int_callback_t) C.int {
+	return C.call_int_callback(cb, val)
+}
+
+//export TransformShape
+func TransformShape(shape C.Shape, cb C.shape_callback_t) C.Shape {
+	return C.call_shape_callback(cb, shape)
+}
+
+//export CallNamedCallback
+func CallNamedCallback(val C.double, name *C.char, cb C.named_callback_t) C.double {
+	// Invoke the callback slot with the given name; the caller dispatches on it.
+	return C.call_named_callback(cb, val, name)
+}
+
+//export CallCallbackWithContext
+func CallCallbackWithContext(val C.double, cb C.context_callback_t, userData unsafe.Pointer) C.double {
+	// The callback is invoked synchronously; userData is not retained after returning.
+	return C.call_context_callback(cb, val, userData)
+}
+
+// CallCallbackConcurrent invokes the callback once per value, each from its own goroutine,
+// and writes the results into out in input order. It waits for every goroutine before
+// returning, and userData is not retained afterwards.
+//
+//export CallCallbackConcurrent
+func CallCallbackConcurrent(vals *C.double, n int, cb C.context_callback_t, userData unsafe.Pointer, out *C.double) {
+	in := unsafe.Slice(vals, n)
+	results := unsafe.Slice(out, n)
+	var wg sync.WaitGroup
+	for i := range in {
+		wg.Add(1)
+		go func(i int) {
+			defer wg.Done()
+			results[i] = C.call_context_callback(cb, in[i], userData)
+		}(i)
+	}
+	wg.Wait()
+}
+
+var (
+	flushMutex sync.Mutex
+	// flushed is closed by FlushAsync to wake every async calculation that is still waiting.
+	flushed = make(chan struct{})
+)
+
+// flushSignal returns the channel the next FlushAsync closes. Exports take it before
+// starting their goroutine, so a flush also covers calls whose goroutine has not begun
+// waiting yet.
+func flushSignal() <-chan struct{} {
+	flushMutex.Lock()
+	defer flushMutex.Unlock()
+	return flushed
+}
+
+// asyncDelay waits for d, or less if the call's flush signal has fired.
+func asyncDelay(d time.Duration, flush <-chan struct{}) {
+	select {
+	case <-time.After(d):
+	case <-flush:
+	}
+}
+
+// FlushAsync cuts short the simulated delay of every async calculation in flight, so
+// their callbacks fire right away. It does not wait for the callbacks to run.
+//
+//export FlushAsync
+func FlushAsync() {
+	flushMutex.Lock()
+	defer flushMutex.Unlock()
+	close(flushed)
+	flushed = make(chan struct{})
+}
+
 //export CalculateCircleAreaAsync
 func CalculateCircleAreaAsync(radius C.double, cb C.async_callback_t, userData unsafe.Pointer) {
+	flush := flushSignal()
 	go func(r C.double, cb C.async_callback_t, userData unsafe.Pointer) {
 		// Simulate asynchronous delay.
-		time.Sleep(1 * time.Second)
+		asyncDelay(1*time.Second, flush)
 		area := C.double(math.Pi * float64(r) * float64(r))
 		// Instead of converting the function pointer, call the helper C function.
 		C.call_async_callback(cb, area, userData)
 	}(radius, cb, userData)
 }
 
+// progressSteps is how many progress reports CalculateCircleAreaAsyncProgress makes.
+const progressSteps = 4
+
+//export CalculateCircleAreaAsyncProgress
+func CalculateCircleAreaAsyncProgress(radius C.double, progress C.progress_callback_t, cb C.async_callback_t, userData unsafe.Pointer) {
+	flush := flushSignal()
+	go func() {
+		// Simulate a long-running computation that reports its progress along the way.
+		for i := 1; i <= progressSteps; i++ {
+			asyncDelay(250*time.Millisecond, flush)
+			C.call_progress_callback(progress, C.float(float32(i)/progressSteps), userData)
+		}
+		area := C.double(math.Pi * float64(radius) * float64(radius))
+		C.call_async_callback(cb, area, userData)
+	}()
+}
+
 //export CalculateCircleAreaAsyncMultiple
-func CalculateCircleAreaAsyncMultiple(radius C.double, cb C.async_callback_t, userData unsafe.Pointer) {
+func CalculateCircleAreaAsyncMultiple(radius C.double, cb C.async_callback_t, done C.done_callback_t, userData unsafe.Pointer) {
+    flush := flushSignal()
     // Spawn a goroutine that calls the callback multiple times.
-    go func(r C.double, cb C.async_callback_t, userData unsafe.Pointer) {
+    go func(r C.double, cb C.async_callback_t, done C.done_callback_t, userData unsafe.Pointer) {
+        // Signal completion exactly once, however the loop ends.
+        defer C.call_done_callback(done, userData)
         // For example, call the callback three times (simulate multiple events).
         for i := 0; i < 3; i++ {
-            time.Sleep(1 * time.Second)
+            asyncDelay(1*time.Second, flush)
             // Calculate the area (same value each time in this example).
             area := C.double(math.Pi * float64(r) * float64(r))
             // Use the helper function to call the callback.
-            // If this is the last callback (i == 2), return false to signal completion
-            shouldContinue := bool(C.call_async_callback(cb, area, userData))
+            // Stop early if the caller is no longer interested in results.
+            shouldContinue := C.call_async_callback(cb, area, userData) != 0
             if !shouldContinue {
                 break
             }
         }
-    }(radius, cb, userData)
+    }(radius, cb, done, userData)
+}
+
+// CalculateCircleAreaPoll produces areas only when the caller asks for them. Before each
+// value it blocks in the request callback until the caller requests one, so it never runs
+// ahead of the consumer. It stops when either callback returns zero, then signals
+// completion exactly once.
+//
+//export CalculateCircleAreaPoll
+func CalculateCircleAreaPoll(radius C.double, request C.request_callback_t, cb C.async_callback_t, done C.done_callback_t, userData unsafe.Pointer) {
+	go func() {
+		defer C.call_done_callback(done, userData)
+		for C.call_request_callback(request, userData) != 0 {
+			area := C.double(math.Pi * float64(radius) * float64(radius))
+			if C.call_async_callback(cb, area, userData) == 0 {
+				return
+			}
+		}
+	}()
 }
 
 //export CalculateShapeArea
@@ -118,31 +675,109 @@ func CalculateShapeArea(shape C.Shape) C.double {
         return C.double(float64(shape.dimension1) * float64(shape.dimension1))
     case C.SHAPE_TRIANGLE:
         return C.double(0.5 * float64(shape.dimension1) * float64(shape.dimension2))
+    case C.SHAPE_RECTANGLE:
+        return C.double(float64(shape.dimension1) * float64(shape.dimension2))
+    case C.SHAPE_ELLIPSE:
+        return C.double(math.Pi * float64(shape.dimension1) * float64(shape.dimension2))
     default:
+        reportError("CalculateShapeArea: unknown shape type %d", int(shape.shape_type))
         return 0.0
     }
 }
 
+// Error codes written by CalculateShapeAreaChecked; 0 means success.
+const (
+	errUnknownShapeType = 1
+	errInvalidDimension = 2
+	errDegenerateShape  = 3
+)
+
+// CalculateShapeAreaChecked is like CalculateShapeArea, but validates the shape first and
+// writes an error code through code instead of returning a meaningless area.
+//
+//export CalculateShapeAreaChecked
+func CalculateShapeAreaChecked(shape C.Shape, code *C.int) C.double {
+	*code = 0
+	d1, d2 := float64(shape.dimension1), float64(shape.dimension2)
+	// Circles and squares only use the first dimension.
+	usesTwo := true
+	switch shape.shape_type {
+	case C.SHAPE_CIRCLE, C.SHAPE_SQUARE:
+		usesTwo = false
+	case C.SHAPE_TRIANGLE, C.SHAPE_RECTANGLE, C.SHAPE_ELLIPSE:
+	default:
+		*code = errUnknownShapeType
+		return 0.0
+	}
+	invalid := func(d float64) bool { return d < 0 || math.IsNaN(d) || math.IsInf(d, 0) }
+	if invalid(d1) || (usesTwo && invalid(d2)) {
+		*code = errInvalidDimension
+		return 0.0
+	}
+	if d1 == 0 || (usesTwo && d2 == 0) {
+		*code = errDegenerateShape
+		return 0.0
+	}
+	return CalculateShapeArea(shape)
+}
+
+// NormalizeShapes rewrites n shapes in the caller's buffer in place: every dimension
+// becomes non-negative, and the unused second dimension of circles and squares is zeroed.
+// The pointer is not retained after returning.
+//
+//export NormalizeShapes
+func NormalizeShapes(shapes *C.Shape, n int) {
+	// View the caller's buffer as a Go slice without copying.
+	buf := unsafe.Slice(shapes, n)
+	for i := range buf {
+		shape := &buf[i]
+		shape.dimension1 = C.double(math.Abs(float64(shape.dimension1)))
+		shape.dimension2 = C.double(math.Abs(float64(shape.dimension2)))
+		if shape.shape_type == C.SHAPE_CIRCLE || shape.shape_type == C.SHAPE_SQUARE {
+			shape.dimension2 = 0
+		}
+	}
+}
+
+//export CalculateShapeAreasBatch
+func CalculateShapeAreasBatch(shapes *C.Shape, n int, out *C.double) {
+	// View the caller's buffers as Go slices without copying.
+	in := unsafe.Slice(shapes, n)
+	areas := unsafe.Slice(out, n)
+	for i, shape := range in {
+		areas[i] = CalculateShapeArea(shape)
+	}
+}
+
 // NumberGenerator manages number generation
 type NumberGenerator struct {
     ch    chan int
-    done  chan bool
+    done  chan struct{}
+    // stopOnce guards closing done, so a generator can be stopped any number of times.
+    stopOnce sync.Once
+}
+
+// stop makes the generator goroutine close ch and exit. Closing done rather than sending on
+// it reaches the goroutine whether it is blocked on a send or sleeping, and never blocks.
+func (gen *NumberGenerator) stop() {
+    gen.stopOnce.Do(func() { close(gen.done) })
 }
 
 var (
-    generators = make(map[int64]*NumberGenerator)
-    nextID    int64 = 1
+    // Ids are Go uintptrs, exported by cgo as GoUintptr.
+    generators = make(map[uintptr]*NumberGenerator)
+    nextID    uintptr = 1
     genMutex sync.Mutex
 )
 
 //export CreateNumberGenerator
-func CreateNumberGenerator() C.longlong {
+func CreateNumberGenerator() uintptr {
     genMutex.Lock()
     defer genMutex.Unlock()
 
     gen := &NumberGenerator{
         ch:   make(chan int),
-        done: make(chan bool),
+        done: make(chan struct{}),
     }
 
     // Start generating numbers in a goroutine
@@ -163,46 +798,71 @@ func CreateNumberGenerator() C.longlong {
     id := nextID
     generators[id] = gen
     nextID++
-    return C.longlong(id)
+    return id
 }
 
+// GetNextNumber writes the next number through out and reports whether one was
+// produced, as 1 or 0. A multi-value return would be exported as a cgo-generated struct, which is
+// fragile to match from other languages, so the number uses an out-parameter instead.
+//
 //export GetNextNumber
-func GetNextNumber(id C.longlong) (C.int, C._Bool) {
+func GetNextNumber(id uintptr, out *C.int) C.uchar {
+    num, ok := nextNumber("GetNextNumber", id)
+    if !ok {
+        return 0
+    }
+    *out = C.int(num)
+    return 1
+}
+
+// GetNextNumber2 returns the next number and whether one was produced in a NextResult,
+// the recommended way to return several values.
+//
+//export GetNextNumber2
+func GetNextNumber2(id uintptr) C.NextResult {
+    num, ok := nextNumber("GetNextNumber2", id)
+    if !ok {
+        return C.NextResult{}
+    }
+    return C.NextResult{value: C.int(num), ok: 1}
+}
+
+// nextNumber blocks until the generator with the given id produces a number, reporting
+// false once it has been stopped or if the id is unknown.
+func nextNumber(caller string, id uintptr) (int, bool) {
     genMutex.Lock()
-    gen, exists := generators[int64(id)]
+    gen, exists := generators[id]
     genMutex.Unlock()
 
     if !exists {
-        return 0, C._Bool(false)
+        reportError("%s: unknown generator id %d", caller, id)
+        return 0, false
     }
 
     num, ok := <-gen.ch
-    return C.int(num), C._Bool(ok)
+    return num, ok
 }
 
 //export StopNumberGenerator
-func StopNumberGenerator(id C.longlong) {
+func StopNumberGenerator(id uintptr) {
     genMutex.Lock()
-    gen, exists := generators[int64(id)]
+    gen, exists := generators[id]
     genMutex.Unlock()
 
     if exists {
-        gen.done <- true
+        gen.stop()
         time.Sleep(100 * time.Millisecond) // Wait for cleanup
     }
 }
 
 //export FreeNumberGenerator
-func FreeNumberGenerator(id C.longlong) {
+func FreeNumberGenerator(id uintptr) {
     genMutex.Lock()
     defer genMutex.Unlock()
 
-    if gen, exists := generators[int64(id)]; exists {
-        select {
-        case gen.done <- true:
-        default:
-        }
-        delete(generators, int64(id))
+    if gen, exists := generators[id]; exists {
+        gen.stop()
+        delete(generators, id)
     }
 }
 
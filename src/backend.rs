@@ -0,0 +1,393 @@
+use crate::error::LibraryError;
+use crate::shapes::validate_dimension;
+use crate::{Circle, CircleLibrary, Shape, ShapeArea, ShapeType};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use tokio::sync::mpsc;
+
+/// The operations offered by the circle library, independent of how they are computed.
+///
+/// `CircleLibrary` implements this by calling into the Go shared library, while
+/// `MockBackend` computes everything in Rust. Code written against `CircleBackend` can
+/// therefore be tested deterministically without building or shipping a `.dll`/`.so`.
+pub trait CircleBackend {
+    /// Calculates the area of a circle given the radius.
+    ///
+    /// Implementations reject negative, NaN and infinite radii with
+    /// `LibraryError::InvalidInput`.
+    fn calculate_circle_area(&self, radius: f64) -> Result<f64, LibraryError>;
+
+    /// Calculates the area of the given circle, validating its radius like
+    /// `calculate_circle_area`.
+    fn calculate_circle_struct_area(&self, circle: &Circle) -> Result<f64, LibraryError>;
+
+    /// Returns a formatted string with circle information.
+    fn format_circle_info(&self, radius: f64) -> Result<String, LibraryError>;
+
+    /// Invokes `callback` with `val` and returns its result.
+    fn call_callback_with<F>(&self, val: f64, callback: F) -> f64
+    where
+        F: Fn(f64) -> f64 + Send + 'static;
+
+    /// Asynchronously calculates the area of a circle.
+    fn calculate_circle_area_async(
+        &self,
+        radius: f64,
+    ) -> impl Future<Output = Result<f64, LibraryError>> + Send;
+
+    /// Produces several area results for the same radius on a channel.
+    fn calculate_circle_area_async_multi(
+        &self,
+        radius: f64,
+    ) -> Result<mpsc::UnboundedReceiver<f64>, LibraryError>;
+
+    /// Calculates the area of any shape.
+    fn calculate_shape_area(&self, shape: &Shape) -> Result<f64, LibraryError>;
+}
+
+impl CircleBackend for CircleLibrary {
+    fn calculate_circle_area(&self, radius: f64) -> Result<f64, LibraryError> {
+        CircleLibrary::calculate_circle_area(self, radius)
+    }
+
+    fn calculate_circle_struct_area(&self, circle: &Circle) -> Result<f64, LibraryError> {
+        CircleLibrary::calculate_circle_struct_area(self, circle)
+    }
+
+    fn format_circle_info(&self, radius: f64) -> Result<String, LibraryError> {
+        CircleLibrary::format_circle_info(self, radius)
+    }
+
+    fn call_callback_with<F>(&self, val: f64, callback: F) -> f64
+    where
+        F: Fn(f64) -> f64 + Send + 'static,
+    {
+        CircleLibrary::call_callback_with(self, val, callback)
+    }
+
+    fn calculate_circle_area_async(
+        &self,
+        radius: f64,
+    ) -> impl Future<Output = Result<f64, LibraryError>> + Send {
+        CircleLibrary::calculate_circle_area_async(self, radius)
+    }
+
+    fn calculate_circle_area_async_multi(
+        &self,
+        radius: f64,
+    ) -> Result<mpsc::UnboundedReceiver<f64>, LibraryError> {
+        CircleLibrary::calculate_circle_area_async_multi(self, radius)
+    }
+
+    fn calculate_shape_area(&self, shape: &Shape) -> Result<f64, LibraryError> {
+        CircleLibrary::calculate_shape_area(self, shape)
+    }
+}
+
+/// An object-safe counterpart of `CircleBackend`, for holding a backend as
+/// `Box<dyn CircleApi>` or `Arc<dyn CircleApi>`.
+///
+/// `CircleBackend` cannot be used as a trait object, since `call_callback_with` is generic
+/// and `calculate_circle_area_async` returns an opaque future. Here the callback is boxed
+/// and the future is returned as a boxed trait object instead. Every `CircleBackend`
+/// implements `CircleApi`, so `CircleLibrary`, `MockBackend` and any custom backend can be
+/// injected the same way:
+///
+/// ```ignore
+/// let api: Box<dyn CircleApi> = Box::new(CircleLibrary::new("lib.dll")?);
+/// let area = api.calculate_circle_area(2.0)?;
+/// ```
+///
+/// The method names match those of `CircleBackend`, so calls on a concrete backend are
+/// ambiguous while both traits are imported; import only the one in use.
+pub trait CircleApi: Send + Sync {
+    /// Calculates the area of a circle given the radius.
+    fn calculate_circle_area(&self, radius: f64) -> Result<f64, LibraryError>;
+
+    /// Calculates the area of the given circle.
+    fn calculate_circle_struct_area(&self, circle: &Circle) -> Result<f64, LibraryError>;
+
+    /// Returns a formatted string with circle information.
+    fn format_circle_info(&self, radius: f64) -> Result<String, LibraryError>;
+
+    /// Invokes `callback` with `val` and returns its result.
+    fn call_callback_with(&self, val: f64, callback: Box<dyn Fn(f64) -> f64 + Send>) -> f64;
+
+    /// Asynchronously calculates the area of a circle.
+    fn calculate_circle_area_async(
+        &self,
+        radius: f64,
+    ) -> Pin<Box<dyn Future<Output = Result<f64, LibraryError>> + Send + '_>>;
+
+    /// Produces several area results for the same radius on a channel.
+    fn calculate_circle_area_async_multi(
+        &self,
+        radius: f64,
+    ) -> Result<mpsc::UnboundedReceiver<f64>, LibraryError>;
+
+    /// Calculates the area of any shape.
+    fn calculate_shape_area(&self, shape: &Shape) -> Result<f64, LibraryError>;
+}
+
+impl<B: CircleBackend + Send + Sync> CircleApi for B {
+    fn calculate_circle_area(&self, radius: f64) -> Result<f64, LibraryError> {
+        CircleBackend::calculate_circle_area(self, radius)
+    }
+
+    fn calculate_circle_struct_area(&self, circle: &Circle) -> Result<f64, LibraryError> {
+        CircleBackend::calculate_circle_struct_area(self, circle)
+    }
+
+    fn format_circle_info(&self, radius: f64) -> Result<String, LibraryError> {
+        CircleBackend::format_circle_info(self, radius)
+    }
+
+    fn call_callback_with(&self, val: f64, callback: Box<dyn Fn(f64) -> f64 + Send>) -> f64 {
+        CircleBackend::call_callback_with(self, val, callback)
+    }
+
+    fn calculate_circle_area_async(
+        &self,
+        radius: f64,
+    ) -> Pin<Box<dyn Future<Output = Result<f64, LibraryError>> + Send + '_>> {
+        Box::pin(CircleBackend::calculate_circle_area_async(self, radius))
+    }
+
+    fn calculate_circle_area_async_multi(
+        &self,
+        radius: f64,
+    ) -> Result<mpsc::UnboundedReceiver<f64>, LibraryError> {
+        CircleBackend::calculate_circle_area_async_multi(self, radius)
+    }
+
+    fn calculate_shape_area(&self, shape: &Shape) -> Result<f64, LibraryError> {
+        CircleBackend::calculate_shape_area(self, shape)
+    }
+}
+
+/// Number of results `MockBackend::calculate_circle_area_async_multi` produces, matching
+/// the Go implementation.
+const MOCK_MULTI_SHOT_COUNT: usize = 3;
+
+/// An in-process `CircleBackend` that computes everything in Rust.
+///
+/// Areas can be overridden per radius with canned values, and the mock counts how often
+/// each method and each callback closure was invoked so tests can assert on interactions.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    // Canned areas keyed by the bit pattern of the radius.
+    canned_areas: Mutex<HashMap<u64, f64>>,
+    call_counts: Mutex<HashMap<&'static str, usize>>,
+    callback_invocations: AtomicUsize,
+}
+
+impl MockBackend {
+    /// Creates a mock that computes areas with `std::f64::consts::PI`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the mock with a canned area for `radius`, replacing the computed value.
+    pub fn with_area(self, radius: f64, area: f64) -> Self {
+        self.set_area(radius, area);
+        self
+    }
+
+    /// Sets a canned area for `radius`, replacing the computed value.
+    pub fn set_area(&self, radius: f64, area: f64) {
+        self.canned_areas
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(radius.to_bits(), area);
+    }
+
+    /// Returns how many times the named `CircleBackend` method has been called.
+    pub fn call_count(&self, method: &str) -> usize {
+        self.call_counts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(method)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns how many times callback closures passed to the mock have been invoked.
+    pub fn callback_invocations(&self) -> usize {
+        self.callback_invocations.load(Ordering::SeqCst)
+    }
+
+    fn record_call(&self, method: &'static str) {
+        *self
+            .call_counts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(method)
+            .or_insert(0) += 1;
+    }
+
+    fn area_for(&self, radius: f64) -> f64 {
+        match self
+            .canned_areas
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&radius.to_bits())
+        {
+            Some(&area) => area,
+            None => Circle { radius }.area(),
+        }
+    }
+}
+
+impl CircleBackend for MockBackend {
+    fn calculate_circle_area(&self, radius: f64) -> Result<f64, LibraryError> {
+        self.record_call("calculate_circle_area");
+        validate_dimension("radius", radius)?;
+        Ok(self.area_for(radius))
+    }
+
+    fn calculate_circle_struct_area(&self, circle: &Circle) -> Result<f64, LibraryError> {
+        self.record_call("calculate_circle_struct_area");
+        validate_dimension("radius", circle.radius)?;
+        Ok(self.area_for(circle.radius))
+    }
+
+    fn format_circle_info(&self, radius: f64) -> Result<String, LibraryError> {
+        self.record_call("format_circle_info");
+        Ok(format!(
+            "Circle with radius {:.2} has area {:.2}",
+            radius,
+            self.area_for(radius)
+        ))
+    }
+
+    fn call_callback_with<F>(&self, val: f64, callback: F) -> f64
+    where
+        F: Fn(f64) -> f64 + Send + 'static,
+    {
+        self.record_call("call_callback_with");
+        self.callback_invocations.fetch_add(1, Ordering::SeqCst);
+        callback(val)
+    }
+
+    fn calculate_circle_area_async(
+        &self,
+        radius: f64,
+    ) -> impl Future<Output = Result<f64, LibraryError>> + Send {
+        self.record_call("calculate_circle_area_async");
+        std::future::ready(validate_dimension("radius", radius).map(|()| self.area_for(radius)))
+    }
+
+    fn calculate_circle_area_async_multi(
+        &self,
+        radius: f64,
+    ) -> Result<mpsc::UnboundedReceiver<f64>, LibraryError> {
+        self.record_call("calculate_circle_area_async_multi");
+        validate_dimension("radius", radius)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let area = self.area_for(radius);
+        for _ in 0..MOCK_MULTI_SHOT_COUNT {
+            // The receiver is still held by us, so sending cannot fail.
+            let _ = tx.send(area);
+        }
+        // Dropping the sender closes the channel, like the Go side signalling completion.
+        Ok(rx)
+    }
+
+    fn calculate_shape_area(&self, shape: &Shape) -> Result<f64, LibraryError> {
+        self.record_call("calculate_shape_area");
+        Ok(match shape.shape_type {
+            // Circles honour canned areas like the other circle methods.
+            ShapeType::Circle => self.area_for(shape.dimension1),
+            _ => shape.area(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // Only `CircleBackend` is imported, since `CircleApi` has methods of the same names.
+    use super::{CircleBackend, MockBackend, MOCK_MULTI_SHOT_COUNT};
+    use crate::error::LibraryError;
+    use crate::Shape;
+
+    // Code under test, written against the trait rather than a concrete library.
+    fn total_area<B: CircleBackend>(backend: &B, radii: &[f64]) -> Result<f64, LibraryError> {
+        radii
+            .iter()
+            .map(|&radius| backend.calculate_circle_area(radius))
+            .sum()
+    }
+
+    #[test]
+    fn mock_stands_in_for_the_library() {
+        let mock = MockBackend::new().with_area(1.0, 10.0);
+        let total = total_area(&mock, &[1.0, 2.0]).unwrap();
+        assert_eq!(total, 10.0 + std::f64::consts::PI * 4.0);
+        assert_eq!(mock.call_count("calculate_circle_area"), 2);
+        assert_eq!(mock.call_count("calculate_shape_area"), 0);
+    }
+
+    #[test]
+    fn mock_validates_like_the_library() {
+        let mock = MockBackend::new();
+        assert!(matches!(
+            total_area(&mock, &[1.0, -1.0]),
+            Err(LibraryError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn mock_counts_callback_invocations() {
+        let mock = MockBackend::new();
+        assert_eq!(mock.call_callback_with(3.0, |val| val * 2.0), 6.0);
+        assert_eq!(mock.call_callback_with(1.0, |val| val + 1.0), 2.0);
+        assert_eq!(mock.callback_invocations(), 2);
+        assert_eq!(
+            mock.format_circle_info(2.0).unwrap(),
+            "Circle with radius 2.00 has area 12.57"
+        );
+    }
+
+    #[tokio::test]
+    async fn mock_async_methods() {
+        let mock = MockBackend::new().with_area(2.0, 5.0);
+        assert_eq!(mock.calculate_circle_area_async(2.0).await.unwrap(), 5.0);
+        let mut rx = mock.calculate_circle_area_async_multi(2.0).unwrap();
+        let mut results = Vec::new();
+        while let Some(area) = rx.recv().await {
+            results.push(area);
+        }
+        assert_eq!(results, [5.0; MOCK_MULTI_SHOT_COUNT]);
+    }
+
+    #[test]
+    fn mock_honours_canned_areas_for_circle_shapes() {
+        let mock = MockBackend::new();
+        mock.set_area(2.0, 7.0);
+        let circle = Shape::circle(2.0).unwrap();
+        assert_eq!(mock.calculate_shape_area(&circle).unwrap(), 7.0);
+        let square = Shape::square(2.0).unwrap();
+        assert_eq!(mock.calculate_shape_area(&square).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn mock_recovers_from_a_poisoned_lock() {
+        let mock = MockBackend::new();
+        std::thread::scope(|scope| {
+            let poisoner = scope.spawn(|| {
+                let _counts = mock.call_counts.lock().unwrap();
+                panic!("poison the call counts");
+            });
+            assert!(poisoner.join().is_err());
+        });
+        assert!(mock.call_counts.is_poisoned());
+        assert_eq!(
+            mock.calculate_circle_area(1.0).unwrap(),
+            std::f64::consts::PI
+        );
+        assert_eq!(mock.call_count("calculate_circle_area"), 1);
+    }
+}
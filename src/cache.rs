@@ -0,0 +1,204 @@
+//! Memoization of circle areas in front of a `CircleBackend`.
+
+use crate::backend::CircleBackend;
+use crate::error::LibraryError;
+use crate::{Circle, CircleLibrary, Shape};
+use lru::LruCache;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tokio::sync::mpsc;
+
+/// Capacity of the cache created by `CachedCircleLibrary::new`.
+pub const DEFAULT_CACHE_CAPACITY: usize = 128;
+
+/// Wraps a `CircleBackend` and caches the areas it computes per radius.
+///
+/// `calculate_circle_area` and `calculate_circle_struct_area` look the radius up in an LRU
+/// cache keyed by its bit pattern before calling the backend, so repeated radii do not
+/// cross the FFI boundary again. Only successful results are cached, and NaN radii are
+/// never cached, since one NaN bit pattern does not identify a single radius. All other
+/// `CircleBackend` methods are passed through unchanged.
+///
+/// ```ignore
+/// let cached = CachedCircleLibrary::new(CircleLibrary::new("lib.dll")?).with_cache_capacity(16);
+/// let area = cached.calculate_circle_area(2.0)?; // calls Go
+/// let again = cached.calculate_circle_area(2.0)?; // served from the cache
+/// ```
+#[derive(Debug)]
+pub struct CachedCircleLibrary<B = CircleLibrary> {
+    backend: B,
+    // `None` when the capacity is zero, which disables caching.
+    areas: Option<Mutex<LruCache<u64, f64>>>,
+}
+
+impl<B: CircleBackend> CachedCircleLibrary<B> {
+    /// Wraps `backend` with a cache of `DEFAULT_CACHE_CAPACITY` areas.
+    pub fn new(backend: B) -> Self {
+        CachedCircleLibrary {
+            backend,
+            areas: None,
+        }
+        .with_cache_capacity(DEFAULT_CACHE_CAPACITY)
+    }
+
+    /// Returns the wrapper with a new, empty cache holding at most `capacity` areas.
+    ///
+    /// Once the cache is full, the least recently used area is evicted. A capacity of zero
+    /// disables caching.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.areas =
+            NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity)));
+        self
+    }
+
+    /// Returns the wrapped backend.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Returns how many areas are currently cached.
+    pub fn cached_len(&self) -> usize {
+        self.areas.as_ref().map_or(0, |areas| lock(areas).len())
+    }
+
+    /// Removes every cached area.
+    pub fn clear_cache(&self) {
+        if let Some(areas) = &self.areas {
+            lock(areas).clear();
+        }
+    }
+
+    /// Returns the cached area for `radius`, computing and caching it on a miss.
+    fn cached_area(
+        &self,
+        radius: f64,
+        compute: impl FnOnce() -> Result<f64, LibraryError>,
+    ) -> Result<f64, LibraryError> {
+        let areas = match &self.areas {
+            Some(areas) if !radius.is_nan() => areas,
+            _ => return compute(),
+        };
+        let key = radius.to_bits();
+        if let Some(&area) = lock(areas).get(&key) {
+            return Ok(area);
+        }
+        // The lock is not held while computing, so concurrent misses may both call the
+        // backend; they compute the same area.
+        let area = compute()?;
+        lock(areas).put(key, area);
+        Ok(area)
+    }
+}
+
+/// Locks the cache, recovering from poisoning; it only ever holds complete entries.
+fn lock(areas: &Mutex<LruCache<u64, f64>>) -> MutexGuard<'_, LruCache<u64, f64>> {
+    areas.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<B: CircleBackend> CircleBackend for CachedCircleLibrary<B> {
+    fn calculate_circle_area(&self, radius: f64) -> Result<f64, LibraryError> {
+        self.cached_area(radius, || self.backend.calculate_circle_area(radius))
+    }
+
+    fn calculate_circle_struct_area(&self, circle: &Circle) -> Result<f64, LibraryError> {
+        self.cached_area(circle.radius, || {
+            self.backend.calculate_circle_struct_area(circle)
+        })
+    }
+
+    fn format_circle_info(&self, radius: f64) -> Result<String, LibraryError> {
+        self.backend.format_circle_info(radius)
+    }
+
+    fn call_callback_with<F>(&self, val: f64, callback: F) -> f64
+    where
+        F: Fn(f64) -> f64 + Send + 'static,
+    {
+        self.backend.call_callback_with(val, callback)
+    }
+
+    fn calculate_circle_area_async(
+        &self,
+        radius: f64,
+    ) -> impl Future<Output = Result<f64, LibraryError>> + Send {
+        self.backend.calculate_circle_area_async(radius)
+    }
+
+    fn calculate_circle_area_async_multi(
+        &self,
+        radius: f64,
+    ) -> Result<mpsc::UnboundedReceiver<f64>, LibraryError> {
+        self.backend.calculate_circle_area_async_multi(radius)
+    }
+
+    fn calculate_shape_area(&self, shape: &Shape) -> Result<f64, LibraryError> {
+        self.backend.calculate_shape_area(shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MockBackend;
+
+    #[test]
+    fn repeated_radius_is_served_from_the_cache() {
+        let cached = CachedCircleLibrary::new(MockBackend::new());
+        let first = cached.calculate_circle_area(2.0).unwrap();
+        let second = cached.calculate_circle_area(2.0).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(cached.backend().call_count("calculate_circle_area"), 1);
+        // The struct variant shares the cache.
+        cached
+            .calculate_circle_struct_area(&Circle { radius: 2.0 })
+            .unwrap();
+        assert_eq!(
+            cached.backend().call_count("calculate_circle_struct_area"),
+            0
+        );
+        assert_eq!(cached.cached_len(), 1);
+    }
+
+    #[test]
+    fn least_recently_used_area_is_evicted() {
+        let cached = CachedCircleLibrary::new(MockBackend::new()).with_cache_capacity(2);
+        for radius in [1.0, 2.0, 1.0, 3.0] {
+            cached.calculate_circle_area(radius).unwrap();
+        }
+        assert_eq!(cached.cached_len(), 2);
+        // 2.0 was the least recently used when 3.0 was added.
+        cached.calculate_circle_area(1.0).unwrap();
+        assert_eq!(cached.backend().call_count("calculate_circle_area"), 3);
+        cached.calculate_circle_area(2.0).unwrap();
+        assert_eq!(cached.backend().call_count("calculate_circle_area"), 4);
+    }
+
+    #[test]
+    fn nan_radius_and_errors_are_never_cached() {
+        let cached = CachedCircleLibrary::new(MockBackend::new());
+        for _ in 0..2 {
+            assert!(cached.calculate_circle_area(f64::NAN).is_err());
+            assert!(cached.calculate_circle_area(-1.0).is_err());
+        }
+        assert_eq!(cached.backend().call_count("calculate_circle_area"), 4);
+        assert_eq!(cached.cached_len(), 0);
+    }
+
+    #[test]
+    fn zero_capacity_disables_caching() {
+        let cached = CachedCircleLibrary::new(MockBackend::new()).with_cache_capacity(0);
+        cached.calculate_circle_area(2.0).unwrap();
+        cached.calculate_circle_area(2.0).unwrap();
+        assert_eq!(cached.backend().call_count("calculate_circle_area"), 2);
+    }
+
+    #[test]
+    fn clear_cache_forces_a_new_call() {
+        let cached = CachedCircleLibrary::new(MockBackend::new());
+        cached.calculate_circle_area(2.0).unwrap();
+        cached.clear_cache();
+        cached.calculate_circle_area(2.0).unwrap();
+        assert_eq!(cached.backend().call_count("calculate_circle_area"), 2);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::error::LibraryError;
+use goblin::mach::{Mach, MachO, SingleArch};
+use goblin::Object;
+
+/// Returns the names of the symbols exported by the shared library in `bytes`, sorted.
+///
+/// ELF, PE and Mach-O libraries are supported. For fat Mach-O binaries the first
+/// architecture is inspected, and the leading underscore Mach-O adds to C names is removed
+/// so names match what `dlsym` expects.
+pub(crate) fn exported_symbols(bytes: &[u8]) -> Result<Vec<String>, LibraryError> {
+    let object = Object::parse(bytes).map_err(parse_error)?;
+    let mut names = match object {
+        Object::Elf(elf) => elf
+            .dynsyms
+            .iter()
+            .filter(|sym| !sym.is_import() && sym.st_bind() != goblin::elf::sym::STB_LOCAL)
+            .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name))
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+            .collect(),
+        Object::PE(pe) => pe
+            .exports
+            .iter()
+            .filter_map(|export| export.name)
+            .map(str::to_owned)
+            .collect(),
+        Object::Mach(Mach::Binary(macho)) => mach_exports(&macho)?,
+        Object::Mach(Mach::Fat(fat)) => match fat.get(0).map_err(parse_error)? {
+            SingleArch::MachO(macho) => mach_exports(&macho)?,
+            SingleArch::Archive(_) => return Err(unsupported("a static archive")),
+        },
+        Object::Archive(_) => return Err(unsupported("a static archive")),
+        _ => return Err(unsupported("an unsupported object format")),
+    };
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+fn mach_exports(macho: &MachO<'_>) -> Result<Vec<String>, LibraryError> {
+    Ok(macho
+        .exports()
+        .map_err(parse_error)?
+        .into_iter()
+        .map(|export| match export.name.strip_prefix('_') {
+            Some(name) => name.to_owned(),
+            None => export.name,
+        })
+        .collect())
+}
+
+fn parse_error(e: goblin::error::Error) -> LibraryError {
+    LibraryError::InvalidInput(format!("cannot parse shared library: {}", e))
+}
+
+fn unsupported(kind: &str) -> LibraryError {
+    LibraryError::InvalidInput(format!("file is {}, not a shared library", kind))
+}
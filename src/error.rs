@@ -0,0 +1,249 @@
+use crate::layout::ByteOrder;
+use std::fmt;
+use std::os::raw::c_int;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Errors that can occur while loading or calling into the Go shared library.
+///
+/// Each variant describes a distinct failure mode so callers can tell, for
+/// example, "library not found" apart from "function not exported".
+#[derive(Debug)]
+pub enum LibraryError {
+    /// The shared library itself could not be loaded.
+    LoadFailed(libloading::Error),
+    /// No library file for the given base name was found in any searched location.
+    NotFound {
+        name: String,
+        searched: Vec<PathBuf>,
+    },
+    /// The library was loaded but does not export the requested symbol.
+    ///
+    /// `source` is `None` when an optional symbol was skipped at load time and the
+    /// error is reported when calling a method that depends on it.
+    SymbolMissing {
+        name: String,
+        source: Option<libloading::Error>,
+    },
+    /// The library reports an ABI version this crate is not compatible with.
+    AbiMismatch { expected: i32, found: i32 },
+    /// The library reports a different size for a shared struct than the Rust definition.
+    LayoutMismatch {
+        type_name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The library was built for a different byte order than this process.
+    ByteOrderMismatch {
+        expected: ByteOrder,
+        found: ByteOrder,
+    },
+    /// A library function returned a null pointer where a value was expected.
+    NullPointer { function: String },
+    /// A file needed to load the library could not be written.
+    Io(std::io::Error),
+    /// A string returned by the library was not valid UTF-8.
+    Utf8(std::str::Utf8Error),
+    /// A string argument contained an interior NUL byte and cannot be passed as a C string.
+    InteriorNul(std::ffi::NulError),
+    /// An argument was rejected before crossing the FFI boundary.
+    InvalidInput(String),
+    /// An integer operation overflowed and the library's result wrapped around.
+    Overflow { operation: String },
+    /// The Go library rejected a call and reported why through an error code.
+    Go(GoErrorCode),
+    /// An asynchronous call did not complete within the given duration.
+    Timeout(Duration),
+    /// An asynchronous callback's sender was dropped without delivering a result.
+    CallbackDropped,
+    /// A call offloaded to Tokio's blocking thread pool was cancelled before it completed,
+    /// e.g. because the runtime shut down before the call ran.
+    TaskCancelled,
+    /// Shared libraries cannot be loaded dynamically on this platform.
+    Unsupported,
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::LoadFailed(e) => write!(f, "failed to load library: {}", e),
+            LibraryError::NotFound { name, searched } => {
+                write!(f, "library `{}` not found; searched:", name)?;
+                for path in searched {
+                    write!(f, " {}", path.display())?;
+                }
+                Ok(())
+            }
+            LibraryError::SymbolMissing { name, .. } => {
+                write!(f, "symbol `{}` is not exported by the library", name)
+            }
+            LibraryError::AbiMismatch { expected, found } => write!(
+                f,
+                "library ABI version {} does not match expected version {}",
+                found, expected
+            ),
+            LibraryError::LayoutMismatch {
+                type_name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "library reports `{}` as {} bytes, expected {}",
+                type_name, found, expected
+            ),
+            LibraryError::ByteOrderMismatch { expected, found } => {
+                write!(f, "library is {}, but this process is {}", found, expected)
+            }
+            LibraryError::NullPointer { function } => {
+                write!(f, "`{}` returned a null pointer", function)
+            }
+            LibraryError::Io(e) => write!(f, "I/O error: {}", e),
+            LibraryError::Utf8(e) => write!(f, "library returned invalid UTF-8: {}", e),
+            LibraryError::InteriorNul(e) => write!(f, "string contains a NUL byte: {}", e),
+            LibraryError::InvalidInput(reason) => write!(f, "invalid input: {}", reason),
+            LibraryError::Overflow { operation } => write!(f, "`{}` overflowed", operation),
+            LibraryError::Go(code) => write!(f, "Go library reported an error: {}", code),
+            LibraryError::Timeout(dur) => write!(f, "call did not complete within {:?}", dur),
+            LibraryError::CallbackDropped => {
+                write!(f, "callback was dropped without delivering a result")
+            }
+            LibraryError::TaskCancelled => {
+                write!(f, "blocking task was cancelled before it completed")
+            }
+            LibraryError::Unsupported => write!(
+                f,
+                "dynamic library loading is not supported on this platform"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LibraryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LibraryError::LoadFailed(e) => Some(e),
+            LibraryError::SymbolMissing { source, .. } => source
+                .as_ref()
+                .map(|e| e as &(dyn std::error::Error + 'static)),
+            LibraryError::NotFound { .. }
+            | LibraryError::AbiMismatch { .. }
+            | LibraryError::LayoutMismatch { .. }
+            | LibraryError::ByteOrderMismatch { .. }
+            | LibraryError::NullPointer { .. }
+            | LibraryError::InvalidInput(_)
+            | LibraryError::Overflow { .. }
+            | LibraryError::Go(_)
+            | LibraryError::Timeout(_)
+            | LibraryError::CallbackDropped
+            | LibraryError::TaskCancelled
+            | LibraryError::Unsupported => None,
+            LibraryError::Io(e) => Some(e),
+            LibraryError::Utf8(e) => Some(e),
+            LibraryError::InteriorNul(e) => Some(e),
+        }
+    }
+}
+
+impl From<libloading::Error> for LibraryError {
+    fn from(e: libloading::Error) -> Self {
+        LibraryError::LoadFailed(e)
+    }
+}
+
+impl From<std::io::Error> for LibraryError {
+    fn from(e: std::io::Error) -> Self {
+        LibraryError::Io(e)
+    }
+}
+
+impl From<std::str::Utf8Error> for LibraryError {
+    fn from(e: std::str::Utf8Error) -> Self {
+        LibraryError::Utf8(e)
+    }
+}
+
+impl From<std::ffi::NulError> for LibraryError {
+    fn from(e: std::ffi::NulError) -> Self {
+        LibraryError::InteriorNul(e)
+    }
+}
+
+/// An error code written by a Go export through its error-code out-parameter.
+///
+/// Zero means success and is never represented here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoErrorCode {
+    /// The shape type is not one Go knows about.
+    UnknownShapeType,
+    /// A dimension is negative, NaN or infinite.
+    InvalidDimension,
+    /// The shape is degenerate, e.g. a triangle with zero height.
+    DegenerateShape,
+    /// A code this crate does not know about, e.g. from a newer library.
+    Other(c_int),
+}
+
+impl GoErrorCode {
+    /// Returns the error for a non-zero code, or `None` for zero (success).
+    pub fn from_code(code: c_int) -> Option<GoErrorCode> {
+        match code {
+            0 => None,
+            1 => Some(GoErrorCode::UnknownShapeType),
+            2 => Some(GoErrorCode::InvalidDimension),
+            3 => Some(GoErrorCode::DegenerateShape),
+            other => Some(GoErrorCode::Other(other)),
+        }
+    }
+
+    /// Returns the raw code Go reported.
+    pub fn code(self) -> c_int {
+        match self {
+            GoErrorCode::UnknownShapeType => 1,
+            GoErrorCode::InvalidDimension => 2,
+            GoErrorCode::DegenerateShape => 3,
+            GoErrorCode::Other(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for GoErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoErrorCode::UnknownShapeType => write!(f, "unknown shape type"),
+            GoErrorCode::InvalidDimension => write!(f, "invalid dimension"),
+            GoErrorCode::DegenerateShape => write!(f, "degenerate shape"),
+            GoErrorCode::Other(code) => write!(f, "error code {}", code),
+        }
+    }
+}
+
+impl std::error::Error for GoErrorCode {}
+
+/// Error returned by a fallible callback closure, e.g. one passed to
+/// `CircleLibrary::call_callback_try_with`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallbackError {
+    message: String,
+}
+
+impl CallbackError {
+    /// Creates a callback error with the given message.
+    pub fn new(message: impl Into<String>) -> Self {
+        CallbackError {
+            message: message.into(),
+        }
+    }
+
+    /// Returns the message describing the failure.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for CallbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "callback failed: {}", self.message)
+    }
+}
+
+impl std::error::Error for CallbackError {}
@@ -0,0 +1,56 @@
+//! Error handlers registered through `CircleLibrary::on_error`.
+//!
+//! Go may report errors from any goroutine, and therefore from any thread, so handlers
+//! cannot live in thread-local storage. Each library registers its handler here under its
+//! owner id and passes only that id to Go as user data; the trampoline looks it up on
+//! every report. The entry is removed by the owner's `HandlerGuard` once the last clone of
+//! the library is dropped, so reports arriving after that are ignored.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// A handler receiving the messages of errors reported by Go.
+pub(crate) type ErrorHandler = Arc<dyn Fn(&str) + Send + Sync>;
+
+lazy_static! {
+    static ref ERROR_HANDLERS: Mutex<HashMap<usize, ErrorHandler>> = Mutex::new(HashMap::new());
+}
+
+/// Locks the handler map, recovering from poisoning so a panic elsewhere cannot stop
+/// errors from being reported.
+fn handlers() -> MutexGuard<'static, HashMap<usize, ErrorHandler>> {
+    ERROR_HANDLERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Registers `handler` for `owner`, replacing any previous one.
+pub(crate) fn set(owner: usize, handler: ErrorHandler) {
+    handlers().insert(owner, handler);
+}
+
+/// Returns the handler registered for `owner`, if any.
+pub(crate) fn get(owner: usize) -> Option<ErrorHandler> {
+    handlers().get(&owner).cloned()
+}
+
+/// Removes the handler registered for `owner` when dropped.
+///
+/// Shared by every clone of a `CircleLibrary`, so the handler lives exactly as long as the
+/// library that registered it.
+pub(crate) struct HandlerGuard {
+    owner: usize,
+}
+
+impl HandlerGuard {
+    pub(crate) fn new(owner: usize) -> Self {
+        HandlerGuard { owner }
+    }
+}
+
+impl Drop for HandlerGuard {
+    fn drop(&mut self) {
+        handlers().remove(&self.owner);
+    }
+}
@@ -0,0 +1,437 @@
+use crate::error::LibraryError;
+use crate::layout::GoUintptr;
+use crate::loader::{get_optional_symbol, get_symbol, require_symbol, RuntimeGuard};
+use libloading::{Library, Symbol};
+use std::fmt;
+use std::os::raw::{c_double, c_int};
+use std::sync::{mpsc as std_mpsc, Arc, Mutex, OnceLock, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+
+/// A value that a Go generator can produce through its `next` export.
+///
+/// `Raw` is the C type the export writes through its out-parameter, and `from_ffi`
+/// converts it into the Rust value.
+pub trait FromFfi: Sized {
+    /// The C type written by the generator's `next` export.
+    type Raw: Copy + Default + Send + 'static;
+
+    /// Converts a value produced by Go into its Rust representation.
+    fn from_ffi(raw: Self::Raw) -> Self;
+}
+
+impl FromFfi for i32 {
+    type Raw = c_int;
+
+    fn from_ffi(raw: c_int) -> Self {
+        raw
+    }
+}
+
+impl FromFfi for f64 {
+    type Raw = c_double;
+
+    fn from_ffi(raw: c_double) -> Self {
+        raw
+    }
+}
+
+/// The id of a Go generator, a Go `uintptr` in the generator exports.
+///
+/// Ids have the width of a pointer, so they are 64 bits on 64-bit targets and 32 bits on
+/// 32-bit ones, matching the `GoUintptr` cgo declares for the exports.
+pub type GeneratorId = GoUintptr;
+
+/// The value and validity flag returned together by a generator's `next_result` export.
+///
+/// This is the recommended way to return several values from Go: declare a C struct in
+/// the cgo preamble and return it by value. The struct cgo generates for a multi-value Go
+/// return has no declaration Rust can match, whereas this one has a fixed `#[repr(C)]`
+/// layout that is also checked through `SizeOfNextResult` when the library is loaded.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NextResult<R> {
+    pub value: R,
+    // A byte rather than a `bool`, like every flag crossing the boundary.
+    pub ok: u8,
+}
+
+/// The names of the exports backing a Go channel generator.
+///
+/// `create` takes no arguments and returns a generator id. `next_result`, if set, takes the
+/// id and returns a `NextResult`; otherwise `next` takes the id and an out-parameter,
+/// returning whether a value was written. `stop` and `free` take the id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneratorSymbols {
+    pub create: &'static str,
+    pub next_result: Option<&'static str>,
+    pub next: &'static str,
+    pub stop: &'static str,
+    pub free: &'static str,
+}
+
+impl GeneratorSymbols {
+    /// The exports of the Go number generator.
+    pub const NUMBERS: GeneratorSymbols = GeneratorSymbols {
+        create: "CreateNumberGenerator",
+        next_result: Some("GetNextNumber2"),
+        next: "GetNextNumber",
+        stop: "StopNumberGenerator",
+        free: "FreeNumberGenerator",
+    };
+}
+
+/// A safe wrapper around a Go channel-based generator producing values of type `T`.
+///
+/// The generator holds its own reference to the loaded library and resolves the generator
+/// symbols once on creation, so it can be moved across threads and outlive the
+/// `CircleLibrary` it was created from.
+pub struct GoGenerator<T: FromFfi> {
+    id: GeneratorId,
+    symbols: GeneratorSymbols,
+    // Frees the Go generator once the generator and every pull still running on a helper
+    // thread are done with it. Also keeps the library, and therefore the function
+    // pointers below, valid for as long as the generator exists.
+    handle: Arc<GeneratorHandle>,
+    // The `next_result` export returns the value and whether it was produced in a
+    // `NextResult`; it is preferred when the library exports it.
+    get_next_result: Option<unsafe extern "C" fn(GeneratorId) -> NextResult<T::Raw>>,
+    // The older `next` export returns whether a value was produced and writes the value
+    // through an out-parameter. The flag is a byte rather than a `bool`, so a value other
+    // than 0 or 1 is not undefined behavior.
+    get_next: Option<unsafe extern "C" fn(GeneratorId, *mut T::Raw) -> u8>,
+    stop: Option<unsafe extern "C" fn(GeneratorId)>,
+    // Started by the first `next_timeout` call and reused by every later one.
+    timeout_worker: OnceLock<TimeoutWorker<T::Raw>>,
+}
+
+/// The Go side of a generator, freed through the `free` export once the last reference
+/// is dropped.
+///
+/// Shared with the worker thread of `GoGenerator::next_timeout`, so a pull that is still
+/// blocked in Go after a timeout never sees its generator freed underneath it.
+struct GeneratorHandle {
+    id: GeneratorId,
+    free: Option<unsafe extern "C" fn(GeneratorId)>,
+    // Keeps the library loaded for as long as the generator exists.
+    _lib: Arc<Library>,
+    // Delays `ShutdownLibrary` until the generator is gone, if created by a `CircleLibrary`.
+    _runtime: Option<Arc<RuntimeGuard>>,
+}
+
+impl Drop for GeneratorHandle {
+    fn drop(&mut self) {
+        if let Some(free_generator) = self.free {
+            unsafe {
+                free_generator(self.id);
+            }
+        }
+    }
+}
+
+/// One of the two exports a generator can pull values through.
+#[derive(Clone, Copy)]
+enum NextFn<R> {
+    Result(unsafe extern "C" fn(GeneratorId) -> NextResult<R>),
+    OutParam(unsafe extern "C" fn(GeneratorId, *mut R) -> u8),
+}
+
+impl<R: Copy + Default> NextFn<R> {
+    /// Pulls the next raw value of generator `id`, or `None` once it is exhausted.
+    ///
+    /// # Safety
+    /// The library the export was resolved from must still be loaded.
+    unsafe fn call(self, id: GeneratorId) -> Option<R> {
+        match self {
+            NextFn::Result(get_next_result) => {
+                let result = get_next_result(id);
+                (result.ok != 0).then_some(result.value)
+            }
+            NextFn::OutParam(get_next) => {
+                let mut raw = R::default();
+                (get_next(id, &mut raw) != 0).then_some(raw)
+            }
+        }
+    }
+}
+
+/// The thread `GoGenerator::next_timeout` pulls values on.
+///
+/// The thread pulls one value per request and exits once the generator drops the request
+/// sender, after any pull still blocked in Go has returned.
+struct TimeoutWorker<R> {
+    requests: std_mpsc::Sender<()>,
+    state: Mutex<TimeoutState<R>>,
+}
+
+struct TimeoutState<R> {
+    results: std_mpsc::Receiver<Option<R>>,
+    // Whether a requested value has not been received yet, because the call that
+    // requested it timed out.
+    outstanding: bool,
+}
+
+impl<R: Copy + Default + Send + 'static> TimeoutWorker<R> {
+    fn spawn(next_fn: NextFn<R>, handle: Arc<GeneratorHandle>) -> Self {
+        let (requests, request_rx) = std_mpsc::channel();
+        let (result_tx, results) = std_mpsc::channel();
+        std::thread::spawn(move || {
+            // Holding the handle keeps the generator from being freed until Go returns,
+            // even if nobody waits any more.
+            while request_rx.recv().is_ok() {
+                if result_tx.send(unsafe { next_fn.call(handle.id) }).is_err() {
+                    break;
+                }
+            }
+        });
+        TimeoutWorker {
+            requests,
+            state: Mutex::new(TimeoutState {
+                results,
+                outstanding: false,
+            }),
+        }
+    }
+}
+
+/// A safe wrapper around the Go number generator.
+pub type NumberGenerator = GoGenerator<i32>;
+
+impl<T: FromFfi> fmt::Debug for GoGenerator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GoGenerator")
+            .field("id", &self.id)
+            .field("create", &self.symbols.create)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: FromFfi> fmt::Display for GoGenerator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "generator {} from `{}`", self.id, self.symbols.create)
+    }
+}
+
+impl NumberGenerator {
+    pub fn new(lib: &Arc<Library>) -> Result<Self, LibraryError> {
+        Self::with_symbols(lib, GeneratorSymbols::NUMBERS)
+    }
+
+    /// Like `new`, but keeps the Go runtime initialized for as long as the generator exists.
+    pub(crate) fn with_runtime(
+        lib: &Arc<Library>,
+        runtime: Arc<RuntimeGuard>,
+    ) -> Result<Self, LibraryError> {
+        Self::create(lib, GeneratorSymbols::NUMBERS, Some(runtime))
+    }
+}
+
+impl<T: FromFfi> GoGenerator<T> {
+    /// Creates a generator through the exports named in `symbols`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the `create` export is missing. The other
+    /// exports are only needed later and are reported missing when they are used.
+    pub fn with_symbols(
+        lib: &Arc<Library>,
+        symbols: GeneratorSymbols,
+    ) -> Result<Self, LibraryError> {
+        Self::create(lib, symbols, None)
+    }
+
+    fn create(
+        lib: &Arc<Library>,
+        symbols: GeneratorSymbols,
+        runtime: Option<Arc<RuntimeGuard>>,
+    ) -> Result<Self, LibraryError> {
+        unsafe {
+            let create_generator: Symbol<unsafe extern "C" fn() -> GeneratorId> =
+                get_symbol(lib, symbols.create)?;
+            // The remaining symbols are only needed later; report them missing at call time.
+            let get_next_result: Option<
+                Symbol<unsafe extern "C" fn(GeneratorId) -> NextResult<T::Raw>>,
+            > = match symbols.next_result {
+                Some(name) => get_optional_symbol(lib, name, true)?,
+                None => None,
+            };
+            let get_next: Option<Symbol<unsafe extern "C" fn(GeneratorId, *mut T::Raw) -> u8>> =
+                get_optional_symbol(lib, symbols.next, true)?;
+            let stop: Option<Symbol<unsafe extern "C" fn(GeneratorId)>> =
+                get_optional_symbol(lib, symbols.stop, true)?;
+            let free: Option<Symbol<unsafe extern "C" fn(GeneratorId)>> =
+                get_optional_symbol(lib, symbols.free, true)?;
+            let id = create_generator();
+            Ok(GoGenerator {
+                id,
+                symbols,
+                get_next_result: get_next_result.map(|s| *s),
+                get_next: get_next.map(|s| *s),
+                stop: stop.map(|s| *s),
+                timeout_worker: OnceLock::new(),
+                handle: Arc::new(GeneratorHandle {
+                    id,
+                    free: free.map(|s| *s),
+                    _lib: Arc::clone(lib),
+                    _runtime: runtime,
+                }),
+            })
+        }
+    }
+
+    pub fn next(&self) -> Result<Option<T>, LibraryError> {
+        let next_fn = self.next_fn()?;
+        Ok(unsafe { next_fn.call(self.id) }.map(T::from_ffi))
+    }
+
+    /// Returns the export used to pull values, preferring `next_result` over `next`.
+    fn next_fn(&self) -> Result<NextFn<T::Raw>, LibraryError> {
+        match self.get_next_result {
+            Some(get_next_result) => Ok(NextFn::Result(get_next_result)),
+            None => require_symbol(self.get_next, self.symbols.next).map(NextFn::OutParam),
+        }
+    }
+
+    /// Like `next`, but gives up if Go has not produced a value within `dur`.
+    ///
+    /// The `next` export blocks while the Go channel is empty, and a blocked FFI call
+    /// cannot be cancelled. The call therefore runs on a worker thread, started by the
+    /// first call and shared by every later one, that this method stops waiting for after
+    /// `dur`. On timeout the worker stays blocked until Go returns, and the next call waits
+    /// for that same pull instead of starting another one, so a value Go produces after a
+    /// timeout is returned by the next call rather than lost. Concurrent calls take turns,
+    /// so a call can wait longer than `dur` while another one is waiting. The worker
+    /// shares the generator's handle, so dropping the generator in the meantime only frees
+    /// it on the Go side once the blocked call has returned.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Timeout` if no value arrives within `dur`, or
+    /// `LibraryError::SymbolMissing` if the `next` export is missing.
+    pub fn next_timeout(&self, dur: Duration) -> Result<Option<T>, LibraryError> {
+        let next_fn = self.next_fn()?;
+        let worker = self
+            .timeout_worker
+            .get_or_init(|| TimeoutWorker::spawn(next_fn, Arc::clone(&self.handle)));
+        let mut state = worker.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if !state.outstanding {
+            // The worker only exits once `requests` is dropped, so this cannot fail.
+            let _ = worker.requests.send(());
+            state.outstanding = true;
+        }
+        match state.results.recv_timeout(dur) {
+            Ok(raw) => {
+                state.outstanding = false;
+                Ok(raw.map(T::from_ffi))
+            }
+            Err(_) => Err(LibraryError::Timeout(dur)),
+        }
+    }
+
+    /// Pulls the next value from the generator, distinguishing a clean end from a failure.
+    ///
+    /// Unlike `next`, which reports both as part of a `Result<Option<_>, _>`, this returns
+    /// `GeneratorState::Exhausted` once the generator has finished and
+    /// `GeneratorState::Error` if the call itself failed, e.g. because the `next` export
+    /// is missing.
+    pub fn next_state(&self) -> GeneratorState<T> {
+        match self.next() {
+            Ok(Some(value)) => GeneratorState::Value(value),
+            Ok(None) => GeneratorState::Exhausted,
+            Err(e) => GeneratorState::Error(e),
+        }
+    }
+
+    /// Returns an iterator that pulls values from the generator.
+    ///
+    /// The iterator yields `Ok(value)` until the generator is exhausted. If a call fails,
+    /// the error is yielded once and the iterator then ends, so results can be collected
+    /// with `collect::<Result<Vec<_>, _>>()`.
+    pub fn iter(&self) -> GoGeneratorIter<'_, T> {
+        GoGeneratorIter {
+            generator: self,
+            done: false,
+        }
+    }
+
+    /// Turns the generator into a `Stream` of values.
+    ///
+    /// The `next` export blocks until Go produces the next value, so the generator is
+    /// polled on tokio's blocking thread pool and each value is forwarded through a
+    /// channel of capacity one. The stream ends when the generator is exhausted or a call
+    /// fails. Once the stream is dropped the blocking task stops after at most one more
+    /// pull and drops the generator, freeing it on the Go side.
+    ///
+    /// # Panics
+    /// Panics if called outside a tokio runtime.
+    pub fn into_stream(self) -> impl Stream<Item = T>
+    where
+        T: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(1);
+        tokio::task::spawn_blocking(move || {
+            while !tx.is_closed() {
+                match self.next() {
+                    Ok(Some(value)) => {
+                        if tx.blocking_send(value).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            // `self` is dropped here, which frees the Go generator.
+        });
+        ReceiverStream::new(rx)
+    }
+
+    pub fn stop(&self) -> Result<(), LibraryError> {
+        let stop_generator = require_symbol(self.stop, self.symbols.stop)?;
+        unsafe {
+            stop_generator(self.id);
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of pulling from a `GoGenerator`, returned by `GoGenerator::next_state`.
+#[derive(Debug)]
+pub enum GeneratorState<T = i32> {
+    /// The generator produced a value.
+    Value(T),
+    /// The generator has finished and will produce no more values.
+    Exhausted,
+    /// Pulling from the generator failed.
+    Error(LibraryError),
+}
+
+/// Iterator over the values produced by a `GoGenerator`, created by `GoGenerator::iter`.
+pub struct GoGeneratorIter<'a, T: FromFfi> {
+    generator: &'a GoGenerator<T>,
+    done: bool,
+}
+
+/// Iterator over the numbers produced by a `NumberGenerator`.
+pub type NumberGeneratorIter<'a> = GoGeneratorIter<'a, i32>;
+
+impl<T: FromFfi> Iterator for GoGeneratorIter<'_, T> {
+    type Item = Result<T, LibraryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.generator.next() {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                // Surface the error once, then stop.
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
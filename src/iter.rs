@@ -0,0 +1,86 @@
+//! Iterator adapters computing areas through a `CircleBackend`.
+
+use crate::backend::CircleBackend;
+use crate::error::LibraryError;
+
+/// Extends iterators of radii with `circle_areas`, for pipelines such as
+///
+/// ```ignore
+/// let total: f64 = (1..=10)
+///     .map(f64::from)
+///     .circle_areas(&circle_lib)
+///     .sum::<Result<f64, _>>()?;
+/// ```
+pub trait CircleAreaExt: Iterator<Item = f64> + Sized {
+    /// Returns an iterator over the areas of circles with these radii.
+    ///
+    /// Nothing is buffered: each area is computed by `backend` only when the adapter is
+    /// advanced, with the same validation as `CircleBackend::calculate_circle_area`.
+    fn circle_areas<B: CircleBackend>(self, backend: &B) -> CircleAreas<'_, Self, B> {
+        CircleAreas {
+            radii: self,
+            backend,
+        }
+    }
+}
+
+impl<I: Iterator<Item = f64>> CircleAreaExt for I {}
+
+/// Iterator returned by `CircleAreaExt::circle_areas`.
+#[derive(Debug, Clone)]
+pub struct CircleAreas<'a, I, B> {
+    radii: I,
+    backend: &'a B,
+}
+
+impl<I, B> Iterator for CircleAreas<'_, I, B>
+where
+    I: Iterator<Item = f64>,
+    B: CircleBackend,
+{
+    type Item = Result<f64, LibraryError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let radius = self.radii.next()?;
+        Some(self.backend.calculate_circle_area(radius))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.radii.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MockBackend;
+
+    #[test]
+    fn sum_matches_a_manual_loop() {
+        let mock = MockBackend::new();
+        let total = (1..=10)
+            .map(f64::from)
+            .circle_areas(&mock)
+            .sum::<Result<f64, _>>()
+            .unwrap();
+        let mut expected = 0.0;
+        for radius in 1..=10 {
+            expected += mock.calculate_circle_area(f64::from(radius)).unwrap();
+        }
+        assert_eq!(total, expected);
+    }
+
+    #[test]
+    fn areas_are_computed_lazily() {
+        let mock = MockBackend::new();
+        let mut areas = [1.0, -1.0, 2.0].into_iter().circle_areas(&mock);
+        assert_eq!(areas.size_hint(), (3, Some(3)));
+        assert_eq!(mock.call_count("calculate_circle_area"), 0);
+        assert!(areas.next().unwrap().is_ok());
+        assert!(matches!(
+            areas.next(),
+            Some(Err(LibraryError::InvalidInput(_)))
+        ));
+        assert_eq!(mock.call_count("calculate_circle_area"), 2);
+    }
+}
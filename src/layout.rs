@@ -0,0 +1,151 @@
+use crate::error::LibraryError;
+use crate::generator::NextResult;
+use crate::loader::get_optional_symbol;
+use crate::{AreaMeta, Circle, CircleDetails, Shape};
+use libloading::{Library, Symbol};
+use std::fmt;
+use std::mem::size_of;
+use std::os::raw::{c_int, c_void};
+
+/// Size in bytes of the C `Shape` struct: a 4-byte enum, 4 bytes of padding and two doubles.
+pub const EXPECTED_SHAPE_SIZE: usize = 24;
+/// Size in bytes of the C `Circle` struct: a single double.
+pub const EXPECTED_CIRCLE_SIZE: usize = 8;
+/// Size in bytes of the C `CircleDetails` struct: three doubles.
+pub const EXPECTED_CIRCLE_DETAILS_SIZE: usize = 24;
+/// Size in bytes of the C `AreaMeta` struct: two ints.
+pub const EXPECTED_AREA_META_SIZE: usize = 8;
+/// Size in bytes of the C `NextResult` struct: an int, a byte and 3 bytes of padding.
+pub const EXPECTED_NEXT_RESULT_SIZE: usize = 8;
+
+/// Go's `int`, exported by cgo as `GoInt`. Its width is that of a pointer on every Go target.
+///
+/// The Go exports take slice lengths as a Go `int`.
+pub type GoInt = isize;
+/// Go's `uintptr`, exported by cgo as `GoUintptr`.
+///
+/// The Go exports identify generators by a `uintptr`; see `generator::GeneratorId`.
+pub type GoUintptr = usize;
+
+// Ids are passed to Go as user data pointers and read back as `usize`, and `GoInt` and
+// `GoUintptr` rely on `isize`/`usize` having the width of a pointer.
+const _: () = assert!(size_of::<usize>() == size_of::<*mut c_void>());
+
+/// Converts a slice length to the `GoInt` the Go exports take lengths as.
+///
+/// A slice never spans more than `isize::MAX` bytes, so its length always fits; larger
+/// counts, which only `generate_shapes` accepts, are clamped to `GoInt::MAX`.
+pub(crate) fn go_len(len: usize) -> GoInt {
+    GoInt::try_from(len).unwrap_or(GoInt::MAX)
+}
+
+// Fail the build if a Rust struct no longer matches the C layout it is passed as.
+const _: () = assert!(size_of::<Shape>() == EXPECTED_SHAPE_SIZE);
+const _: () = assert!(size_of::<Circle>() == EXPECTED_CIRCLE_SIZE);
+const _: () = assert!(size_of::<CircleDetails>() == EXPECTED_CIRCLE_DETAILS_SIZE);
+const _: () = assert!(size_of::<AreaMeta>() == EXPECTED_AREA_META_SIZE);
+const _: () = assert!(size_of::<NextResult<c_int>>() == EXPECTED_NEXT_RESULT_SIZE);
+
+/// The order in which the bytes of multi-byte values are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    /// Returns the byte order of the target this crate was compiled for.
+    pub fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            ByteOrder::Big
+        } else {
+            ByteOrder::Little
+        }
+    }
+
+    /// Decodes the value returned by the Go `NativeByteOrder` export.
+    fn from_code(code: c_int) -> Option<Self> {
+        match code {
+            0 => Some(ByteOrder::Little),
+            1 => Some(ByteOrder::Big),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ByteOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ByteOrder::Little => write!(f, "little-endian"),
+            ByteOrder::Big => write!(f, "big-endian"),
+        }
+    }
+}
+
+/// Compares the byte order and struct sizes reported by the library against the Rust side.
+///
+/// A library built for the other byte order cannot share structs or scalars with this
+/// process at all, so a mismatch is reported rather than worked around by swapping bytes.
+/// Each of the exports is optional; properties the library does not report are not
+/// checked.
+pub(crate) fn check_struct_layouts(lib: &Library) -> Result<(), LibraryError> {
+    check_byte_order(lib)?;
+    check_size(lib, "SizeOfShape", "Shape", size_of::<Shape>())?;
+    check_size(lib, "SizeOfCircle", "Circle", size_of::<Circle>())?;
+    check_size(
+        lib,
+        "SizeOfCircleDetails",
+        "CircleDetails",
+        size_of::<CircleDetails>(),
+    )?;
+    check_size(lib, "SizeOfAreaMeta", "AreaMeta", size_of::<AreaMeta>())?;
+    check_size(
+        lib,
+        "SizeOfNextResult",
+        "NextResult",
+        size_of::<NextResult<c_int>>(),
+    )
+}
+
+fn check_byte_order(lib: &Library) -> Result<(), LibraryError> {
+    let native_byte_order: Option<Symbol<unsafe extern "C" fn() -> c_int>> =
+        unsafe { get_optional_symbol(lib, "NativeByteOrder", true)? };
+    let Some(native_byte_order) = native_byte_order else {
+        return Ok(());
+    };
+    let code = unsafe { native_byte_order() };
+    let expected = ByteOrder::native();
+    match ByteOrder::from_code(code) {
+        Some(found) if found == expected => Ok(()),
+        Some(found) => Err(LibraryError::ByteOrderMismatch { expected, found }),
+        None => Err(LibraryError::InvalidInput(format!(
+            "library reports unknown byte order code {}",
+            code
+        ))),
+    }
+}
+
+fn check_size(
+    lib: &Library,
+    symbol: &str,
+    type_name: &str,
+    expected: usize,
+) -> Result<(), LibraryError> {
+    let size_of_type: Option<Symbol<unsafe extern "C" fn() -> usize>> =
+        unsafe { get_optional_symbol(lib, symbol, true)? };
+    match size_of_type {
+        Some(size_of_type) => {
+            let found = unsafe { size_of_type() };
+            if found == expected {
+                Ok(())
+            } else {
+                Err(LibraryError::LayoutMismatch {
+                    type_name: type_name.to_string(),
+                    expected,
+                    found,
+                })
+            }
+        }
+        None => Ok(()),
+    }
+}
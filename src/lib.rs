@@ -0,0 +1,69 @@
+//! Safe Rust bindings for the Go circle library, loaded at runtime as a shared library.
+//!
+//! The `std` feature (enabled by default) provides `CircleLibrary` and everything else that
+//! loads or calls the Go library. Without it the crate is `no_std` and only offers the
+//! shared shape types and their pure Rust area computations from `shapes`;
+//! `cargo test --lib --no-default-features` verifies that they still build and work against
+//! `core`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+#[macro_use]
+mod macros;
+#[cfg(feature = "std")]
+pub mod backend;
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
+mod error_handlers;
+#[cfg(feature = "std")]
+pub mod generator;
+#[cfg(feature = "std")]
+pub mod iter;
+#[cfg(feature = "std")]
+pub mod layout;
+#[cfg(feature = "std")]
+mod library;
+#[cfg(feature = "std")]
+pub mod live_circle;
+#[cfg(feature = "std")]
+mod loader;
+#[cfg(feature = "std")]
+pub mod manager;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "std")]
+mod pending;
+pub mod prelude;
+#[cfg(feature = "std")]
+pub mod shape_builder;
+pub mod shapes;
+#[cfg(feature = "std")]
+pub mod user_data;
+
+#[cfg(feature = "cache")]
+pub use cache::CachedCircleLibrary;
+#[cfg(feature = "std")]
+pub use generator::{GeneratorId, GeneratorState, NumberGenerator, NumberGeneratorIter};
+#[cfg(feature = "std")]
+pub use iter::CircleAreaExt;
+#[cfg(feature = "std")]
+pub use library::{
+    AreaMeta, CallbackType, Capabilities, CircleDetails, CircleLibrary, CircleLibraryBuilder,
+    DroppedResults, GoString, IntCallbackType, MultiShotHandle, PollStream, RawSymbols,
+    StringOwnership, EXPECTED_ABI_VERSION, SHUTDOWN_TIMEOUT,
+};
+#[cfg(feature = "std")]
+pub use live_circle::LiveCircle;
+#[cfg(feature = "std")]
+pub use loader::LoadFlags;
+#[cfg(feature = "std")]
+pub use shape_builder::ShapeBuilder;
+pub use shapes::{Circle, Shape, ShapeArea, ShapeType};
+#[cfg(feature = "std")]
+pub use user_data::UserData;
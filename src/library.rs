@@ -0,0 +1,3208 @@
+//! `CircleLibrary` and the other types that load and call the Go shared library.
+//!
+//! # Unwinding
+//!
+//! Every FFI function type here uses the plain `"C"` ABI, and none of them may unwind:
+//!
+//! - Callbacks handed to Go catch Rust panics before returning, either resuming them once
+//!   the Go call has returned or (for callbacks invoked from goroutines) discarding them.
+//!   A panic that escaped anyway would abort the process rather than unwind into Go.
+//! - Go exports cannot unwind into Rust either. An unwind cannot pass through Go stack
+//!   frames, so a C++ exception thrown by code the Go library links against terminates
+//!   the process before it reaches Rust, whatever ABI Rust declares.
+//!
+//! `"C-unwind"` would therefore add unwinding tables without making any call safer, and
+//! is deliberately not used.
+
+#[cfg(feature = "diagnostics")]
+use crate::diagnostics;
+use crate::error::{CallbackError, GoErrorCode, LibraryError};
+use crate::generator::NumberGenerator;
+use crate::layout::{go_len, GoInt};
+use crate::loader::{
+    check_abi_version, get_optional_symbol, get_symbol, library_search_paths, open_library,
+    require_symbol, LoadFlags, RuntimeGuard, TempLibraryFile,
+};
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::shapes::{validate_dimension, Circle, Shape, ShapeArea, ShapeType};
+use crate::user_data::UserData;
+use crate::{error_handlers, layout, loader, pending};
+use libloading::{Library, Symbol};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::future::Future;
+use std::ops::Deref;
+use std::os::raw::{c_char, c_double, c_float, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::Stream;
+
+/// ABI version of the Go library this crate was written against.
+///
+/// Bump this together with `abiVersion` in `main.go` whenever a shared struct layout or
+/// exported function signature changes incompatibly.
+pub const EXPECTED_ABI_VERSION: c_int = 2;
+
+/// How long `CircleLibrary::shutdown` waits for pending async calls to complete.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How often `CircleLibrary::shutdown` checks whether the pending calls have completed.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Type alias for the callback function pointer that the shared library expects.
+/// (This matches the Go-exported callback type.)
+pub type CallbackType = unsafe extern "C" fn(c_double) -> c_double;
+/// Type alias for the integer callback function pointer expected by `CallIntCallback`.
+pub type IntCallbackType = unsafe extern "C" fn(c_int) -> c_int;
+/// Callback type expected by the asynchronous function.
+///
+/// Flags cross the boundary as a `u8` rather than a `bool`: a `bool` holding anything but 0
+/// or 1 is undefined behavior, and a byte from Go is not guaranteed to be either. Non-zero
+/// means true, and flags sent to Go are always exactly 0 or 1.
+type AsyncCallback = unsafe extern "C" fn(c_double, *mut c_void) -> u8;
+/// Completion callback invoked exactly once when a multi-shot computation has finished.
+type AsyncDoneCallback = unsafe extern "C" fn(*mut c_void);
+/// Progress callback invoked with the completed fraction of a long-running computation.
+type ProgressCallback = unsafe extern "C" fn(c_float, *mut c_void);
+/// Request callback of a pull-based computation, invoked by Go before producing each value.
+/// It blocks until the next value is wanted and returns non-zero to produce it, zero to stop.
+type RequestCallback = unsafe extern "C" fn(*mut c_void) -> u8;
+/// Callback type expected by `TransformShape`, receiving and returning a shape by value.
+type ShapeCallback = unsafe extern "C" fn(Shape) -> Shape;
+/// Callback type that receives a user data pointer alongside the value.
+type ContextCallback = unsafe extern "C" fn(c_double, *mut c_void) -> c_double;
+/// Error handler type expected by `SetErrorHandler`.
+type ErrorHandlerCallback = unsafe extern "C" fn(*const c_char, *mut c_void);
+/// Closure registered with `CircleLibrary::set_default_callback` or
+/// `CircleLibrary::register_callback`.
+type SharedCallback = Arc<dyn Fn(f64) -> f64 + Send + Sync>;
+/// Callback type expected by `CallNamedCallback`, receiving the name of the invoked slot.
+type NamedCallbackType = unsafe extern "C" fn(c_double, *const c_char) -> c_double;
+
+// Closure-based callbacks.
+// `call_callback_with` and friends push the user's closure onto a per-thread stack for the
+// duration of the FFI call, so concurrent calls on different threads never see each other's
+// closures and nested calls from inside a closure simply stack on top of the outer one.
+
+ffi_callback! {
+    /// Runs `call` with a trampoline that forwards `CallCallback` invocations to `callback`.
+    fn with_callback(val: c_double) -> c_double = 0.0;
+}
+
+ffi_callback! {
+    /// Runs `call` with a trampoline that forwards `CallIntCallback` invocations to `callback`.
+    fn with_int_callback(val: c_int) -> c_int = 0;
+}
+
+ffi_callback! {
+    /// Runs `call` with a trampoline that forwards `CallNamedCallback` invocations, along
+    /// with the name of the invoked slot, to `callback`.
+    fn with_named_callback(val: c_double, name: *const c_char) -> c_double = 0.0;
+}
+
+ffi_callback! {
+    /// Runs `call` with a trampoline that forwards `TransformShape` invocations to
+    /// `callback`. Go receives the input shape unchanged if the closure panics.
+    fn with_shape_callback(shape: Shape) -> Shape = shape;
+}
+
+/// Checks that `shape.shape_type` holds one of the `ShapeType` discriminants.
+///
+/// A `Shape` built from untrusted bytes may carry any value in that field, so it is read as
+/// the raw `c_int` Go receives rather than matched as a `ShapeType`.
+///
+/// # Safety
+/// `shape` must point to memory that is valid for reading a `Shape`.
+unsafe fn validate_shape_type(shape: *const Shape) -> Result<(), LibraryError> {
+    let raw = std::ptr::addr_of!((*shape).shape_type)
+        .cast::<c_int>()
+        .read();
+    match ShapeType::from_c_int(raw) {
+        Some(_) => Ok(()),
+        None => Err(LibraryError::InvalidInput(format!(
+            "unknown shape type discriminant {}",
+            raw
+        ))),
+    }
+}
+
+/// Derived measurements of a circle, with C layout matching the Go `CircleDetails` struct.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct CircleDetails {
+    pub area: c_double,
+    pub circumference: c_double,
+    pub diameter: c_double,
+}
+
+/// How the Go library computes areas, with C layout matching the Go `AreaMeta` struct.
+///
+/// Returned by `CircleLibrary::area_metadata`, e.g. to round reported areas to the
+/// precision they were actually computed with.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AreaMeta {
+    /// Bits of floating-point precision areas are computed with, e.g. 32 or 64.
+    pub precision_bits: c_int,
+    /// Unit code of the computed areas; 0 means the squared unit of the input dimensions.
+    pub unit: c_int,
+}
+
+/// A safe wrapper around the Go circle library that includes callback support.
+///
+/// This struct loads the shared library and exposes safe methods for calculating
+/// the circle area, formatting circle info, and invoking a callback. The `call_callback_with`
+/// method allows a Rust closure (e.g. `|x| x * x`) to be used as the callback, hiding all
+/// unsafe FFI and pointer operations.
+///
+/// The loaded library is reference counted: cloning a `CircleLibrary` is cheap, and the
+/// handle is closed when the last clone (and every generator and `GoString` created from
+/// it) is dropped, after `ShutdownLibrary` has run.
+///
+/// # Unloading
+///
+/// The library itself is never unloaded: it is opened with `RTLD_NODELETE` on Unix and
+/// pinned on Windows, so it stays mapped until the process exits. A Go runtime cannot be
+/// stopped once started; its threads and any goroutines started by async calls keep
+/// running code from the library, and may call back through it, after the last handle is
+/// gone. Unmapping it would crash the process. Loading many different libraries therefore
+/// keeps each of them resident, while loading the same path again reuses the instance
+/// that is already mapped.
+///
+/// `CircleLibrary` is `Send` and `Sync`, so it can be shared across threads, e.g. in an
+/// `Arc` used by a web server's handlers. This follows from its fields rather than an
+/// `unsafe impl`: `Library` is `Send + Sync`, plain function pointers are too, and closures
+/// passed to the callback methods are kept in thread-local storage or in the user data of
+/// a single call rather than in shared state. The Go exports themselves are safe to call
+/// concurrently.
+#[derive(Clone)]
+pub struct CircleLibrary {
+    // Keep the loaded library alive for the lifetime of the wrapper.
+    //
+    // Safety invariant: every function pointer below was resolved from this library and is
+    // only valid while it stays loaded. The pointers are private and only called through
+    // `&self`, so holding this `Arc` guarantees they never outlive the library.
+    lib: Arc<Library>,
+    // Calls `ShutdownLibrary` once the last clone and generator are dropped.
+    runtime: Arc<RuntimeGuard>,
+    // The file the library was loaded from, if it was written by `from_bytes`. Declared
+    // after `lib` so the handle is released before the file is deleted.
+    temp_file: Option<Arc<TempLibraryFile>>,
+    // The path the library was loaded from, reported by `path` and `Debug`.
+    path: PathBuf,
+    // Identifies this library (and its clones) as the owner of pending async calls.
+    id: usize,
+    // Unregisters the `on_error` handler of `id` once the last clone is dropped.
+    error_handler: Arc<error_handlers::HandlerGuard>,
+    // How symbols were resolved, reused by `reload`.
+    options: CircleLibraryBuilder,
+    // Per-method call counters, shared by all clones.
+    #[cfg(feature = "metrics")]
+    metrics: Arc<metrics::CallMetrics>,
+    // Closure used by `call_callback_default`, shared by all clones.
+    default_callback: Arc<RwLock<Option<SharedCallback>>>,
+    // Closures invoked by name through `call_named_callback`, shared by all clones.
+    //
+    // Both locks only guard whole-value replacements and clones, which a panic cannot leave
+    // half done, so they are accessed ignoring poisoning.
+    named_callbacks: Arc<RwLock<HashMap<String, SharedCallback>>>,
+    calculate_circle_area: unsafe extern "C" fn(c_double) -> c_double,
+    calculate_struct_area: unsafe extern "C" fn(Circle) -> c_double,
+    format_circle_info: unsafe extern "C" fn(c_double) -> *mut c_char,
+    free_string: unsafe extern "C" fn(*mut c_char),
+    // Whether strings returned by the library must be released through `free_string`.
+    string_ownership: StringOwnership,
+    call_callback: unsafe extern "C" fn(c_double, CallbackType) -> c_double,
+    // Pointers to the asynchronous functions; `None` if marked optional and not exported.
+    calculate_circle_area_async: Option<unsafe extern "C" fn(c_double, AsyncCallback, *mut c_void)>,
+    calculate_circle_area_async_multiple:
+        Option<unsafe extern "C" fn(c_double, AsyncCallback, AsyncDoneCallback, *mut c_void)>,
+    calculate_circle_area_async_progress:
+        Option<unsafe extern "C" fn(c_double, ProgressCallback, AsyncCallback, *mut c_void)>,
+    flush_async: Option<unsafe extern "C" fn()>,
+    calculate_circle_area_poll: Option<
+        unsafe extern "C" fn(
+            c_double,
+            RequestCallback,
+            AsyncCallback,
+            AsyncDoneCallback,
+            *mut c_void,
+        ),
+    >,
+    calculate_shape_area: Option<unsafe extern "C" fn(Shape) -> c_double>,
+    calculate_shape_area_checked: Option<unsafe extern "C" fn(Shape, *mut c_int) -> c_double>,
+    call_named_callback:
+        Option<unsafe extern "C" fn(c_double, *const c_char, NamedCallbackType) -> c_double>,
+    call_callback_with_context:
+        Option<unsafe extern "C" fn(c_double, ContextCallback, *mut c_void) -> c_double>,
+    call_callback_concurrent: Option<
+        unsafe extern "C" fn(*const c_double, GoInt, ContextCallback, *mut c_void, *mut c_double),
+    >,
+    calculate_circle_areas_batch:
+        Option<unsafe extern "C" fn(*const c_double, GoInt, *mut c_double)>,
+    calculate_shape_areas_batch: Option<unsafe extern "C" fn(*const Shape, GoInt, *mut c_double)>,
+    sum_areas: Option<unsafe extern "C" fn(*const c_double, c_int) -> c_double>,
+    normalize_shapes: Option<unsafe extern "C" fn(*mut Shape, GoInt)>,
+    get_circle_details: Option<unsafe extern "C" fn(c_double) -> *mut CircleDetails>,
+    free_circle_details: Option<unsafe extern "C" fn(*mut CircleDetails)>,
+    calculate_circle_area_f32: Option<unsafe extern "C" fn(c_float) -> c_float>,
+    format_circle_info_labeled:
+        Option<unsafe extern "C" fn(*const c_char, c_double) -> *mut c_char>,
+    format_circle_info_prec: Option<unsafe extern "C" fn(c_double, c_int) -> *mut c_char>,
+    call_int_callback: Option<unsafe extern "C" fn(c_int, IntCallbackType) -> c_int>,
+    transform_shape: Option<unsafe extern "C" fn(Shape, ShapeCallback) -> Shape>,
+    calculate_circumference: Option<unsafe extern "C" fn(c_double) -> c_double>,
+    calculate_diameter: Option<unsafe extern "C" fn(c_double) -> c_double>,
+    map_radii: Option<unsafe extern "C" fn(*const c_double, GoInt) -> *mut DoubleArray>,
+    free_double_array: Option<unsafe extern "C" fn(*mut DoubleArray)>,
+    format_circle_infos: Option<unsafe extern "C" fn(*const c_double, GoInt) -> *mut StringArray>,
+    free_string_array: Option<unsafe extern "C" fn(*mut StringArray)>,
+    generate_shapes: Option<unsafe extern "C" fn(GoInt, *mut GoInt) -> *mut Shape>,
+    free_shapes: Option<unsafe extern "C" fn(*mut Shape, GoInt)>,
+    library_pi: Option<unsafe extern "C" fn() -> c_double>,
+    area_metadata: Option<unsafe extern "C" fn() -> AreaMeta>,
+    set_error_handler: Option<unsafe extern "C" fn(ErrorHandlerCallback, *mut c_void)>,
+    add_ints: Option<unsafe extern "C" fn(c_int, c_int) -> c_int>,
+    multiply_ints: Option<unsafe extern "C" fn(c_int, c_int) -> c_int>,
+}
+
+// Fails to compile if a future field makes `CircleLibrary` lose `Send` or `Sync`.
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+    let _ = assert_send_sync::<CircleLibrary>;
+};
+
+/// Stand-in for `metrics::CallTimer` when the `metrics` feature is disabled.
+#[cfg(not(feature = "metrics"))]
+struct NoopTimer;
+
+/// A Go-allocated array of doubles, with C layout matching the Go `DoubleArray` struct.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DoubleArray {
+    data: *mut c_double,
+    len: usize,
+}
+
+/// A Go-allocated array of C strings, with C layout matching the Go `StringArray` struct.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct StringArray {
+    data: *mut *mut c_char,
+    len: usize,
+}
+
+/// Who owns the strings returned by the Go library.
+///
+/// Reported by the optional `StringIsOwned` export; libraries that do not export it are
+/// assumed to return owned strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringOwnership {
+    /// Strings are allocated per call and must be released through `FreeString`.
+    Owned,
+    /// Strings are static and must not be freed.
+    Static,
+}
+
+/// A NUL-terminated string returned by the Go library, freed through `FreeString` when
+/// dropped if the library hands out owned strings.
+///
+/// Dereferences to a `CStr`, so the bytes can be read without copying them into a Rust
+/// `String`. Returned by `CircleLibrary::format_circle_info_owned`.
+pub struct GoString {
+    ptr: NonNull<c_char>,
+    // `None` if the string is static and must not be freed.
+    free_string: Option<unsafe extern "C" fn(*mut c_char)>,
+    // Keeps the library loaded, and therefore `free_string` valid, until the string is freed.
+    _lib: Arc<Library>,
+}
+
+impl Deref for GoString {
+    type Target = CStr;
+
+    fn deref(&self) -> &CStr {
+        // The pointer is non-null and NUL-terminated, and stays valid until `drop`.
+        unsafe { CStr::from_ptr(self.ptr.as_ptr()) }
+    }
+}
+
+impl fmt::Debug for CircleLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircleLibrary")
+            .field("path", &self.path)
+            .field("capabilities", &self.capabilities())
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for CircleLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "circle library at {}", self.path.display())
+    }
+}
+
+impl fmt::Debug for GoString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl Drop for GoString {
+    fn drop(&mut self) {
+        if let Some(free_string) = self.free_string {
+            unsafe { free_string(self.ptr.as_ptr()) }
+        }
+    }
+}
+
+/// Which optional functions a loaded `CircleLibrary` supports.
+///
+/// Returned by `CircleLibrary::capabilities`, e.g. to disable UI for operations the loaded
+/// Go library does not export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Capabilities {
+    pub circle_area_async: bool,
+    pub circle_area_async_multi: bool,
+    pub circle_area_async_progress: bool,
+    pub circle_area_poll: bool,
+    pub flush_async: bool,
+    pub shape_area: bool,
+    pub shape_area_checked: bool,
+    pub circle_area_f32: bool,
+    pub circle_areas_batch: bool,
+    pub shape_areas_batch: bool,
+    pub sum_areas: bool,
+    pub normalize_shapes: bool,
+    pub circle_details: bool,
+    pub labeled_info: bool,
+    pub info_precision: bool,
+    pub callback_with_context: bool,
+    pub callback_concurrent: bool,
+    pub named_callback: bool,
+    pub int_callback: bool,
+    pub shape_transform: bool,
+    pub circumference: bool,
+    pub diameter: bool,
+    pub map_radii: bool,
+    pub circle_infos: bool,
+    pub generate_shapes: bool,
+    pub pi: bool,
+    pub area_metadata: bool,
+    pub error_handler: bool,
+    pub int_arithmetic: bool,
+}
+
+/// Cancels an in-flight multi-shot calculation started by
+/// `CircleLibrary::calculate_circle_area_async_multi_cancellable`.
+///
+/// Cancelling sets a flag shared with the callback closure; the next time Go invokes the
+/// callback it returns false, which tells Go to stop producing results. Dropping the handle
+/// cancels as well, so keep it alive for as long as results are wanted.
+#[derive(Debug)]
+pub struct MultiShotHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl MultiShotHandle {
+    /// Asks the Go side to stop producing results.
+    ///
+    /// Results already delivered to the channel remain readable; at most the in-flight
+    /// result is discarded.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true once `cancel` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MultiShotHandle {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// The raw function pointers resolved from the Go library, returned by
+/// `CircleLibrary::raw_symbols`.
+///
+/// This is an escape hatch for calling the Go exports in ways the safe methods do not
+/// support. Nothing here is checked: arguments are passed to Go as is, returned strings must
+/// be freed correctly, and every pointer must only be called while a `CircleLibrary` for
+/// the library is alive. More pointers may be added in later versions.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct RawSymbols {
+    pub calculate_circle_area: unsafe extern "C" fn(c_double) -> c_double,
+    pub calculate_struct_area: unsafe extern "C" fn(Circle) -> c_double,
+    pub format_circle_info: unsafe extern "C" fn(c_double) -> *mut c_char,
+    pub free_string: unsafe extern "C" fn(*mut c_char),
+    pub call_callback: unsafe extern "C" fn(c_double, CallbackType) -> c_double,
+    pub calculate_shape_area: Option<unsafe extern "C" fn(Shape) -> c_double>,
+    pub calculate_circle_area_f32: Option<unsafe extern "C" fn(c_float) -> c_float>,
+    pub call_int_callback: Option<unsafe extern "C" fn(c_int, IntCallbackType) -> c_int>,
+}
+
+/// Counts the results of a bounded multi-shot calculation that were dropped because the
+/// channel was full, as returned by `CircleLibrary::calculate_circle_area_async_multi_bounded`.
+#[derive(Debug, Clone)]
+pub struct DroppedResults {
+    count: Arc<AtomicUsize>,
+}
+
+impl DroppedResults {
+    /// Returns how many results have been dropped so far.
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+}
+
+/// The results of a pull-based area calculation started by
+/// `CircleLibrary::calculate_circle_area_poll`.
+///
+/// Go only produces a value once the stream is polled for one, so it never runs ahead of
+/// the consumer. The protocol uses one channel in each direction:
+///
+/// 1. Before computing a value, Go invokes the request callback, which blocks until the
+///    stream is polled and a request is sent on the request channel.
+/// 2. Go computes the value and delivers it through the result callback, which sends it on
+///    the result channel that the stream is polling.
+/// 3. This repeats until either side stops. Dropping the stream closes the request
+///    channel, so the pending request callback returns 0 and Go stops. When Go stops on
+///    its own, it calls the done callback, which closes the result channel and ends the
+///    stream.
+///
+/// At most one request is outstanding at a time.
+#[derive(Debug)]
+pub struct PollStream {
+    // Sending asks Go for the next value; dropping the sender tells Go to stop.
+    requests: std::sync::mpsc::Sender<()>,
+    results: mpsc::UnboundedReceiver<f64>,
+    // Whether a value has been requested but not received yet.
+    requested: bool,
+}
+
+impl Stream for PollStream {
+    type Item = f64;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<f64>> {
+        if !self.requested {
+            // A closed request channel means Go has already finished.
+            if self.requests.send(()).is_err() {
+                return Poll::Ready(None);
+            }
+            self.requested = true;
+        }
+        let result = ready!(self.results.poll_recv(cx));
+        self.requested = false;
+        Poll::Ready(result)
+    }
+}
+
+/// The Go side of a `PollStream`, passed to Go as user data and reclaimed by
+/// `async_done_trampoline` once Go signals completion.
+struct PollState {
+    requests: std::sync::mpsc::Receiver<()>,
+    results: mpsc::UnboundedSender<f64>,
+}
+
+/// Builder for loading a `CircleLibrary` with some symbol groups marked optional.
+///
+/// The core symbols (sync area, struct area, formatting and callbacks) are always required.
+/// Groups marked optional may be absent from the library; the methods that depend on them
+/// then return `LibraryError::SymbolMissing` at call time instead of failing the load.
+/// This allows binding against older versions of the Go library.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CircleLibraryBuilder {
+    optional_async: bool,
+    optional_shape: bool,
+    require_f32: bool,
+    pub(crate) load_flags: LoadFlags,
+}
+
+impl CircleLibraryBuilder {
+    /// Creates a builder that requires every symbol, matching `CircleLibrary::new`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the core symbols: `CalculateCircleArea`, `CalculateCircleStructArea`,
+    /// `FormatCircleInfo`, `FreeString` and `CallCallback`.
+    ///
+    /// They are always required, since no `CircleLibrary` method works without them, so
+    /// this does not change the configuration. It lets a chain such as
+    /// `CircleLibrary::builder().require_core().optional_async()` spell out every group.
+    pub fn require_core(self) -> Self {
+        self
+    }
+
+    /// Marks the async symbols (`CalculateCircleAreaAsync` and
+    /// `CalculateCircleAreaAsyncMultiple`) as optional.
+    pub fn optional_async(mut self) -> Self {
+        self.optional_async = true;
+        self
+    }
+
+    /// Marks the async symbols as required (the default).
+    pub fn require_async(mut self) -> Self {
+        self.optional_async = false;
+        self
+    }
+
+    /// Marks the `CalculateShapeArea` symbol as optional.
+    pub fn optional_shape(mut self) -> Self {
+        self.optional_shape = true;
+        self
+    }
+
+    /// Marks the `CalculateShapeArea` symbol as required (the default).
+    pub fn require_shape(mut self) -> Self {
+        self.optional_shape = false;
+        self
+    }
+
+    /// Requires the `CalculateCircleAreaF32` symbol. It is optional by default, since
+    /// older libraries only export `f64` entry points.
+    pub fn require_f32(mut self) -> Self {
+        self.require_f32 = true;
+        self
+    }
+
+    /// Marks the `CalculateCircleAreaF32` symbol as optional (the default).
+    pub fn optional_f32(mut self) -> Self {
+        self.require_f32 = false;
+        self
+    }
+
+    /// Sets the flags used to open the library on Unix; see `LoadFlags`.
+    pub fn load_flags(mut self, flags: LoadFlags) -> Self {
+        self.load_flags = flags;
+        self
+    }
+
+    /// Loads the shared library from the given path using this configuration.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::LoadFailed` if the library cannot be loaded, or
+    /// `LibraryError::SymbolMissing` if a required symbol is not exported.
+    pub fn build(&self, path: &str) -> Result<CircleLibrary, LibraryError> {
+        CircleLibrary::load(Path::new(path), self)
+    }
+}
+
+impl CircleLibrary {
+    /// Loads the shared library from the given path.
+    ///
+    /// # Arguments
+    /// * `path` - The file path to the shared library (e.g., "lib.dll).
+    ///
+    /// If the library exports `InitLibrary`, it is called once the library has been
+    /// accepted. Its `ShutdownLibrary` export, if any, is called when the last clone of the
+    /// returned value and the last generator created from it have been dropped. The library
+    /// is never unloaded from the process; see `CircleLibrary`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Unsupported` if the platform cannot load libraries dynamically,
+    /// `LibraryError::LoadFailed` if the library cannot be loaded, or
+    /// `LibraryError::SymbolMissing` if any required symbol is not exported,
+    /// `LibraryError::AbiMismatch` if the library reports an incompatible ABI version,
+    /// `LibraryError::ByteOrderMismatch` if it was built for a different byte order, or
+    /// `LibraryError::LayoutMismatch` if it reports a shared struct size that differs from
+    /// the Rust definition.
+    pub fn new(path: &str) -> Result<Self, LibraryError> {
+        Self::load(Path::new(path), &CircleLibraryBuilder::new())
+    }
+
+    /// Loads the shared library on Tokio's blocking thread pool.
+    ///
+    /// Loading a library and resolving its symbols blocks for as long as the system loader
+    /// takes, which can be noticeable for a large Go library. Running it inside
+    /// `tokio::task::spawn_blocking` keeps executor threads free in the meantime. The path
+    /// is copied, so the returned future is `'static`; it must be awaited inside a Tokio
+    /// runtime.
+    ///
+    /// # Errors
+    /// Returns any error `new` can return, or `LibraryError::TaskCancelled` if the
+    /// runtime shuts down before the library is loaded.
+    ///
+    /// # Panics
+    /// A panic while loading is resumed when the future is awaited.
+    pub fn new_async(
+        path: &str,
+    ) -> impl Future<Output = Result<Self, LibraryError>> + Send + 'static {
+        let path = path.to_string();
+        async move {
+            match tokio::task::spawn_blocking(move || Self::new(&path)).await {
+                Ok(result) => result,
+                Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+                Err(_) => Err(LibraryError::TaskCancelled),
+            }
+        }
+    }
+
+    /// Loads the shared library, retrying up to `attempts` times if loading fails.
+    ///
+    /// This helps when the library has only just been written to disk, e.g. by a test
+    /// harness that builds it and loads it in the same process, and the first loads fail
+    /// intermittently. Only failures of the load itself are retried, with `delay` between
+    /// attempts; a missing symbol or ABI mismatch is returned immediately. An `attempts` of
+    /// zero is treated as one.
+    ///
+    /// # Errors
+    /// Returns the error of the last attempt if every attempt fails, or any error `new`
+    /// can return once the library has been loaded.
+    pub fn new_with_retry(
+        path: &str,
+        attempts: u32,
+        delay: Duration,
+    ) -> Result<Self, LibraryError> {
+        let attempts = attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match Self::new(path) {
+                Err(LibraryError::LoadFailed(_)) if attempt < attempts => {
+                    std::thread::sleep(delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Loads the library from its bytes, e.g. embedded with `include_bytes!`.
+    ///
+    /// The bytes are written to a uniquely named file with the platform's library
+    /// extension in the system temp directory, which is deleted again once the last clone
+    /// of the returned library is dropped. Windows refuses to delete a library that is still
+    /// loaded, so there the file is left behind. Some systems refuse to load libraries from the
+    /// temp directory (e.g. when it is mounted `noexec`); use `from_bytes_in` to pick a
+    /// different directory there.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Io` if the file cannot be written, or any error `new` can
+    /// return.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, LibraryError> {
+        Self::from_bytes_in(data, &env::temp_dir())
+    }
+
+    /// Like `from_bytes`, but writes the library file into `dir`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Io` if the file cannot be written, or any error `new` can
+    /// return.
+    pub fn from_bytes_in(data: &[u8], dir: &Path) -> Result<Self, LibraryError> {
+        let file = TempLibraryFile::create(data, dir)?;
+        // If loading fails, dropping `file` deletes it again.
+        let mut library = Self::load(&file.path, &CircleLibraryBuilder::new())?;
+        library.temp_file = Some(Arc::new(file));
+        Ok(library)
+    }
+
+    /// Lists the symbols exported by the shared library at `path`, without loading it.
+    ///
+    /// Useful when loading fails with `LibraryError::SymbolMissing`, to spot a name
+    /// mismatch such as `calculate_circle_area` versus `CalculateCircleArea`. The export
+    /// table of ELF, PE and Mach-O files is parsed directly, so this also works for
+    /// libraries that cannot be loaded in the current process.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Io` if the file cannot be read, or
+    /// `LibraryError::InvalidInput` if it is not a shared library that can be parsed.
+    #[cfg(feature = "diagnostics")]
+    pub fn diagnose(path: &str) -> Result<Vec<String>, LibraryError> {
+        let bytes = std::fs::read(path)?;
+        diagnostics::exported_symbols(&bytes)
+    }
+
+    /// Loads the shared library from the given path, opening it with `flags` on Unix.
+    ///
+    /// Use `LoadFlags::now` to fail fast if the library has unresolved dependencies, or
+    /// `LoadFlags::global` if plugins loaded later need to link against its symbols. On
+    /// other platforms the flags are ignored and this behaves like `new`. The flags are
+    /// kept for `reload`.
+    ///
+    /// # Errors
+    /// Returns any error `new` can return.
+    pub fn new_with_flags(path: &str, flags: LoadFlags) -> Result<Self, LibraryError> {
+        Self::load(
+            Path::new(path),
+            &CircleLibraryBuilder::new().load_flags(flags),
+        )
+    }
+
+    /// Returns a builder for loading a library with optional symbol groups.
+    pub fn builder() -> CircleLibraryBuilder {
+        CircleLibraryBuilder::new()
+    }
+
+    /// Loads the shared library by base name, resolving the platform-specific file name.
+    ///
+    /// A base name like `"circle"` becomes `circle.dll` on Windows, `libcircle.so` on Linux
+    /// and `libcircle.dylib` on macOS. The executable's directory is searched first,
+    /// followed by the current working directory.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::NotFound` listing every attempted path if no candidate file
+    /// exists, or any error `new` can return once a candidate has been found.
+    pub fn open(name: &str) -> Result<Self, LibraryError> {
+        let searched = library_search_paths(name);
+        match searched.iter().find(|path| path.is_file()) {
+            Some(path) => Self::load(path, &CircleLibraryBuilder::new()),
+            None => Err(LibraryError::NotFound {
+                name: name.to_string(),
+                searched,
+            }),
+        }
+    }
+
+    /// Replaces the loaded library with the one at `path`, e.g. after rebuilding it.
+    ///
+    /// The file at `path` is copied to a uniquely named file in the system temp directory
+    /// and loaded from there, like `from_bytes` does, since the system loader would return
+    /// the already loaded library if `path` was opened again, even after the file has been
+    /// overwritten. `path` still reports the original path.
+    ///
+    /// The new library is loaded and all of its symbols are resolved, using the same
+    /// optional symbol groups as the original load, before anything is swapped in. If that
+    /// fails the error is returned and this value keeps using the old library. Otherwise
+    /// the handle to the old library is released once clones, generators and `GoString`s
+    /// created from it are gone as well, since they hold their own reference. The old
+    /// library itself stays loaded, as Go cannot be unloaded from a running process.
+    ///
+    /// Pending async calls, the default and named callbacks, the error handler registered with
+    /// `on_error` and (with the `metrics` feature) call counts carry over to the new library.
+    /// Calls already in flight keep running on the old library's code and deliver their
+    /// results as usual.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Io` if `path` cannot be copied, e.g. because it does not
+    /// exist, or any other error `new` can return for it.
+    pub fn reload(&mut self, path: &str) -> Result<(), LibraryError> {
+        let file = TempLibraryFile::copy(Path::new(path), &env::temp_dir())?;
+        // If loading fails, dropping `file` deletes the copy again.
+        let mut reloaded = Self::load(&file.path, &self.options)?;
+        reloaded.temp_file = Some(Arc::new(file));
+        reloaded.path = PathBuf::from(path);
+        reloaded.id = self.id;
+        reloaded.error_handler = Arc::clone(&self.error_handler);
+        reloaded.default_callback = Arc::clone(&self.default_callback);
+        reloaded.named_callbacks = Arc::clone(&self.named_callbacks);
+        #[cfg(feature = "metrics")]
+        {
+            reloaded.metrics = Arc::clone(&self.metrics);
+        }
+        if error_handlers::get(self.id).is_some() {
+            if let Some(set_error_handler) = reloaded.set_error_handler {
+                unsafe {
+                    set_error_handler(error_handler_trampoline, self.id as *mut c_void);
+                }
+            }
+        }
+        // Dropping the previous value releases the old library.
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// Returns whether shared libraries can be loaded dynamically in this process.
+    ///
+    /// This is checked at runtime, once, by asking the system loader for the running
+    /// program. It fails where `dlopen` is only a stub, e.g. in statically linked musl
+    /// binaries. There every constructor fails with `LibraryError::Unsupported` rather than
+    /// the loader's own message, and callers can fall back to the pure Rust `ShapeArea`
+    /// implementations instead.
+    pub fn is_supported() -> bool {
+        loader::dynamic_loading_supported()
+    }
+
+    /// Loads the shared library at `path` and resolves its symbols as configured by `options`.
+    fn load(path: &Path, options: &CircleLibraryBuilder) -> Result<Self, LibraryError> {
+        // Load the library.
+        let lib = Arc::new(open_library(path, options.load_flags)?);
+        Self::from_library(lib, path, options)
+    }
+
+    /// Resolves the symbols of an already loaded library as configured by `options`.
+    pub(crate) fn from_library(
+        lib: Arc<Library>,
+        path: &Path,
+        options: &CircleLibraryBuilder,
+    ) -> Result<Self, LibraryError> {
+        check_abi_version(&lib)?;
+        layout::check_struct_layouts(&lib)?;
+
+        unsafe {
+            // Load the function symbols. `get_symbol` names the missing symbol in its error,
+            // e.g. `SymbolMissing { name: "CalculateShapeArea", .. }`.
+            let calculate_circle_area: Symbol<unsafe extern "C" fn(c_double) -> c_double> =
+                get_symbol(&lib, "CalculateCircleArea")?;
+            let calculate_struct_area: Symbol<unsafe extern "C" fn(Circle) -> c_double> =
+                get_symbol(&lib, "CalculateCircleStructArea")?;
+            let format_circle_info: Symbol<unsafe extern "C" fn(c_double) -> *mut c_char> =
+                get_symbol(&lib, "FormatCircleInfo")?;
+            let free_string: Symbol<unsafe extern "C" fn(*mut c_char)> =
+                get_symbol(&lib, "FreeString")?;
+            let call_callback: Symbol<unsafe extern "C" fn(c_double, CallbackType) -> c_double> =
+                get_symbol(&lib, "CallCallback")?;
+            // Libraries that predate `StringIsOwned` always return owned strings.
+            let string_is_owned: Option<Symbol<unsafe extern "C" fn() -> u8>> =
+                get_optional_symbol(&lib, "StringIsOwned", true)?;
+            let string_ownership = match string_is_owned {
+                Some(string_is_owned) if string_is_owned() == 0 => StringOwnership::Static,
+                _ => StringOwnership::Owned,
+            };
+            // Load the symbols that may be marked optional.
+            let calculate_circle_area_async: Option<
+                Symbol<unsafe extern "C" fn(c_double, AsyncCallback, *mut c_void)>,
+            > = get_optional_symbol(&lib, "CalculateCircleAreaAsync", options.optional_async)?;
+
+            let calculate_circle_area_async_multiple: Option<
+                Symbol<
+                    unsafe extern "C" fn(c_double, AsyncCallback, AsyncDoneCallback, *mut c_void),
+                >,
+            > = get_optional_symbol(
+                &lib,
+                "CalculateCircleAreaAsyncMultiple",
+                options.optional_async,
+            )?;
+
+            let calculate_shape_area: Option<Symbol<unsafe extern "C" fn(Shape) -> c_double>> =
+                get_optional_symbol(&lib, "CalculateShapeArea", options.optional_shape)?;
+
+            // Symbols added in later versions of the Go library are always optional.
+            let calculate_shape_area_checked: Option<
+                Symbol<unsafe extern "C" fn(Shape, *mut c_int) -> c_double>,
+            > = get_optional_symbol(&lib, "CalculateShapeAreaChecked", true)?;
+            let call_callback_with_context: Option<
+                Symbol<unsafe extern "C" fn(c_double, ContextCallback, *mut c_void) -> c_double>,
+            > = get_optional_symbol(&lib, "CallCallbackWithContext", true)?;
+            let call_callback_concurrent: Option<
+                Symbol<
+                    unsafe extern "C" fn(
+                        *const c_double,
+                        GoInt,
+                        ContextCallback,
+                        *mut c_void,
+                        *mut c_double,
+                    ),
+                >,
+            > = get_optional_symbol(&lib, "CallCallbackConcurrent", true)?;
+            let call_named_callback: Option<
+                Symbol<
+                    unsafe extern "C" fn(c_double, *const c_char, NamedCallbackType) -> c_double,
+                >,
+            > = get_optional_symbol(&lib, "CallNamedCallback", true)?;
+            let calculate_shape_areas_batch: Option<
+                Symbol<unsafe extern "C" fn(*const Shape, GoInt, *mut c_double)>,
+            > = get_optional_symbol(&lib, "CalculateShapeAreasBatch", true)?;
+            let calculate_circle_area_async_progress: Option<
+                Symbol<
+                    unsafe extern "C" fn(c_double, ProgressCallback, AsyncCallback, *mut c_void),
+                >,
+            > = get_optional_symbol(&lib, "CalculateCircleAreaAsyncProgress", true)?;
+            let calculate_circle_area_poll: Option<
+                Symbol<
+                    unsafe extern "C" fn(
+                        c_double,
+                        RequestCallback,
+                        AsyncCallback,
+                        AsyncDoneCallback,
+                        *mut c_void,
+                    ),
+                >,
+            > = get_optional_symbol(&lib, "CalculateCircleAreaPoll", true)?;
+            let flush_async: Option<Symbol<unsafe extern "C" fn()>> =
+                get_optional_symbol(&lib, "FlushAsync", true)?;
+            let calculate_circle_areas_batch: Option<
+                Symbol<unsafe extern "C" fn(*const c_double, GoInt, *mut c_double)>,
+            > = get_optional_symbol(&lib, "CalculateCircleAreasBatch", true)?;
+            let sum_areas: Option<
+                Symbol<unsafe extern "C" fn(*const c_double, c_int) -> c_double>,
+            > = get_optional_symbol(&lib, "SumAreas", true)?;
+            let normalize_shapes: Option<Symbol<unsafe extern "C" fn(*mut Shape, GoInt)>> =
+                get_optional_symbol(&lib, "NormalizeShapes", true)?;
+            let get_circle_details: Option<
+                Symbol<unsafe extern "C" fn(c_double) -> *mut CircleDetails>,
+            > = get_optional_symbol(&lib, "GetCircleDetails", true)?;
+            let free_circle_details: Option<Symbol<unsafe extern "C" fn(*mut CircleDetails)>> =
+                get_optional_symbol(&lib, "FreeCircleDetails", true)?;
+            let calculate_circle_area_f32: Option<
+                Symbol<unsafe extern "C" fn(c_float) -> c_float>,
+            > = get_optional_symbol(&lib, "CalculateCircleAreaF32", !options.require_f32)?;
+            let format_circle_info_labeled: Option<
+                Symbol<unsafe extern "C" fn(*const c_char, c_double) -> *mut c_char>,
+            > = get_optional_symbol(&lib, "FormatCircleInfoLabeled", true)?;
+            let format_circle_info_prec: Option<
+                Symbol<unsafe extern "C" fn(c_double, c_int) -> *mut c_char>,
+            > = get_optional_symbol(&lib, "FormatCircleInfoPrec", true)?;
+            let call_int_callback: Option<
+                Symbol<unsafe extern "C" fn(c_int, IntCallbackType) -> c_int>,
+            > = get_optional_symbol(&lib, "CallIntCallback", true)?;
+            let transform_shape: Option<
+                Symbol<unsafe extern "C" fn(Shape, ShapeCallback) -> Shape>,
+            > = get_optional_symbol(&lib, "TransformShape", true)?;
+            let calculate_circumference: Option<
+                Symbol<unsafe extern "C" fn(c_double) -> c_double>,
+            > = get_optional_symbol(&lib, "CalculateCircumference", true)?;
+            let calculate_diameter: Option<Symbol<unsafe extern "C" fn(c_double) -> c_double>> =
+                get_optional_symbol(&lib, "CalculateDiameter", true)?;
+            let map_radii: Option<
+                Symbol<unsafe extern "C" fn(*const c_double, GoInt) -> *mut DoubleArray>,
+            > = get_optional_symbol(&lib, "MapRadii", true)?;
+            let free_double_array: Option<Symbol<unsafe extern "C" fn(*mut DoubleArray)>> =
+                get_optional_symbol(&lib, "FreeDoubleArray", true)?;
+            let format_circle_infos: Option<
+                Symbol<unsafe extern "C" fn(*const c_double, GoInt) -> *mut StringArray>,
+            > = get_optional_symbol(&lib, "FormatCircleInfos", true)?;
+            let free_string_array: Option<Symbol<unsafe extern "C" fn(*mut StringArray)>> =
+                get_optional_symbol(&lib, "FreeStringArray", true)?;
+            let generate_shapes: Option<
+                Symbol<unsafe extern "C" fn(GoInt, *mut GoInt) -> *mut Shape>,
+            > = get_optional_symbol(&lib, "GenerateShapes", true)?;
+            let free_shapes: Option<Symbol<unsafe extern "C" fn(*mut Shape, GoInt)>> =
+                get_optional_symbol(&lib, "FreeShapes", true)?;
+            let library_pi: Option<Symbol<unsafe extern "C" fn() -> c_double>> =
+                get_optional_symbol(&lib, "LibraryPi", true)?;
+            let area_metadata: Option<Symbol<unsafe extern "C" fn() -> AreaMeta>> =
+                get_optional_symbol(&lib, "AreaMetadata", true)?;
+            let set_error_handler: Option<
+                Symbol<unsafe extern "C" fn(ErrorHandlerCallback, *mut c_void)>,
+            > = get_optional_symbol(&lib, "SetErrorHandler", true)?;
+            let add_ints: Option<Symbol<unsafe extern "C" fn(c_int, c_int) -> c_int>> =
+                get_optional_symbol(&lib, "AddInts", true)?;
+            let multiply_ints: Option<Symbol<unsafe extern "C" fn(c_int, c_int) -> c_int>> =
+                get_optional_symbol(&lib, "MultiplyInts", true)?;
+            // Initialize last, so a library rejected by the checks or symbol lookups above
+            // is never initialized.
+            let runtime = Arc::new(RuntimeGuard::init(&lib)?);
+            let id = pending::new_owner_id();
+
+            Ok(CircleLibrary {
+                // Dereference the symbols to store the function pointers.
+                calculate_circle_area: *calculate_circle_area,
+                calculate_struct_area: *calculate_struct_area,
+                format_circle_info: *format_circle_info,
+                free_string: *free_string,
+                string_ownership,
+                call_callback: *call_callback,
+                calculate_circle_area_async: calculate_circle_area_async.map(|s| *s),
+                calculate_circle_area_async_multiple: calculate_circle_area_async_multiple
+                    .map(|s| *s),
+                calculate_circle_area_async_progress: calculate_circle_area_async_progress
+                    .map(|s| *s),
+                calculate_circle_area_poll: calculate_circle_area_poll.map(|s| *s),
+                flush_async: flush_async.map(|s| *s),
+                calculate_shape_area: calculate_shape_area.map(|s| *s),
+                calculate_shape_area_checked: calculate_shape_area_checked.map(|s| *s),
+                call_callback_with_context: call_callback_with_context.map(|s| *s),
+                call_callback_concurrent: call_callback_concurrent.map(|s| *s),
+                call_named_callback: call_named_callback.map(|s| *s),
+                calculate_circle_areas_batch: calculate_circle_areas_batch.map(|s| *s),
+                calculate_shape_areas_batch: calculate_shape_areas_batch.map(|s| *s),
+                sum_areas: sum_areas.map(|s| *s),
+                normalize_shapes: normalize_shapes.map(|s| *s),
+                get_circle_details: get_circle_details.map(|s| *s),
+                free_circle_details: free_circle_details.map(|s| *s),
+                calculate_circle_area_f32: calculate_circle_area_f32.map(|s| *s),
+                format_circle_info_labeled: format_circle_info_labeled.map(|s| *s),
+                format_circle_info_prec: format_circle_info_prec.map(|s| *s),
+                call_int_callback: call_int_callback.map(|s| *s),
+                transform_shape: transform_shape.map(|s| *s),
+                calculate_circumference: calculate_circumference.map(|s| *s),
+                calculate_diameter: calculate_diameter.map(|s| *s),
+                map_radii: map_radii.map(|s| *s),
+                format_circle_infos: format_circle_infos.map(|s| *s),
+                free_string_array: free_string_array.map(|s| *s),
+                free_double_array: free_double_array.map(|s| *s),
+                generate_shapes: generate_shapes.map(|s| *s),
+                free_shapes: free_shapes.map(|s| *s),
+                library_pi: library_pi.map(|s| *s),
+                area_metadata: area_metadata.map(|s| *s),
+                set_error_handler: set_error_handler.map(|s| *s),
+                add_ints: add_ints.map(|s| *s),
+                multiply_ints: multiply_ints.map(|s| *s),
+                path: path.to_path_buf(),
+                id,
+                error_handler: Arc::new(error_handlers::HandlerGuard::new(id)),
+                options: *options,
+                default_callback: Arc::new(RwLock::new(None)),
+                named_callbacks: Arc::default(),
+                #[cfg(feature = "metrics")]
+                metrics: Arc::default(),
+                runtime,
+                lib,
+                temp_file: None,
+            })
+        }
+    }
+
+    /// Creates a Go number generator that keeps this library loaded for as long as it
+    /// exists, so it can be stored, moved and returned independently of this
+    /// `CircleLibrary` value.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the library does not export
+    /// `CreateNumberGenerator`.
+    pub fn number_generator(&self) -> Result<NumberGenerator, LibraryError> {
+        let _call = self.track("number_generator");
+        NumberGenerator::with_runtime(&self.lib, Arc::clone(&self.runtime))
+    }
+
+    /// Returns the raw function pointers resolved from the library.
+    ///
+    /// ```no_run
+    /// # use go_rust_ffi::CircleLibrary;
+    /// # let circle_lib = CircleLibrary::new("lib.dll")?;
+    /// let raw = unsafe { circle_lib.raw_symbols() };
+    /// // `circle_lib` is still alive here, so the library is loaded and the pointer valid.
+    /// let area = unsafe { (raw.calculate_circle_area)(2.0) };
+    /// # Ok::<(), go_rust_ffi::error::LibraryError>(())
+    /// ```
+    ///
+    /// # Safety
+    /// The pointers are only valid while the library stays loaded, i.e. while this
+    /// `CircleLibrary` or one of its clones is alive; calling one afterwards is undefined
+    /// behavior. Calls through them bypass every check the safe methods make, and the
+    /// caller is responsible for upholding the contract of each Go export.
+    pub unsafe fn raw_symbols(&self) -> RawSymbols {
+        RawSymbols {
+            calculate_circle_area: self.calculate_circle_area,
+            calculate_struct_area: self.calculate_struct_area,
+            format_circle_info: self.format_circle_info,
+            free_string: self.free_string,
+            call_callback: self.call_callback,
+            calculate_shape_area: self.calculate_shape_area,
+            calculate_circle_area_f32: self.calculate_circle_area_f32,
+            call_int_callback: self.call_int_callback,
+        }
+    }
+
+    /// Returns the path the library was loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns whether the library exports a symbol with the given name.
+    ///
+    /// The symbol is looked up and immediately discarded, so this never fails.
+    pub fn has_symbol(&self, name: &str) -> bool {
+        unsafe {
+            self.lib
+                .get::<unsafe extern "C" fn()>(name.as_bytes())
+                .is_ok()
+        }
+    }
+
+    /// Returns how often each method has been called and the total time spent in it,
+    /// counted across all clones of this library.
+    ///
+    /// Every public method that calls into Go is counted. Methods that only wrap another
+    /// public method, such as `calculate_circle_area_stream`, `try_calculate_shape_area` or
+    /// `timed`, are counted under the method they wrap. Methods that never call into Go,
+    /// such as `capabilities` or `pending_async_calls`, are not counted.
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> metrics::CallStats {
+        self.metrics.snapshot()
+    }
+
+    /// Starts timing a call to `method` for `stats`.
+    #[cfg(feature = "metrics")]
+    fn track(&self, method: &'static str) -> metrics::CallTimer<'_> {
+        self.metrics.start(method)
+    }
+
+    /// Without the `metrics` feature nothing is recorded.
+    #[cfg(not(feature = "metrics"))]
+    fn track(&self, _method: &'static str) -> NoopTimer {
+        NoopTimer
+    }
+
+    /// Reports which optional functions were resolved when the library was loaded.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            circle_area_async: self.calculate_circle_area_async.is_some(),
+            circle_area_async_multi: self.calculate_circle_area_async_multiple.is_some(),
+            circle_area_async_progress: self.calculate_circle_area_async_progress.is_some(),
+            circle_area_poll: self.calculate_circle_area_poll.is_some(),
+            flush_async: self.flush_async.is_some(),
+            shape_area: self.calculate_shape_area.is_some(),
+            shape_area_checked: self.calculate_shape_area_checked.is_some(),
+            circle_area_f32: self.calculate_circle_area_f32.is_some(),
+            circle_areas_batch: self.calculate_circle_areas_batch.is_some(),
+            shape_areas_batch: self.calculate_shape_areas_batch.is_some(),
+            sum_areas: self.sum_areas.is_some(),
+            normalize_shapes: self.normalize_shapes.is_some(),
+            circle_details: self.get_circle_details.is_some() && self.free_circle_details.is_some(),
+            labeled_info: self.format_circle_info_labeled.is_some(),
+            info_precision: self.format_circle_info_prec.is_some(),
+            callback_with_context: self.call_callback_with_context.is_some(),
+            callback_concurrent: self.call_callback_concurrent.is_some(),
+            named_callback: self.call_named_callback.is_some(),
+            int_callback: self.call_int_callback.is_some(),
+            shape_transform: self.transform_shape.is_some(),
+            circumference: self.calculate_circumference.is_some(),
+            diameter: self.calculate_diameter.is_some(),
+            map_radii: self.map_radii.is_some() && self.free_double_array.is_some(),
+            circle_infos: self.format_circle_infos.is_some() && self.free_string_array.is_some(),
+            generate_shapes: self.generate_shapes.is_some() && self.free_shapes.is_some(),
+            pi: self.library_pi.is_some(),
+            area_metadata: self.area_metadata.is_some(),
+            error_handler: self.set_error_handler.is_some(),
+            int_arithmetic: self.add_ints.is_some() && self.multiply_ints.is_some(),
+        }
+    }
+
+    /// Calculates the area of a circle given the radius.
+    ///
+    /// # Arguments
+    /// * `radius` - The circle's radius.
+    ///
+    /// # Returns
+    /// The computed area as an `f64`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn calculate_circle_area(&self, radius: f64) -> Result<f64, LibraryError> {
+        let _call = self.track("calculate_circle_area");
+        validate_dimension("radius", radius)?;
+        // Call Go directly, so the call is not counted under the unchecked variant too.
+        Ok(unsafe { (self.calculate_circle_area)(radius) })
+    }
+
+    /// Like `calculate_circle_area`, but also returns the wall-clock time spent in the FFI
+    /// call.
+    ///
+    /// Only the call into Go is timed, not the validation of `radius`. Use `timed` to
+    /// measure any other synchronous method.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.timed_calculate_circle_area",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn timed_calculate_circle_area(
+        &self,
+        radius: f64,
+    ) -> Result<(f64, Duration), LibraryError> {
+        let _call = self.track("timed_calculate_circle_area");
+        validate_dimension("radius", radius)?;
+        Ok(self.timed(|lib| unsafe { (lib.calculate_circle_area)(radius) }))
+    }
+
+    /// Runs `call` on this library and returns its result together with the wall-clock
+    /// time it took.
+    ///
+    /// ```ignore
+    /// let (info, elapsed) = circle_lib.timed(|lib| lib.format_circle_info(2.0));
+    /// println!("{:?} took {:?}", info?, elapsed);
+    /// ```
+    pub fn timed<T>(&self, call: impl FnOnce(&Self) -> T) -> (T, Duration) {
+        let started = Instant::now();
+        let result = call(self);
+        (result, started.elapsed())
+    }
+
+    /// Calculates the area of a circle without validating the radius.
+    ///
+    /// Negative, NaN and infinite radii are passed to Go as is, and the result is whatever
+    /// Go computes for them.
+    pub fn calculate_circle_area_unchecked(&self, radius: f64) -> f64 {
+        let _call = self.track("calculate_circle_area_unchecked");
+        unsafe { (self.calculate_circle_area)(radius) }
+    }
+
+    /// Registers `handler` to receive the messages of errors reported by the Go library,
+    /// e.g. an unknown shape type or generator id.
+    ///
+    /// The handler may be invoked from any thread. With the `tracing` feature enabled,
+    /// every reported error is also emitted as a `tracing::error!` event. The Go library
+    /// keeps a single handler, so the most recent registration for the loaded library wins,
+    /// even across separately constructed `CircleLibrary` values. The handler is dropped
+    /// once the last clone of this library is dropped; errors reported after that are
+    /// ignored.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the library does not export
+    /// `SetErrorHandler`.
+    pub fn on_error<F>(&self, handler: F) -> Result<(), LibraryError>
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        let _call = self.track("on_error");
+        let set_error_handler = require_symbol(self.set_error_handler, "SetErrorHandler")?;
+        error_handlers::set(self.id, Arc::new(handler));
+        unsafe {
+            set_error_handler(error_handler_trampoline, self.id as *mut c_void);
+        }
+        Ok(())
+    }
+
+    /// Adds two integers using the Go library.
+    ///
+    /// Go's C `int` arithmetic wraps around on overflow, so the result is checked against
+    /// `i32::checked_add`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Overflow` if the sum does not fit in an `i32`, or
+    /// `LibraryError::SymbolMissing` if the library does not export `AddInts`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.add_ints", level = "debug", skip(self))
+    )]
+    pub fn add_ints(&self, a: i32, b: i32) -> Result<i32, LibraryError> {
+        let _call = self.track("add_ints");
+        let add_ints = require_symbol(self.add_ints, "AddInts")?;
+        let result = unsafe { add_ints(a, b) };
+        check_int_result("AddInts", result, a.checked_add(b))
+    }
+
+    /// Multiplies two integers using the Go library.
+    ///
+    /// Go's C `int` arithmetic wraps around on overflow, so the result is checked against
+    /// `i32::checked_mul`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Overflow` if the product does not fit in an `i32`, or
+    /// `LibraryError::SymbolMissing` if the library does not export `MultiplyInts`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.multiply_ints", level = "debug", skip(self))
+    )]
+    pub fn multiply_ints(&self, a: i32, b: i32) -> Result<i32, LibraryError> {
+        let _call = self.track("multiply_ints");
+        let multiply_ints = require_symbol(self.multiply_ints, "MultiplyInts")?;
+        let result = unsafe { multiply_ints(a, b) };
+        check_int_result("MultiplyInts", result, a.checked_mul(b))
+    }
+
+    /// Returns the value of π the Go library computes with.
+    ///
+    /// Go results can then be checked exactly, e.g. `calculate_circle_area(1.0)` equals
+    /// `pi()`. Falls back to `std::f64::consts::PI` if the library does not export
+    /// `LibraryPi`.
+    pub fn pi(&self) -> f64 {
+        let _call = self.track("pi");
+        match self.library_pi {
+            Some(library_pi) => unsafe { library_pi() },
+            None => std::f64::consts::PI,
+        }
+    }
+
+    /// Returns the precision and unit the Go library computes areas with, or `None` if the
+    /// library does not export `AreaMetadata`.
+    pub fn area_metadata(&self) -> Option<AreaMeta> {
+        let _call = self.track("area_metadata");
+        self.area_metadata
+            .map(|area_metadata| unsafe { area_metadata() })
+    }
+
+    /// Calculates the circumference of a circle given the radius.
+    ///
+    /// Uses the Go `CalculateCircumference` export when available, so the result uses the
+    /// same value of π as `calculate_circle_area`, and computes `2πr` in Rust otherwise.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.circumference", level = "debug", skip(self))
+    )]
+    pub fn circumference(&self, radius: f64) -> f64 {
+        let _call = self.track("circumference");
+        match self.calculate_circumference {
+            Some(calculate_circumference) => unsafe { calculate_circumference(radius) },
+            None => 2.0 * std::f64::consts::PI * radius,
+        }
+    }
+
+    /// Calculates the diameter of a circle given the radius.
+    ///
+    /// Uses the Go `CalculateDiameter` export when available and computes `2r` in Rust
+    /// otherwise.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.diameter", level = "debug", skip(self))
+    )]
+    pub fn diameter(&self, radius: f64) -> f64 {
+        let _call = self.track("diameter");
+        match self.calculate_diameter {
+            Some(calculate_diameter) => unsafe { calculate_diameter(radius) },
+            None => 2.0 * radius,
+        }
+    }
+
+    /// Calculates the area of a circle in single precision.
+    ///
+    /// This binds the Go `float32` entry point so callers working in `f32` (e.g. graphics
+    /// code) avoid converting back and forth. The computation happens in single precision
+    /// on the Go side, so the result carries only about 7 significant digits and may
+    /// differ from `calculate_circle_area` beyond that.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the library does not export
+    /// `CalculateCircleAreaF32`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area_f32",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn calculate_circle_area_f32(&self, radius: f32) -> Result<f32, LibraryError> {
+        let _call = self.track("calculate_circle_area_f32");
+        let calculate_circle_area_f32 =
+            require_symbol(self.calculate_circle_area_f32, "CalculateCircleAreaF32")?;
+        Ok(unsafe { calculate_circle_area_f32(radius) })
+    }
+
+    /// Calculates the areas of many circles with a single FFI call.
+    ///
+    /// The radii are passed to `CalculateCircleAreasBatch` as a pointer and length, and Go
+    /// writes the results into a buffer of the same length. Empty input returns an empty
+    /// vector without calling into Go. If the library does not export the batch function,
+    /// this falls back to calling `CalculateCircleArea` once per radius.
+    ///
+    /// Like `calculate_circle_area_unchecked`, the radii are not validated.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_areas",
+            level = "debug",
+            skip(self, radii),
+            fields(count = radii.len())
+        )
+    )]
+    pub fn calculate_circle_areas(&self, radii: &[f64]) -> Vec<f64> {
+        let _call = self.track("calculate_circle_areas");
+        if radii.is_empty() {
+            return Vec::new();
+        }
+        match self.calculate_circle_areas_batch {
+            Some(calculate_circle_areas_batch) => {
+                let mut areas = vec![0.0; radii.len()];
+                unsafe {
+                    calculate_circle_areas_batch(
+                        radii.as_ptr(),
+                        go_len(radii.len()),
+                        areas.as_mut_ptr(),
+                    );
+                }
+                areas
+            }
+            None => radii
+                .iter()
+                .map(|&radius| unsafe { (self.calculate_circle_area)(radius) })
+                .collect(),
+        }
+    }
+
+    /// Sums the given areas using the Go library.
+    ///
+    /// `SumAreas` takes a pointer and a C `int` count in place of variadic arguments.
+    /// Empty input returns 0.0 without calling into Go, since Go cannot be handed a null
+    /// pointer, and slices longer than `c_int::MAX` are summed in chunks. If the library
+    /// does not export `SumAreas`, the areas are summed in Rust instead.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.sum_areas",
+            level = "debug",
+            skip(self, areas),
+            fields(count = areas.len())
+        )
+    )]
+    pub fn sum_areas(&self, areas: &[f64]) -> f64 {
+        let _call = self.track("sum_areas");
+        match self.sum_areas {
+            Some(sum_areas) => areas
+                .chunks(c_int::MAX as usize)
+                // Chunks are never empty and at most `c_int::MAX` long.
+                .map(|chunk| unsafe { sum_areas(chunk.as_ptr(), chunk.len() as c_int) })
+                .sum(),
+            None => areas.iter().sum(),
+        }
+    }
+
+    /// A safe method that accepts a reference to a Circle and returns its area.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if the radius is negative, NaN or infinite.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_struct_area",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn calculate_circle_struct_area(&self, circle: &Circle) -> Result<f64, LibraryError> {
+        let _call = self.track("calculate_circle_struct_area");
+        validate_dimension("radius", circle.radius)?;
+        // The external function expects the struct by value.
+        Ok(unsafe { (self.calculate_struct_area)(*circle) })
+    }
+
+    /// Returns a formatted string with circle information.
+    ///
+    /// This method handles pointer conversion and memory management internally.
+    ///
+    /// # Arguments
+    /// * `radius` - The circle's radius.
+    ///
+    /// # Returns
+    /// A safe `String` containing the formatted message.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::NullPointer` if the library returns a null pointer, or
+    /// `LibraryError::Utf8` if the returned text is not valid UTF-8.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.format_circle_info", level = "debug", skip(self))
+    )]
+    pub fn format_circle_info(&self, radius: f64) -> Result<String, LibraryError> {
+        let _call = self.track("format_circle_info");
+        unsafe {
+            let c_ptr = (self.format_circle_info)(radius);
+            self.take_go_string(c_ptr, "FormatCircleInfo")
+        }
+    }
+
+    /// Writes formatted circle information into `buf`, replacing its previous contents.
+    ///
+    /// Unlike `format_circle_info`, this reuses the caller's allocation, so formatting in a
+    /// loop with the same buffer only allocates when the text outgrows it. The Go
+    /// allocation is still freed before returning.
+    ///
+    /// # Errors
+    /// The same errors as `format_circle_info`; `buf` is left empty on error.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.format_circle_info_into",
+            level = "debug",
+            skip(self, buf)
+        )
+    )]
+    pub fn format_circle_info_into(
+        &self,
+        radius: f64,
+        buf: &mut String,
+    ) -> Result<(), LibraryError> {
+        let _call = self.track("format_circle_info_into");
+        buf.clear();
+        unsafe {
+            let c_ptr = (self.format_circle_info)(radius);
+            self.read_go_string(c_ptr, "FormatCircleInfo", |text| buf.push_str(text))
+        }
+    }
+
+    /// Returns a formatted string with circle information without copying it.
+    ///
+    /// The returned `GoString` borrows the Go allocation directly and frees it through
+    /// `FreeString` when dropped, so callers that only read the bytes avoid the copy
+    /// `format_circle_info` makes.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::NullPointer` if the library returns a null pointer.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.format_circle_info_owned",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn format_circle_info_owned(&self, radius: f64) -> Result<GoString, LibraryError> {
+        let _call = self.track("format_circle_info_owned");
+        let c_ptr = unsafe { (self.format_circle_info)(radius) };
+        match NonNull::new(c_ptr) {
+            Some(ptr) => Ok(GoString {
+                ptr,
+                free_string: self.owned_string_free(),
+                _lib: Arc::clone(&self.lib),
+            }),
+            None => Err(LibraryError::NullPointer {
+                function: "FormatCircleInfo".into(),
+            }),
+        }
+    }
+
+    /// Returns formatted circle information prefixed with a caller-provided label.
+    ///
+    /// The label is converted to a NUL-terminated C string for the call; labels containing
+    /// an interior NUL byte are rejected rather than silently truncated.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InteriorNul` if `label` contains a NUL byte,
+    /// `LibraryError::SymbolMissing` if the library does not export
+    /// `FormatCircleInfoLabeled`, or the same errors as `format_circle_info`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.format_circle_info_labeled",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn format_circle_info_labeled(
+        &self,
+        label: &str,
+        radius: f64,
+    ) -> Result<String, LibraryError> {
+        let _call = self.track("format_circle_info_labeled");
+        let format_circle_info_labeled =
+            require_symbol(self.format_circle_info_labeled, "FormatCircleInfoLabeled")?;
+        let c_label = CString::new(label)?;
+        unsafe {
+            // `c_label` stays alive until the call returns; Go copies it before then.
+            let c_ptr = format_circle_info_labeled(c_label.as_ptr(), radius);
+            self.take_go_string(c_ptr, "FormatCircleInfoLabeled")
+        }
+    }
+
+    /// Returns formatted circle information with `decimals` decimal places instead of two.
+    ///
+    /// Go formats the numbers itself, so the result is rounded the same way as
+    /// `format_circle_info` rather than re-formatted from its output.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `decimals` does not fit in a C `int`,
+    /// `LibraryError::SymbolMissing` if the library does not export `FormatCircleInfoPrec`,
+    /// or the same errors as `format_circle_info`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.format_circle_info_prec",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn format_circle_info_prec(
+        &self,
+        radius: f64,
+        decimals: usize,
+    ) -> Result<String, LibraryError> {
+        let _call = self.track("format_circle_info_prec");
+        let format_circle_info_prec =
+            require_symbol(self.format_circle_info_prec, "FormatCircleInfoPrec")?;
+        let decimals = c_int::try_from(decimals).map_err(|_| {
+            LibraryError::InvalidInput(format!("{} decimal places is too many", decimals))
+        })?;
+        unsafe {
+            let c_ptr = format_circle_info_prec(radius, decimals);
+            self.take_go_string(c_ptr, "FormatCircleInfoPrec")
+        }
+    }
+
+    /// Converts a Go-allocated C string into a Rust `String` and frees the Go allocation.
+    ///
+    /// # Safety
+    /// `c_ptr` must be null or a string allocated by the library that may be released
+    /// with `FreeString`; it must not be used after this call.
+    unsafe fn take_go_string(
+        &self,
+        c_ptr: *mut c_char,
+        function: &str,
+    ) -> Result<String, LibraryError> {
+        self.read_go_string(c_ptr, function, str::to_owned)
+    }
+
+    /// Passes a Go-allocated C string to `read` as a `&str`, then frees the Go allocation.
+    ///
+    /// # Safety
+    /// Same requirements as `take_go_string`.
+    unsafe fn read_go_string<R>(
+        &self,
+        c_ptr: *mut c_char,
+        function: &str,
+        read: impl FnOnce(&str) -> R,
+    ) -> Result<R, LibraryError> {
+        if c_ptr.is_null() {
+            return Err(LibraryError::NullPointer {
+                function: function.into(),
+            });
+        }
+        let c_str = CStr::from_ptr(c_ptr);
+        let result = c_str.to_str().map(read);
+        // Free the allocated string in the Go library, even if the conversion failed.
+        if let Some(free_string) = self.owned_string_free() {
+            free_string(c_ptr);
+        }
+        Ok(result?)
+    }
+
+    /// Returns whether strings returned by this library are owned by the caller or static.
+    pub fn string_ownership(&self) -> StringOwnership {
+        self.string_ownership
+    }
+
+    /// Returns `FreeString` if returned strings must be freed, and `None` if they are static.
+    fn owned_string_free(&self) -> Option<unsafe extern "C" fn(*mut c_char)> {
+        match self.string_ownership {
+            StringOwnership::Owned => Some(self.free_string),
+            StringOwnership::Static => None,
+        }
+    }
+
+    /// Returns the area, circumference and diameter of a circle computed by Go.
+    ///
+    /// The Go side allocates the `CircleDetails` struct; this method copies it into a
+    /// Rust-owned value and frees the Go allocation before returning, so the raw pointer
+    /// never escapes.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::NullPointer` if the library returns a null pointer, or
+    /// `LibraryError::SymbolMissing` if `GetCircleDetails` or `FreeCircleDetails` is not
+    /// exported.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.get_circle_details", level = "debug", skip(self))
+    )]
+    pub fn get_circle_details(&self, radius: f64) -> Result<CircleDetails, LibraryError> {
+        let _call = self.track("get_circle_details");
+        let get_circle_details = require_symbol(self.get_circle_details, "GetCircleDetails")?;
+        let free_circle_details = require_symbol(self.free_circle_details, "FreeCircleDetails")?;
+        unsafe {
+            let details_ptr = get_circle_details(radius);
+            if details_ptr.is_null() {
+                return Err(LibraryError::NullPointer {
+                    function: "GetCircleDetails".into(),
+                });
+            }
+            // Copy the struct out before releasing the Go allocation.
+            let details = *details_ptr;
+            free_circle_details(details_ptr);
+            Ok(details)
+        }
+    }
+
+    /// Sends `radii` to Go and returns the array it computes from them.
+    ///
+    /// Go maps each radius to the running total of the circle areas up to and including
+    /// it. The result array is allocated by Go; this method copies it into a `Vec` and
+    /// frees the Go allocation before returning. Empty input returns an empty vector
+    /// without calling into Go.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::NullPointer` if the library returns a null pointer, or
+    /// `LibraryError::SymbolMissing` if `MapRadii` or `FreeDoubleArray` is not exported.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.map_radii",
+            level = "debug",
+            skip(self, radii),
+            fields(count = radii.len())
+        )
+    )]
+    pub fn map_radii(&self, radii: &[f64]) -> Result<Vec<f64>, LibraryError> {
+        let _call = self.track("map_radii");
+        let map_radii = require_symbol(self.map_radii, "MapRadii")?;
+        let free_double_array = require_symbol(self.free_double_array, "FreeDoubleArray")?;
+        if radii.is_empty() {
+            return Ok(Vec::new());
+        }
+        unsafe {
+            let array_ptr = map_radii(radii.as_ptr(), go_len(radii.len()));
+            if array_ptr.is_null() {
+                return Err(LibraryError::NullPointer {
+                    function: "MapRadii".into(),
+                });
+            }
+            // Copy the values out before releasing the Go allocation.
+            let DoubleArray { data, len } = *array_ptr;
+            let values = if data.is_null() || len == 0 {
+                Vec::new()
+            } else {
+                std::slice::from_raw_parts(data, len).to_vec()
+            };
+            free_double_array(array_ptr);
+            Ok(values)
+        }
+    }
+
+    /// Returns the formatted circle information for every radius in `radii`, in order.
+    ///
+    /// Go formats all strings in a single call, like `format_circle_info` does for one
+    /// radius. The array and its strings are allocated by Go; this method copies them into
+    /// `String`s and frees the Go allocation through `FreeStringArray` before returning,
+    /// whether or not the conversion succeeded. Empty input returns an empty vector without
+    /// calling into Go.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::NullPointer` if the library returns a null array or a null
+    /// entry, `LibraryError::Utf8` if a string is not valid UTF-8, or
+    /// `LibraryError::SymbolMissing` if `FormatCircleInfos` or `FreeStringArray` is not
+    /// exported.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.format_circle_infos",
+            level = "debug",
+            skip(self, radii),
+            fields(count = radii.len())
+        )
+    )]
+    pub fn format_circle_infos(&self, radii: &[f64]) -> Result<Vec<String>, LibraryError> {
+        let _call = self.track("format_circle_infos");
+        let format_circle_infos = require_symbol(self.format_circle_infos, "FormatCircleInfos")?;
+        let free_string_array = require_symbol(self.free_string_array, "FreeStringArray")?;
+        if radii.is_empty() {
+            return Ok(Vec::new());
+        }
+        unsafe {
+            let array_ptr = format_circle_infos(radii.as_ptr(), go_len(radii.len()));
+            if array_ptr.is_null() {
+                return Err(LibraryError::NullPointer {
+                    function: "FormatCircleInfos".into(),
+                });
+            }
+            // Copy the strings out before releasing the Go allocation.
+            let StringArray { data, len } = *array_ptr;
+            let entries: &[*mut c_char] = if data.is_null() || len == 0 {
+                &[]
+            } else {
+                std::slice::from_raw_parts(data, len)
+            };
+            let infos = entries
+                .iter()
+                .map(|&entry| {
+                    if entry.is_null() {
+                        return Err(LibraryError::NullPointer {
+                            function: "FormatCircleInfos".into(),
+                        });
+                    }
+                    Ok(CStr::from_ptr(entry).to_str()?.to_owned())
+                })
+                .collect();
+            free_string_array(array_ptr);
+            infos
+        }
+    }
+
+    /// Asks Go to generate up to `count` shapes and returns the ones it produced.
+    ///
+    /// Go may produce fewer shapes than requested. The array is allocated by Go; this
+    /// method checks that every shape carries a known `ShapeType`, copies them into a
+    /// `Vec` and frees the Go allocation before returning. A `count` of zero returns an
+    /// empty vector without calling into Go.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::NullPointer` if Go reports shapes but returns a null pointer,
+    /// `LibraryError::InvalidInput` if a shape has an unknown type, or
+    /// `LibraryError::SymbolMissing` if `GenerateShapes` or `FreeShapes` is not exported.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.generate_shapes", level = "debug", skip(self))
+    )]
+    pub fn generate_shapes(&self, count: usize) -> Result<Vec<Shape>, LibraryError> {
+        let _call = self.track("generate_shapes");
+        let generate_shapes = require_symbol(self.generate_shapes, "GenerateShapes")?;
+        let free_shapes = require_symbol(self.free_shapes, "FreeShapes")?;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        unsafe {
+            let mut produced = 0;
+            let shapes_ptr = generate_shapes(go_len(count), &mut produced);
+            if shapes_ptr.is_null() {
+                return if produced == 0 {
+                    Ok(Vec::new())
+                } else {
+                    Err(LibraryError::NullPointer {
+                        function: "GenerateShapes".into(),
+                    })
+                };
+            }
+            // Never read past what was asked for, whatever Go reports.
+            let len = usize::try_from(produced).unwrap_or(0).min(count);
+            // Check the raw discriminants before any `Shape` value is formed from them.
+            let checked = (0..len).try_for_each(|i| validate_shape_type(shapes_ptr.add(i)));
+            let shapes = checked.map(|()| std::slice::from_raw_parts(shapes_ptr, len).to_vec());
+            free_shapes(shapes_ptr, produced);
+            shapes
+        }
+    }
+
+    /// Calls a callback function using the Go library.
+    ///
+    /// The callback is provided as an extern "C" function pointer.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.call_callback",
+            level = "debug",
+            skip(self, callback)
+        )
+    )]
+    pub fn call_callback(&self, val: f64, callback: CallbackType) -> f64 {
+        let _call = self.track("call_callback");
+        unsafe { (self.call_callback)(val, callback) }
+    }
+
+    /// Calls the shared library’s callback function.
+    ///
+    /// Instead of forcing the user to provide an `extern "C" fn`, this method accepts
+    /// any Rust closure with signature `Fn(f64) -> f64`. Internally, the closure is pushed
+    /// onto a thread-local stack and an `extern "C"` trampoline is passed to the FFI call.
+    ///
+    /// This design hides all unsafe details and pointer manipulations from the user.
+    /// Because the storage is per-thread, concurrent calls from different threads are
+    /// independent, and the closure may itself call `call_callback_with` again.
+    ///
+    /// # Panics
+    /// If the closure panics, the panic is caught before it can unwind into Go, the Go
+    /// function receives `0.0`, and the panic is resumed here once the FFI call returns.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.call_callback_with",
+            level = "debug",
+            skip(self, callback)
+        )
+    )]
+    pub fn call_callback_with<F>(&self, val: f64, callback: F) -> f64
+    where
+        F: Fn(f64) -> f64 + Send + 'static,
+    {
+        let _call = self.track("call_callback_with");
+        // Call the FFI function with a trampoline forwarding to our closure.
+        with_callback(callback, |trampoline| unsafe {
+            (self.call_callback)(val, trampoline)
+        })
+    }
+
+    /// Registers `callback` as the closure used by `call_callback_default`, replacing any
+    /// previously registered one.
+    ///
+    /// The closure is shared by all clones of this library, so it must be `Send + Sync`.
+    /// It coexists with `call_callback_with`: per-call closures are unaffected by it.
+    pub fn set_default_callback<F>(&self, callback: F)
+    where
+        F: Fn(f64) -> f64 + Send + Sync + 'static,
+    {
+        *self
+            .default_callback
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(callback));
+    }
+
+    /// Removes the closure registered with `set_default_callback`.
+    pub fn clear_default_callback(&self) {
+        *self
+            .default_callback
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// Calls the shared library’s callback function with the closure registered through
+    /// `set_default_callback`.
+    ///
+    /// This suits tight loops that invoke the same logic repeatedly: the closure is built
+    /// once and each call only clones a shared handle to it.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if no default callback has been registered.
+    ///
+    /// # Panics
+    /// Panics raised by the closure are resumed here, as with `call_callback_with`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.call_callback_default",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn call_callback_default(&self, val: f64) -> Result<f64, LibraryError> {
+        let _call = self.track("call_callback_default");
+        let callback = self
+            .default_callback
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .ok_or_else(|| LibraryError::InvalidInput("no default callback is set".into()))?;
+        Ok(with_callback(
+            move |x| callback(x),
+            |trampoline| unsafe { (self.call_callback)(val, trampoline) },
+        ))
+    }
+
+    /// Registers `callback` under `name`, replacing any closure previously registered under
+    /// the same name.
+    ///
+    /// Registered closures are invoked by `call_named_callback` when Go calls back with
+    /// their name. They are shared by all clones of this library, so they must be
+    /// `Send + Sync`.
+    pub fn register_callback<F>(&self, name: &str, callback: F)
+    where
+        F: Fn(f64) -> f64 + Send + Sync + 'static,
+    {
+        self.named_callbacks
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.to_string(), Arc::new(callback));
+    }
+
+    /// Removes the closure registered under `name`, returning whether there was one.
+    pub fn unregister_callback(&self, name: &str) -> bool {
+        self.named_callbacks
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(name)
+            .is_some()
+    }
+
+    /// Asks Go to invoke the callback slot `name` with `val` and returns its result.
+    ///
+    /// Go calls back through a single trampoline, passing the slot name along, and the
+    /// trampoline dispatches to the closure registered under that name with
+    /// `register_callback`. Names Go passes that have no registered closure yield `0.0`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if no closure is registered under `name`,
+    /// `LibraryError::InteriorNul` if `name` contains a NUL byte, or
+    /// `LibraryError::SymbolMissing` if the library does not export `CallNamedCallback`.
+    ///
+    /// # Panics
+    /// Panics raised by the closure are resumed here, as with `call_callback_with`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.call_named_callback", level = "debug", skip(self))
+    )]
+    pub fn call_named_callback(&self, name: &str, val: f64) -> Result<f64, LibraryError> {
+        let _call = self.track("call_named_callback");
+        let call_named_callback = require_symbol(self.call_named_callback, "CallNamedCallback")?;
+        // Dispatch on a snapshot, so closures may register or remove callbacks themselves.
+        let callbacks = self
+            .named_callbacks
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        if !callbacks.contains_key(name) {
+            return Err(LibraryError::InvalidInput(format!(
+                "no callback is registered under `{}`",
+                name
+            )));
+        }
+        let c_name = CString::new(name)?;
+        let dispatch = move |val: c_double, name: *const c_char| {
+            if name.is_null() {
+                return 0.0;
+            }
+            let name = unsafe { CStr::from_ptr(name) }.to_string_lossy();
+            callbacks
+                .get(name.as_ref())
+                .map_or(0.0, |callback| callback(val))
+        };
+        Ok(with_named_callback(dispatch, |trampoline| unsafe {
+            call_named_callback(val, c_name.as_ptr(), trampoline)
+        }))
+    }
+
+    /// Calls the shared library’s callback function with a closure that may fail.
+    ///
+    /// When the closure returns `Err`, the trampoline hands Go `NaN`, the conventional
+    /// failure value for the `double`-returning callback, and the first error is returned
+    /// from this method once the FFI call completes. The closure is removed from the
+    /// callback store whether or not it failed.
+    ///
+    /// # Errors
+    /// Returns the first `CallbackError` produced by the closure during the call.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.call_callback_try_with",
+            level = "debug",
+            skip(self, callback)
+        )
+    )]
+    pub fn call_callback_try_with<F>(&self, val: f64, callback: F) -> Result<f64, CallbackError>
+    where
+        F: Fn(f64) -> Result<f64, CallbackError> + Send + 'static,
+    {
+        let _call = self.track("call_callback_try_with");
+        // The wrapper closure records the first error; the slot is local to this call.
+        let error_slot: Rc<RefCell<Option<CallbackError>>> = Rc::new(RefCell::new(None));
+        let wrapper_slot = Rc::clone(&error_slot);
+        let result = with_callback(
+            move |x| match callback(x) {
+                Ok(value) => value,
+                Err(e) => {
+                    wrapper_slot.borrow_mut().get_or_insert(e);
+                    f64::NAN
+                }
+            },
+            |trampoline| unsafe { (self.call_callback)(val, trampoline) },
+        );
+        let error = error_slot.borrow_mut().take();
+        match error {
+            Some(e) => Err(e),
+            None => Ok(result),
+        }
+    }
+
+    /// Calls the shared library’s integer callback function with a Rust closure.
+    ///
+    /// This works like `call_callback_with`, but for `Fn(i32) -> i32` closures passed to
+    /// `CallIntCallback`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the library does not export
+    /// `CallIntCallback`.
+    ///
+    /// # Panics
+    /// If the closure panics, the panic is caught before it can unwind into Go, the Go
+    /// function receives `0`, and the panic is resumed here once the FFI call returns.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.call_int_callback_with",
+            level = "debug",
+            skip(self, callback)
+        )
+    )]
+    pub fn call_int_callback_with<F>(&self, val: i32, callback: F) -> Result<i32, LibraryError>
+    where
+        F: Fn(i32) -> i32 + Send + 'static,
+    {
+        let _call = self.track("call_int_callback_with");
+        let call_int_callback = require_symbol(self.call_int_callback, "CallIntCallback")?;
+        Ok(with_int_callback(callback, |trampoline| unsafe {
+            call_int_callback(val, trampoline)
+        }))
+    }
+
+    /// Passes `shape` to Go, which calls `transform` with it and returns the shape it gets
+    /// back.
+    ///
+    /// The shape crosses the boundary by value in both directions; `Shape` has C layout
+    /// matching the Go struct. The closure is routed through a thread-local stack like
+    /// `call_callback_with`, so nested and concurrent calls are independent.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the library does not export
+    /// `TransformShape`.
+    ///
+    /// # Panics
+    /// If the closure panics, the panic is caught before it can unwind into Go, the Go
+    /// function receives the input shape unchanged, and the panic is resumed here once the
+    /// FFI call returns.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.transform_shape_with",
+            level = "debug",
+            skip(self, transform)
+        )
+    )]
+    pub fn transform_shape_with<F>(&self, shape: Shape, transform: F) -> Result<Shape, LibraryError>
+    where
+        F: Fn(Shape) -> Shape + Send + 'static,
+    {
+        let _call = self.track("transform_shape_with");
+        let transform_shape = require_symbol(self.transform_shape, "TransformShape")?;
+        Ok(with_shape_callback(transform, |trampoline| unsafe {
+            transform_shape(shape, trampoline)
+        }))
+    }
+
+    /// Has Go invoke `f` once per value in `vals`, each from its own goroutine, and returns
+    /// the results in the order of `vals`.
+    ///
+    /// The goroutines call back concurrently from arbitrary threads, where the thread-local
+    /// store behind `call_callback_with` is not visible. The closure is therefore passed to
+    /// the trampoline through the user data pointer instead, and must be `Sync` since it is
+    /// shared between the calling threads. Go waits for every goroutine before returning
+    /// and does not keep the pointer, so `f` is only borrowed for the duration of the call.
+    /// Empty input returns an empty vector without calling into Go.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the library does not export
+    /// `CallCallbackConcurrent`.
+    ///
+    /// # Panics
+    /// If the closure panics on any goroutine, that goroutine's result is discarded and the
+    /// first panic is resumed here once every goroutine has finished.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.call_callback_concurrent",
+            level = "debug",
+            skip(self, vals, f),
+            fields(count = vals.len())
+        )
+    )]
+    pub fn call_callback_concurrent<F>(&self, vals: &[f64], f: F) -> Result<Vec<f64>, LibraryError>
+    where
+        F: Fn(f64) -> f64 + Sync,
+    {
+        let _call = self.track("call_callback_concurrent");
+        let call_callback_concurrent =
+            require_symbol(self.call_callback_concurrent, "CallCallbackConcurrent")?;
+        if vals.is_empty() {
+            return Ok(Vec::new());
+        }
+        let context = ConcurrentContext {
+            f,
+            panic: Mutex::new(None),
+        };
+        let user_data = &context as *const ConcurrentContext<F> as *mut c_void;
+        let mut results = vec![0.0; vals.len()];
+        unsafe {
+            call_callback_concurrent(
+                vals.as_ptr(),
+                go_len(vals.len()),
+                concurrent_trampoline::<F>,
+                user_data,
+                results.as_mut_ptr(),
+            );
+        }
+        let panic = context
+            .panic
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(payload) = panic {
+            panic::resume_unwind(payload);
+        }
+        Ok(results)
+    }
+
+    /// Calls the shared library’s callback function with a closure that can mutate `state`.
+    ///
+    /// Unlike `call_callback_with`, the closure and a `&mut S` are routed to the trampoline
+    /// through the user data pointer of `CallCallbackWithContext`, so no global or
+    /// thread-local store is involved and the closure may be `FnMut` without extra locking.
+    ///
+    /// `state` is only borrowed for the duration of this call: the Go function invokes the
+    /// callback synchronously and must not keep the user data pointer after it returns.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the library does not export
+    /// `CallCallbackWithContext`.
+    ///
+    /// # Panics
+    /// If the closure panics, the panic is caught before it can unwind into Go and resumed
+    /// here once the FFI call returns.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.call_callback_with_context",
+            level = "debug",
+            skip(self, state, f)
+        )
+    )]
+    pub fn call_callback_with_context<S, F>(
+        &self,
+        val: f64,
+        state: &mut S,
+        f: F,
+    ) -> Result<f64, LibraryError>
+    where
+        F: FnMut(&mut S, f64) -> f64,
+    {
+        let _call = self.track("call_callback_with_context");
+        let call_callback_with_context =
+            require_symbol(self.call_callback_with_context, "CallCallbackWithContext")?;
+        let mut context = CallbackContext {
+            state,
+            f,
+            panic: None,
+        };
+        let user_data = &mut context as *mut CallbackContext<'_, S, F> as *mut c_void;
+        let result =
+            unsafe { call_callback_with_context(val, context_trampoline::<S, F>, user_data) };
+        if let Some(payload) = context.panic.take() {
+            panic::resume_unwind(payload);
+        }
+        Ok(result)
+    }
+
+    /// Asynchronously calculates the area of a circle.
+    ///
+    /// This method wraps the Go asynchronous function and returns a Future that resolves
+    /// to the computed area. Internally, it creates a oneshot channel, registers the sender
+    /// as a pending call and passes the call's id as user data to the Go function.
+    ///
+    /// A dropped callback is a `LibraryError` variant rather than a separate async error
+    /// type, since the call can also fail before Go is reached, with the same errors as the
+    /// synchronous methods.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::CallbackDropped` if the sender is dropped without a result,
+    /// e.g. because the call was reclaimed, `LibraryError::InvalidInput` if `radius` is
+    /// negative, NaN or infinite, or `LibraryError::SymbolMissing` if the async symbols
+    /// were marked optional and the library does not export them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area_async",
+            level = "debug",
+            skip(self),
+            fields(request_id = tracing::field::Empty)
+        )
+    )]
+    pub async fn calculate_circle_area_async(&self, radius: f64) -> Result<f64, LibraryError> {
+        let _call = self.track("calculate_circle_area_async");
+        let (_, receiver) = self.start_circle_area_async(radius)?;
+        receiver.await.map_err(|_| LibraryError::CallbackDropped)
+    }
+
+    /// Like `calculate_circle_area_async`, but resolves to `0.0` instead of failing if the
+    /// sender is dropped without a result.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the async symbols are not exported.
+    pub async fn calculate_circle_area_async_or_default(
+        &self,
+        radius: f64,
+    ) -> Result<f64, LibraryError> {
+        match self.calculate_circle_area_async(radius).await {
+            Err(LibraryError::CallbackDropped) => Ok(0.0),
+            result => result,
+        }
+    }
+
+    /// Calculates the area of a circle on Tokio's blocking thread pool.
+    ///
+    /// The synchronous FFI call runs inside `tokio::task::spawn_blocking`, so a slow Go
+    /// computation occupies a pool thread instead of an executor thread. The returned future
+    /// holds its own clone of the library (which is `Send + Sync`) and is therefore
+    /// `'static`; it must be awaited inside a Tokio runtime.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::TaskCancelled` if the runtime shuts down before the call runs.
+    ///
+    /// # Panics
+    /// A panic during the call is resumed when the future is awaited.
+    pub fn calculate_circle_area_offloaded(
+        &self,
+        radius: f64,
+    ) -> impl Future<Output = Result<f64, LibraryError>> + Send + 'static {
+        let lib = self.clone();
+        async move {
+            match tokio::task::spawn_blocking(move || lib.calculate_circle_area(radius)).await {
+                Ok(result) => result,
+                Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+                Err(_) => Err(LibraryError::TaskCancelled),
+            }
+        }
+    }
+
+    /// Calculates the area of a circle through the asynchronous Go function, blocking the
+    /// current thread until the result arrives.
+    ///
+    /// This bridges the async API for callers that do not run inside a Tokio runtime; no
+    /// runtime is needed, the calling thread simply waits on the oneshot channel.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::CallbackDropped` if the sender is dropped without a result,
+    /// `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the async symbols are not exported.
+    ///
+    /// # Panics
+    /// Panics if called from within an asynchronous execution context, such as a Tokio
+    /// runtime; use `calculate_circle_area_async` there instead.
+    pub fn calculate_circle_area_blocking(&self, radius: f64) -> Result<f64, LibraryError> {
+        let _call = self.track("calculate_circle_area_blocking");
+        let (_, receiver) = self.start_circle_area_async(radius)?;
+        receiver
+            .blocking_recv()
+            .map_err(|_| LibraryError::CallbackDropped)
+    }
+
+    /// Asynchronously calculates the area of a circle, giving up after `dur`.
+    ///
+    /// On timeout the pending sender is reclaimed immediately; if Go invokes the callback
+    /// later, it finds no pending call for its id and the result is discarded.
+    ///
+    /// The timeout is reported as `LibraryError::Timeout` rather than through a separate
+    /// error type, because the call can also fail in every way `calculate_circle_area_async`
+    /// can, and callers can match on the one variant they care about.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Timeout` if no result arrives within `dur`,
+    /// `LibraryError::CallbackDropped` if the sender is dropped without a result,
+    /// `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the async symbols are not exported.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area_async_timeout",
+            level = "debug",
+            skip(self),
+            fields(request_id = tracing::field::Empty)
+        )
+    )]
+    pub async fn calculate_circle_area_async_timeout(
+        &self,
+        radius: f64,
+        dur: Duration,
+    ) -> Result<f64, LibraryError> {
+        let _call = self.track("calculate_circle_area_async_timeout");
+        let (id, receiver) = self.start_circle_area_async(radius)?;
+        match tokio::time::timeout(dur, receiver).await {
+            Ok(Ok(area)) => Ok(area),
+            Ok(Err(_)) => Err(LibraryError::CallbackDropped),
+            Err(_) => {
+                pending::cancel(id);
+                Err(LibraryError::Timeout(dur))
+            }
+        }
+    }
+
+    /// Asynchronously calculates the area of a circle, reporting progress along the way.
+    ///
+    /// Returns a stream of the completed fractions, from 0.0 to 1.0, that Go reports while
+    /// computing, and a future resolving to the final area. Progress is routed through an
+    /// unbounded channel and the result through a oneshot channel; both senders are kept in
+    /// the pending-call registry under a single id, so the stream ends once the result has
+    /// been delivered or the call is reclaimed. Dropping the stream does not cancel the
+    /// call.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the library does not export
+    /// `CalculateCircleAreaAsyncProgress`. The future fails with
+    /// `LibraryError::CallbackDropped` if the call is reclaimed before its result arrives.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area_async_progress",
+            level = "debug",
+            skip(self),
+            fields(request_id = tracing::field::Empty)
+        )
+    )]
+    pub fn calculate_circle_area_async_progress(
+        &self,
+        radius: f64,
+    ) -> Result<
+        (
+            impl Stream<Item = f32>,
+            impl Future<Output = Result<f64, LibraryError>>,
+        ),
+        LibraryError,
+    > {
+        let _call = self.track("calculate_circle_area_async_progress");
+        validate_dimension("radius", radius)?;
+        let calculate_circle_area_async_progress = require_symbol(
+            self.calculate_circle_area_async_progress,
+            "CalculateCircleAreaAsyncProgress",
+        )?;
+        let (progress_sender, progress_receiver) = mpsc::unbounded_channel::<f32>();
+        let (sender, receiver) = oneshot::channel::<f64>();
+        let id = pending::register_with_progress(self.id, sender, progress_sender);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("request_id", id);
+        unsafe {
+            calculate_circle_area_async_progress(
+                radius,
+                progress_trampoline,
+                async_trampoline,
+                id as *mut c_void,
+            );
+        }
+        let area = async move { receiver.await.map_err(|_| LibraryError::CallbackDropped) };
+        Ok((UnboundedReceiverStream::new(progress_receiver), area))
+    }
+
+    /// Reclaims the senders of all one-shot async calls started by this library whose Go
+    /// callback has not fired yet, returning how many were reclaimed.
+    ///
+    /// If Go never invokes a callback, its sender would otherwise stay registered forever.
+    /// The futures of reclaimed calls complete as if the callback had been dropped, and a
+    /// callback that does fire later is ignored.
+    pub fn reclaim_pending(&self) -> usize {
+        pending::reclaim(self.id)
+    }
+
+    /// Returns how many one-shot async calls started by this library are still waiting
+    /// for their Go callback.
+    pub fn pending_async_calls(&self) -> usize {
+        pending::count(self.id)
+    }
+
+    /// Shuts this handle down, first giving outstanding one-shot async calls a chance to
+    /// complete.
+    ///
+    /// Asks Go to finish its async work right away through the `FlushAsync` export, if the
+    /// library has one, then waits up to `SHUTDOWN_TIMEOUT` for the calls started by this
+    /// library and its clones to deliver their results before dropping `self`.
+    ///
+    /// Calls whose callback has not fired by then are reclaimed as by `reclaim_pending`, so
+    /// no sender is leaked: their futures complete with `LibraryError::CallbackDropped`, and
+    /// a callback that fires later finds nothing to deliver to. The library itself stays
+    /// loaded, so such late callbacks remain safe after the last handle is dropped.
+    pub fn shutdown(self) -> impl Future<Output = ()> + Send + 'static {
+        {
+            let _call = self.track("shutdown");
+            if let Some(flush_async) = self.flush_async {
+                unsafe { flush_async() };
+            }
+        }
+        async move {
+            let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+            while pending::count(self.id) > 0 && Instant::now() < deadline {
+                tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+            }
+            let _abandoned = pending::reclaim(self.id);
+            #[cfg(feature = "tracing")]
+            if _abandoned > 0 {
+                tracing::warn!(
+                    abandoned = _abandoned,
+                    "async callbacks did not fire before shutdown"
+                );
+            }
+        }
+    }
+
+    /// Starts the Go asynchronous area calculation and returns the id of the pending call
+    /// together with the receiving half of the oneshot channel its callback sends into.
+    ///
+    /// Only the id is passed to Go as user data; the sender stays in the pending-call
+    /// registry so it can be reclaimed if the callback never fires. Ids increase
+    /// monotonically across all libraries, so with the `tracing` feature the id is recorded
+    /// as `request_id` on the calling method's span and on the callback's events, tying
+    /// each callback to the call that started it.
+    fn start_circle_area_async(
+        &self,
+        radius: f64,
+    ) -> Result<(usize, oneshot::Receiver<f64>), LibraryError> {
+        validate_dimension("radius", radius)?;
+        let calculate_circle_area_async =
+            require_symbol(self.calculate_circle_area_async, "CalculateCircleAreaAsync")?;
+        let (sender, receiver) = oneshot::channel::<f64>();
+        let id = pending::register(self.id, sender);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("request_id", id);
+        let user_data = id as *mut c_void;
+        unsafe {
+            calculate_circle_area_async(radius, async_trampoline, user_data);
+        }
+        Ok((id, receiver))
+    }
+
+    /// Calls the asynchronous function which produces multiple callback invocations.
+    /// Returns an mpsc::UnboundedReceiver that yields each result.
+    ///
+    /// The Go side decides how many results it produces and signals completion through a
+    /// separate done callback, at which point the channel is closed and `recv()` returns
+    /// `None`. Dropping the receiver early, including when the runtime owning it shuts
+    /// down, tells the Go side to stop producing results: the next callback finds the
+    /// channel closed and returns false without attempting to send.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the async symbols were marked optional and the
+    /// library does not export them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area_async_multi",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn calculate_circle_area_async_multi(
+        &self,
+        radius: f64,
+    ) -> Result<mpsc::UnboundedReceiver<f64>, LibraryError> {
+        let _call = self.track("calculate_circle_area_async_multi");
+        // Create an unbounded channel.
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.forward_circle_area_async_multi(radius, tx)?;
+        Ok(rx)
+    }
+
+    /// Like `calculate_circle_area_async_multi`, but sends the results into a
+    /// caller-provided channel.
+    ///
+    /// This allows fanning results from several calls into one receiver, e.g. by passing a
+    /// clone of the same sender for each radius. Each call owns its sender until Go signals
+    /// completion, so the receiver only sees the channel close once every call has
+    /// finished and the caller's own senders are dropped. Dropping the receiver asks every
+    /// call still running to stop.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the async symbols were marked optional and the
+    /// library does not export them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area_async_multi_into",
+            level = "debug",
+            skip(self, tx)
+        )
+    )]
+    pub fn calculate_circle_area_async_multi_into(
+        &self,
+        radius: f64,
+        tx: mpsc::UnboundedSender<f64>,
+    ) -> Result<(), LibraryError> {
+        let _call = self.track("calculate_circle_area_async_multi_into");
+        self.forward_circle_area_async_multi(radius, tx)
+    }
+
+    /// Starts the Go multi-shot area calculation, sending each result on `tx` until its
+    /// receiver is dropped.
+    fn forward_circle_area_async_multi(
+        &self,
+        radius: f64,
+        tx: mpsc::UnboundedSender<f64>,
+    ) -> Result<(), LibraryError> {
+        self.start_circle_area_async_multi(radius, move |area| {
+            // A dropped receiver is an expected way to stop, not an error.
+            if tx.is_closed() || tx.send(area).is_err() {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("receiver dropped; asking Go to stop producing results");
+                return false;
+            }
+            true
+        })
+    }
+
+    /// Like `calculate_circle_area_async_multi`, but also returns a `MultiShotHandle` that
+    /// stops the Go side early when cancelled or dropped.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the async symbols were marked optional and the
+    /// library does not export them.
+    pub fn calculate_circle_area_async_multi_cancellable(
+        &self,
+        radius: f64,
+    ) -> Result<(mpsc::UnboundedReceiver<f64>, MultiShotHandle), LibraryError> {
+        let _call = self.track("calculate_circle_area_async_multi_cancellable");
+        let (tx, rx) = mpsc::unbounded_channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+        self.start_circle_area_async_multi(radius, move |area| {
+            !flag.load(Ordering::SeqCst) && tx.send(area).is_ok()
+        })?;
+        Ok((rx, MultiShotHandle { cancelled }))
+    }
+
+    /// Like `calculate_circle_area_async_multi`, but buffers at most `capacity` results.
+    ///
+    /// Go cannot be paused, so a result that arrives while the channel is full is dropped
+    /// instead of buffered, and counted in the returned `DroppedResults`; Go keeps
+    /// producing. Memory use therefore stays bounded however slow the consumer is, at the
+    /// cost of losing results. Dropping the receiver still asks Go to stop.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `capacity` is zero or `radius` is negative,
+    /// NaN or infinite, or `LibraryError::SymbolMissing` if the async symbols were marked
+    /// optional and the library does not export them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area_async_multi_bounded",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn calculate_circle_area_async_multi_bounded(
+        &self,
+        radius: f64,
+        capacity: usize,
+    ) -> Result<(mpsc::Receiver<f64>, DroppedResults), LibraryError> {
+        let _call = self.track("calculate_circle_area_async_multi_bounded");
+        if capacity == 0 {
+            return Err(LibraryError::InvalidInput(
+                "channel capacity must be at least 1".to_string(),
+            ));
+        }
+        let (tx, rx) = mpsc::channel(capacity);
+        let dropped = DroppedResults {
+            count: Arc::new(AtomicUsize::new(0)),
+        };
+        let count = Arc::clone(&dropped.count);
+        self.start_circle_area_async_multi(radius, move |area| match tx.try_send(area) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                count.fetch_add(1, Ordering::SeqCst);
+                #[cfg(feature = "tracing")]
+                tracing::debug!("channel full; dropping result");
+                true
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        })?;
+        Ok((rx, dropped))
+    }
+
+    /// Starts the Go multi-shot area calculation, forwarding each result to `on_result`.
+    ///
+    /// `on_result` returns true to keep receiving results and false to ask Go to stop. It
+    /// is boxed and passed to Go as user data, then dropped by `async_done_trampoline` once
+    /// Go signals completion.
+    fn start_circle_area_async_multi<F>(
+        &self,
+        radius: f64,
+        on_result: F,
+    ) -> Result<(), LibraryError>
+    where
+        F: FnMut(f64) -> bool + Send + 'static,
+    {
+        validate_dimension("radius", radius)?;
+        let calculate_circle_area_async_multiple = require_symbol(
+            self.calculate_circle_area_async_multiple,
+            "CalculateCircleAreaAsyncMultiple",
+        )?;
+        // Go reads the answer as a byte; normalize the closure's `bool` to exactly 0 or 1.
+        let mut on_result = on_result;
+        let on_result = move |area: c_double| u8::from(on_result(area));
+        let (trampoline, done_trampoline) = multi_shot_trampolines(&on_result);
+        // Hand the closure to Go; it is reclaimed by `async_done_trampoline`.
+        let user_data = UserData::new(on_result).into_raw();
+        unsafe {
+            calculate_circle_area_async_multiple(radius, trampoline, done_trampoline, user_data);
+        }
+        Ok(())
+    }
+
+    /// Starts a pull-based area calculation whose values Go only produces on request.
+    ///
+    /// Unlike `calculate_circle_area_stream`, where Go pushes results as fast as it produces
+    /// them, each value is computed only once the returned `PollStream` is polled for it;
+    /// see `PollStream` for the protocol. The Go side keeps producing for as long as values
+    /// are requested, and stops once the stream is dropped.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the library does not export
+    /// `CalculateCircleAreaPoll`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_circle_area_poll",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn calculate_circle_area_poll(&self, radius: f64) -> Result<PollStream, LibraryError> {
+        let _call = self.track("calculate_circle_area_poll");
+        validate_dimension("radius", radius)?;
+        let calculate_circle_area_poll =
+            require_symbol(self.calculate_circle_area_poll, "CalculateCircleAreaPoll")?;
+        let (request_tx, request_rx) = std::sync::mpsc::channel();
+        let (result_tx, result_rx) = mpsc::unbounded_channel();
+        let state = PollState {
+            requests: request_rx,
+            results: result_tx,
+        };
+        // Reclaimed by `async_done_trampoline` once Go signals completion.
+        let user_data = UserData::new(state).into_raw();
+        unsafe {
+            calculate_circle_area_poll(
+                radius,
+                poll_request_trampoline,
+                poll_result_trampoline,
+                async_done_trampoline::<PollState>,
+                user_data,
+            );
+        }
+        Ok(PollStream {
+            requests: request_tx,
+            results: result_rx,
+            requested: false,
+        })
+    }
+
+    /// Calls the asynchronous multi-shot function and exposes its results as a `Stream`.
+    ///
+    /// This wraps `calculate_circle_area_async_multi` so results can be consumed with
+    /// stream combinators such as `take`, `filter` or `collect`. The stream ends when the
+    /// Go side signals completion.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if `radius` is negative, NaN or infinite, or
+    /// `LibraryError::SymbolMissing` if the async symbols were marked optional and the
+    /// library does not export them.
+    pub fn calculate_circle_area_stream(
+        &self,
+        radius: f64,
+    ) -> Result<impl Stream<Item = f64>, LibraryError> {
+        let rx = self.calculate_circle_area_async_multi(radius)?;
+        Ok(UnboundedReceiverStream::new(rx))
+    }
+
+    /// Calculate the area of any shape using the shape enum
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if `CalculateShapeArea` was marked optional and
+    /// the library does not export it.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "circle_lib.calculate_shape_area", level = "debug", skip(self))
+    )]
+    pub fn calculate_shape_area(&self, shape: &Shape) -> Result<f64, LibraryError> {
+        let _call = self.track("calculate_shape_area");
+        let calculate_shape_area = require_symbol(self.calculate_shape_area, "CalculateShapeArea")?;
+        Ok(unsafe { calculate_shape_area(*shape) })
+    }
+
+    /// Calculates the area of a shape, letting Go validate it.
+    ///
+    /// `CalculateShapeAreaChecked` writes an error code through an out-parameter, which is
+    /// zero on success; a non-zero code is returned as `LibraryError::Go`. Unlike
+    /// `try_calculate_shape_area`, this reports failures only Go checks for, such as a
+    /// degenerate triangle.
+    ///
+    /// The error is a `LibraryError` rather than the bare `GoErrorCode`, because
+    /// `CalculateShapeAreaChecked` is an optional export and a library without it has to be
+    /// reported as well. Match on `LibraryError::Go` to get at the code.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Go` with the reported `GoErrorCode` if Go rejects the shape,
+    /// or `LibraryError::SymbolMissing` if the library does not export
+    /// `CalculateShapeAreaChecked`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_shape_area_checked",
+            level = "debug",
+            skip(self)
+        )
+    )]
+    pub fn calculate_shape_area_checked(&self, shape: &Shape) -> Result<f64, LibraryError> {
+        let _call = self.track("calculate_shape_area_checked");
+        let calculate_shape_area_checked = require_symbol(
+            self.calculate_shape_area_checked,
+            "CalculateShapeAreaChecked",
+        )?;
+        let mut code: c_int = 0;
+        let area = unsafe { calculate_shape_area_checked(*shape, &mut code) };
+        match GoErrorCode::from_code(code) {
+            None => Ok(area),
+            Some(code) => Err(LibraryError::Go(code)),
+        }
+    }
+
+    /// Calculates the area of a shape that may come from untrusted data, validating it first.
+    ///
+    /// Unlike `calculate_shape_area`, this checks that `shape_type` holds a known
+    /// discriminant, which a `Shape` read from raw bytes may not, and that the dimensions
+    /// used by that type are finite and non-negative. Go is only called for valid shapes.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` for an unknown shape type or an invalid
+    /// dimension, or any error `calculate_shape_area` can return.
+    pub fn try_calculate_shape_area(&self, shape: &Shape) -> Result<f64, LibraryError> {
+        unsafe { validate_shape_type(shape)? };
+        shape.validate()?;
+        self.calculate_shape_area(shape)
+    }
+
+    /// Calculates the area of any shape, using Go if the library exports
+    /// `CalculateShapeArea` and the pure Rust `ShapeArea` implementation otherwise.
+    pub fn calculate_shape_area_or_fallback(&self, shape: &Shape) -> f64 {
+        match self.calculate_shape_area(shape) {
+            Ok(area) => area,
+            Err(_) => shape.area(),
+        }
+    }
+
+    /// Calculates the areas of many shapes, of any mix of types, with a single FFI call.
+    ///
+    /// Every shape is validated before calling into Go. `Shape` has C layout, so the slice
+    /// is passed to `CalculateShapeAreasBatch` directly as a pointer and length. Empty
+    /// input returns an empty vector without calling into Go. If the library does not
+    /// export the batch function, this falls back to calling `CalculateShapeArea` once
+    /// per shape.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` for the first shape with an invalid dimension,
+    /// or `LibraryError::SymbolMissing` if the fallback is needed and `CalculateShapeArea`
+    /// is not exported.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.calculate_shape_areas",
+            level = "debug",
+            skip(self, shapes),
+            fields(count = shapes.len())
+        )
+    )]
+    pub fn calculate_shape_areas(&self, shapes: &[Shape]) -> Result<Vec<f64>, LibraryError> {
+        let _call = self.track("calculate_shape_areas");
+        for shape in shapes {
+            shape.validate()?;
+        }
+        if shapes.is_empty() {
+            return Ok(Vec::new());
+        }
+        match self.calculate_shape_areas_batch {
+            Some(calculate_shape_areas_batch) => {
+                let mut areas = vec![0.0; shapes.len()];
+                unsafe {
+                    calculate_shape_areas_batch(
+                        shapes.as_ptr(),
+                        go_len(shapes.len()),
+                        areas.as_mut_ptr(),
+                    );
+                }
+                Ok(areas)
+            }
+            None => {
+                let calculate_shape_area =
+                    require_symbol(self.calculate_shape_area, "CalculateShapeArea")?;
+                Ok(shapes
+                    .iter()
+                    .map(|&shape| unsafe { calculate_shape_area(shape) })
+                    .collect())
+            }
+        }
+    }
+
+    /// Lets Go normalize the dimensions of `shapes` in place.
+    ///
+    /// Go makes every dimension non-negative and zeroes `dimension2` of circles and
+    /// squares, which do not use it. `Shape` has C layout, so Go writes straight into the
+    /// caller's slice through its pointer and length, without copying; it does not keep
+    /// the pointer after returning. Empty input returns without calling into Go.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::SymbolMissing` if the library does not export
+    /// `NormalizeShapes`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "circle_lib.normalize_shapes",
+            level = "debug",
+            skip(self, shapes),
+            fields(count = shapes.len())
+        )
+    )]
+    pub fn normalize_shapes(&self, shapes: &mut [Shape]) -> Result<(), LibraryError> {
+        let _call = self.track("normalize_shapes");
+        let normalize_shapes = require_symbol(self.normalize_shapes, "NormalizeShapes")?;
+        if shapes.is_empty() {
+            return Ok(());
+        }
+        // Go only rewrites the dimensions, so every shape type stays a valid discriminant.
+        unsafe { normalize_shapes(shapes.as_mut_ptr(), go_len(shapes.len())) };
+        Ok(())
+    }
+}
+
+/// Accepts an integer result from Go only if it matches the checked Rust computation.
+///
+/// A mismatch means the C-side arithmetic wrapped around.
+fn check_int_result(
+    operation: &str,
+    result: c_int,
+    expected: Option<i32>,
+) -> Result<i32, LibraryError> {
+    match expected {
+        Some(expected) if expected == result => Ok(result),
+        _ => Err(LibraryError::Overflow {
+            operation: operation.to_string(),
+        }),
+    }
+}
+
+/// State passed as user data to `context_trampoline` by `call_callback_with_context`.
+struct CallbackContext<'a, S, F> {
+    state: &'a mut S,
+    f: F,
+    // Panic payload caught by the trampoline, resumed once the FFI call returns.
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+/// Extern "C" trampoline for callbacks that carry a user data pointer.
+/// It recovers the `CallbackContext` and calls the closure with the borrowed state.
+unsafe extern "C" fn context_trampoline<S, F>(val: c_double, user_data: *mut c_void) -> c_double
+where
+    F: FnMut(&mut S, f64) -> f64,
+{
+    #[cfg(feature = "tracing")]
+    tracing::trace!(val, "context callback invoked");
+    let context = &mut *(user_data as *mut CallbackContext<'_, S, F>);
+    let CallbackContext {
+        state,
+        f,
+        panic: panic_slot,
+    } = context;
+    panic::catch_unwind(AssertUnwindSafe(|| f(state, val))).unwrap_or_else(|payload| {
+        *panic_slot = Some(payload);
+        0.0
+    })
+}
+
+/// State passed as user data to `concurrent_trampoline` by `call_callback_concurrent`.
+struct ConcurrentContext<F> {
+    f: F,
+    // First panic payload caught on any goroutine, resumed once the FFI call returns.
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+/// Extern "C" trampoline for callbacks invoked from several goroutines at once.
+/// It only takes shared references to the `ConcurrentContext`, which is `Sync` because the
+/// closure is.
+unsafe extern "C" fn concurrent_trampoline<F>(val: c_double, user_data: *mut c_void) -> c_double
+where
+    F: Fn(f64) -> f64 + Sync,
+{
+    #[cfg(feature = "tracing")]
+    tracing::trace!(val, "concurrent callback invoked");
+    let context = &*(user_data as *const ConcurrentContext<F>);
+    panic::catch_unwind(AssertUnwindSafe(|| (context.f)(val))).unwrap_or_else(|payload| {
+        let mut panic = context.panic.lock().unwrap_or_else(PoisonError::into_inner);
+        panic.get_or_insert(payload);
+        0.0
+    })
+}
+
+ffi_callback! {
+    /// Extern "C" trampoline for asynchronous callbacks that supports multiple shots.
+    /// It forwards each result to the closure behind the user data, which returns 1 to
+    /// keep receiving callbacks and 0 to ask Go to stop.
+    user_data fn async_trampoline_multi(result: c_double) -> u8 = 0;
+}
+
+/// Extern "C" trampoline invoked once the multi-shot computation has finished.
+/// It reclaims the boxed closure, dropping everything it captured (e.g. the sender).
+unsafe extern "C" fn async_done_trampoline<F>(user_data: *mut c_void) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!("async done callback invoked");
+    let boxed_callback = UserData::<F>::from_raw(user_data);
+    let _ = panic::catch_unwind(AssertUnwindSafe(move || drop(boxed_callback)));
+}
+
+/// Extern "C" trampoline invoked by Go before producing each value of a `PollStream`.
+/// It blocks the calling goroutine until the stream requests a value, and returns 0 once
+/// the stream has been dropped.
+unsafe extern "C" fn poll_request_trampoline(user_data: *mut c_void) -> u8 {
+    let state = UserData::<PollState>::get(user_data);
+    let requested = panic::catch_unwind(AssertUnwindSafe(|| state.requests.recv().is_ok()));
+    u8::from(requested.unwrap_or(false))
+}
+
+/// Extern "C" trampoline delivering a requested value to a `PollStream`.
+/// Returns 0 if the stream has been dropped in the meantime.
+unsafe extern "C" fn poll_result_trampoline(result: c_double, user_data: *mut c_void) -> u8 {
+    let state = UserData::<PollState>::get(user_data);
+    #[cfg(feature = "tracing")]
+    tracing::trace!(result, "poll result delivered");
+    u8::from(state.results.send(result).is_ok())
+}
+
+/// Extern "C" trampoline for errors reported by Go.
+/// The user data carries the owner id of the library whose handler should receive them.
+unsafe extern "C" fn error_handler_trampoline(message: *const c_char, user_data: *mut c_void) {
+    if message.is_null() {
+        return;
+    }
+    let message = CStr::from_ptr(message).to_string_lossy();
+    #[cfg(feature = "tracing")]
+    tracing::error!(message = %message, "Go library reported an error");
+    if let Some(handler) = error_handlers::get(user_data as usize) {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(&message)));
+    }
+}
+
+/// Returns the multi-shot trampolines for a closure of type `F`, which cannot be named.
+fn multi_shot_trampolines<F>(_: &F) -> (AsyncCallback, AsyncDoneCallback)
+where
+    F: FnMut(c_double) -> u8,
+{
+    (async_trampoline_multi::<F>, async_done_trampoline::<F>)
+}
+
+/// Extern "C" trampoline for progress reports of an asynchronous call.
+/// The user data carries the id of the pending call whose progress stream receives them.
+unsafe extern "C" fn progress_trampoline(fraction: c_float, user_data: *mut c_void) {
+    let id = user_data as usize;
+    #[cfg(feature = "tracing")]
+    tracing::trace!(request_id = id, fraction, "progress callback invoked");
+    let _ = panic::catch_unwind(move || pending::report_progress(id, fraction));
+}
+
+/// Extern "C" trampoline for asynchronous callbacks.
+/// The user data carries the id of a pending call; the result is delivered to its sender.
+/// Ids that are no longer pending (reclaimed, or already completed) are ignored. Because
+/// the user data is an id rather than a pointer, a duplicate callback from Go is harmless:
+/// the first invocation removes the pending call and later ones find nothing to free.
+unsafe extern "C" fn async_trampoline(result: c_double, user_data: *mut c_void) -> u8 {
+    let id = user_data as usize;
+    #[cfg(feature = "tracing")]
+    tracing::trace!(request_id = id, result, "async callback invoked");
+    let delivered = panic::catch_unwind(move || pending::complete(id, result)).unwrap_or(false);
+    if !delivered {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            request_id = id,
+            "ignoring callback for an async call that is no longer pending"
+        );
+    }
+    0 // This is a one-shot callback, so we're done after sending
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[test]
+    fn concurrent_callbacks_do_not_see_each_other() {
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = [2.0, 3.0]
+            .into_iter()
+            .map(|factor| {
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    (0..100)
+                        .map(|i| {
+                            with_callback(
+                                move |val| val * factor,
+                                |trampoline| unsafe { trampoline(f64::from(i)) },
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for (handle, factor) in handles.into_iter().zip([2.0, 3.0]) {
+            let results = handle.join().unwrap();
+            for (i, result) in results.into_iter().enumerate() {
+                assert_eq!(result, i as f64 * factor);
+            }
+        }
+    }
+
+    #[test]
+    fn nested_callback_runs_its_own_closure() {
+        let result = with_callback(
+            |val| {
+                let inner = with_callback(|val| val + 1.0, |trampoline| unsafe { trampoline(val) });
+                inner * 10.0
+            },
+            // The outer closure must still be in place after the nested call returns.
+            |trampoline| unsafe { trampoline(1.0) + trampoline(2.0) },
+        );
+        assert_eq!(result, 20.0 + 30.0);
+    }
+
+    #[test]
+    fn callback_panic_is_resumed_after_the_call() {
+        let outcome = panic::catch_unwind(|| {
+            with_callback(|_| panic!("boom"), |trampoline| unsafe { trampoline(1.0) })
+        });
+        assert!(outcome.is_err());
+        // The stack was restored, so a later call gets its own closure.
+        let result = with_callback(|val| val * 2.0, |trampoline| unsafe { trampoline(4.0) });
+        assert_eq!(result, 8.0);
+    }
+
+    #[test]
+    fn int_result_at_the_i32_boundary() {
+        let (a, b) = (i32::MAX, 1);
+        // Go's wrapping `int32` addition, as `AddInts` computes it.
+        let wrapped = a.wrapping_add(b);
+        assert!(matches!(
+            check_int_result("AddInts", wrapped, a.checked_add(b)),
+            Err(LibraryError::Overflow { operation }) if operation == "AddInts"
+        ));
+        let max = check_int_result("AddInts", i32::MAX, (i32::MAX - 1).checked_add(1));
+        assert_eq!(max.unwrap(), i32::MAX);
+    }
+
+    #[test]
+    fn int_result_for_wrapping_multiplication() {
+        let (a, b) = (i32::MIN, -1);
+        assert!(check_int_result("MultiplyInts", a.wrapping_mul(b), a.checked_mul(b)).is_err());
+        assert_eq!(
+            check_int_result("MultiplyInts", -42, 6_i32.checked_mul(-7)).unwrap(),
+            -42
+        );
+    }
+
+    // A `Shape` as Go sees it, so an out-of-range discriminant can be built without ever
+    // materializing an invalid `ShapeType` value.
+    #[repr(C)]
+    struct RawShape {
+        shape_type: c_int,
+        dimension1: c_double,
+        dimension2: c_double,
+    }
+
+    #[test]
+    fn out_of_range_shape_type_is_rejected() {
+        for shape_type in [-1, 5, c_int::MAX] {
+            let raw = RawShape {
+                shape_type,
+                dimension1: 1.0,
+                dimension2: 1.0,
+            };
+            let result = unsafe { validate_shape_type(std::ptr::addr_of!(raw).cast::<Shape>()) };
+            assert!(matches!(result, Err(LibraryError::InvalidInput(_))));
+        }
+    }
+
+    #[test]
+    fn known_shape_types_are_accepted() {
+        for shape_type in 0..5 {
+            let raw = RawShape {
+                shape_type,
+                dimension1: 1.0,
+                dimension2: 1.0,
+            };
+            let result = unsafe { validate_shape_type(std::ptr::addr_of!(raw).cast::<Shape>()) };
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn callback_stack_is_empty_after_the_call_panics() {
+        let outcome = panic::catch_unwind(|| {
+            with_callback(|val| val * 2.0, |_| -> f64 { panic!("FFI call failed") })
+        });
+        assert!(outcome.is_err());
+        // Outside of any call the trampoline finds no closure and returns the default.
+        let trampoline = with_callback(|val| val, |trampoline| trampoline);
+        assert_eq!(unsafe { trampoline(5.0) }, 0.0);
+    }
+
+    #[test]
+    fn dynamic_loading_is_supported() {
+        // Test binaries link the system loader dynamically, so it works here, and a library
+        // that fails to load reports the loader's own error.
+        assert!(CircleLibrary::is_supported());
+        assert!(matches!(
+            CircleLibrary::new("does-not-exist.so"),
+            Err(LibraryError::LoadFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::error::LibraryError;
+use crate::{Circle, CircleLibrary};
+
+/// A circle bound to the library that computes its measurements, created by
+/// `CircleLibrary::circle`.
+///
+/// Reads more naturally than passing the radius to every library method:
+///
+/// ```ignore
+/// let circle = circle_lib.circle(10.0);
+/// println!("{} has area {}", circle.info()?, circle.area()?);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct LiveCircle<'a> {
+    lib: &'a CircleLibrary,
+    radius: f64,
+}
+
+impl<'a> LiveCircle<'a> {
+    /// Returns the circle's radius.
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Returns the circle as a plain `Circle` struct.
+    pub fn to_circle(&self) -> Circle {
+        Circle {
+            radius: self.radius,
+        }
+    }
+
+    /// Calculates the circle's area, like `CircleLibrary::calculate_circle_area`.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if the radius is negative, NaN or infinite.
+    pub fn area(&self) -> Result<f64, LibraryError> {
+        self.lib.calculate_circle_area(self.radius)
+    }
+
+    /// Calculates the circle's circumference, like `CircleLibrary::circumference`.
+    pub fn circumference(&self) -> f64 {
+        self.lib.circumference(self.radius)
+    }
+
+    /// Calculates the circle's diameter, like `CircleLibrary::diameter`.
+    pub fn diameter(&self) -> f64 {
+        self.lib.diameter(self.radius)
+    }
+
+    /// Formats information about the circle, like `CircleLibrary::format_circle_info`.
+    ///
+    /// # Errors
+    /// Returns any error `CircleLibrary::format_circle_info` can return.
+    pub fn info(&self) -> Result<String, LibraryError> {
+        self.lib.format_circle_info(self.radius)
+    }
+
+    /// Asynchronously calculates the circle's area, like
+    /// `CircleLibrary::calculate_circle_area_async`.
+    ///
+    /// # Errors
+    /// Returns any error `CircleLibrary::calculate_circle_area_async` can return.
+    pub async fn area_async(&self) -> Result<f64, LibraryError> {
+        self.lib.calculate_circle_area_async(self.radius).await
+    }
+}
+
+impl CircleLibrary {
+    /// Returns a circle with the given radius whose measurements are computed by this
+    /// library.
+    pub fn circle(&self, radius: f64) -> LiveCircle<'_> {
+        LiveCircle { lib: self, radius }
+    }
+}
@@ -0,0 +1,308 @@
+//! Locating shared library files and resolving their symbols.
+
+use crate::error::LibraryError;
+use crate::EXPECTED_ABI_VERSION;
+use libloading::{Library, Symbol};
+use std::env::{self, consts::DLL_EXTENSION, consts::DLL_PREFIX};
+use std::fs;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// Flags controlling how the system loader opens the library on Unix.
+///
+/// The default matches `libloading::Library::new`: symbols are bound lazily and kept local
+/// to the library (`RTLD_LAZY | RTLD_LOCAL`). On other platforms the flags are ignored and
+/// the library is loaded as usual.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LoadFlags {
+    now: bool,
+    global: bool,
+}
+
+impl LoadFlags {
+    /// Returns the default flags, `RTLD_LAZY | RTLD_LOCAL`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves every undefined symbol while loading (`RTLD_NOW`), so a library with
+    /// missing dependencies fails to load instead of failing on first use.
+    pub fn now(mut self) -> Self {
+        self.now = true;
+        self
+    }
+
+    /// Makes the library's symbols available to libraries loaded later (`RTLD_GLOBAL`),
+    /// e.g. plugins that link against the Go library.
+    pub fn global(mut self) -> Self {
+        self.global = true;
+        self
+    }
+
+    /// Returns the flags as passed to `dlopen`.
+    #[cfg(unix)]
+    fn to_raw(self) -> c_int {
+        use libloading::os::unix::{RTLD_GLOBAL, RTLD_LAZY, RTLD_LOCAL, RTLD_NOW};
+        let binding = if self.now { RTLD_NOW } else { RTLD_LAZY };
+        let visibility = if self.global { RTLD_GLOBAL } else { RTLD_LOCAL };
+        binding | visibility
+    }
+}
+
+/// Loads the library at `path` with the given flags, which only apply on Unix.
+///
+/// The library is never unloaded from the process, however the returned handle is used.
+/// The Go runtime cannot be shut down: its threads keep running code from the library, and
+/// goroutines started by async calls may call back through it after every handle is gone.
+/// The library is therefore opened with `RTLD_NODELETE` on Unix and pinned on Windows, so
+/// dropping the returned handle closes it without unmapping the library's code.
+///
+/// If loading fails because the system loader does not work in this process at all, see
+/// `dynamic_loading_supported`, the error is `LibraryError::Unsupported`.
+pub(crate) fn open_library(path: &Path, flags: LoadFlags) -> Result<Library, LibraryError> {
+    open_pinned(path, flags).map_err(|e| match e {
+        LibraryError::LoadFailed(_) if !dynamic_loading_supported() => LibraryError::Unsupported,
+        e => e,
+    })
+}
+
+fn open_pinned(path: &Path, flags: LoadFlags) -> Result<Library, LibraryError> {
+    #[cfg(unix)]
+    {
+        let flags = flags.to_raw() | libc::RTLD_NODELETE;
+        let lib = unsafe { libloading::os::unix::Library::open(Some(path), flags) }?;
+        Ok(lib.into())
+    }
+    #[cfg(windows)]
+    {
+        let _ = flags;
+        let lib = unsafe { libloading::os::windows::Library::new(path) }?;
+        lib.pin()?;
+        Ok(lib.into())
+    }
+}
+
+/// Returns whether the system loader works in this process.
+///
+/// Checked once, by opening the running program itself. That needs no file access, so it
+/// only fails where the loader is a stub, e.g. in statically linked musl binaries, whose
+/// `dlopen` always fails with "Dynamic loading not supported".
+pub(crate) fn dynamic_loading_supported() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        // Opening the program itself loads nothing new, so no initializers run.
+        #[cfg(unix)]
+        let this = unsafe {
+            libloading::os::unix::Library::open(None::<&Path>, libloading::os::unix::RTLD_LAZY)
+        };
+        #[cfg(windows)]
+        let this = libloading::os::windows::Library::this();
+        this.is_ok()
+    })
+}
+
+/// Checks the library's `LibraryAbiVersion` export against `EXPECTED_ABI_VERSION`.
+///
+/// Libraries that predate the export are accepted; with the `tracing` feature a warning is
+/// emitted for them.
+pub(crate) fn check_abi_version(lib: &Library) -> Result<(), LibraryError> {
+    let abi_version: Option<Symbol<unsafe extern "C" fn() -> c_int>> =
+        unsafe { get_optional_symbol(lib, "LibraryAbiVersion", true)? };
+    match abi_version {
+        Some(abi_version) => {
+            let found = unsafe { abi_version() };
+            if found == EXPECTED_ABI_VERSION {
+                Ok(())
+            } else {
+                Err(LibraryError::AbiMismatch {
+                    expected: EXPECTED_ABI_VERSION,
+                    found,
+                })
+            }
+        }
+        None => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                expected = EXPECTED_ABI_VERSION,
+                "library does not export LibraryAbiVersion; assuming the expected ABI version"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Runs the library's optional `InitLibrary` export on creation and its optional
+/// `ShutdownLibrary` export when dropped.
+///
+/// Shared by every clone of a `CircleLibrary` and the generators created from it, so the
+/// Go runtime is shut down once the last of them is gone. The guard holds its own
+/// reference to the library, which therefore stays loaded until after `ShutdownLibrary`
+/// has returned.
+pub(crate) struct RuntimeGuard {
+    shutdown: Option<unsafe extern "C" fn()>,
+    // Released after `drop` has run, so `shutdown` is still valid when it is called.
+    _lib: Arc<Library>,
+}
+
+impl RuntimeGuard {
+    pub(crate) fn init(lib: &Arc<Library>) -> Result<Self, LibraryError> {
+        unsafe {
+            let init: Option<Symbol<unsafe extern "C" fn()>> =
+                get_optional_symbol(lib, "InitLibrary", true)?;
+            let shutdown: Option<Symbol<unsafe extern "C" fn()>> =
+                get_optional_symbol(lib, "ShutdownLibrary", true)?;
+            if let Some(init) = init {
+                init();
+            }
+            Ok(RuntimeGuard {
+                shutdown: shutdown.map(|s| *s),
+                _lib: Arc::clone(lib),
+            })
+        }
+    }
+}
+
+impl Drop for RuntimeGuard {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown {
+            unsafe { shutdown() };
+        }
+    }
+}
+
+/// A uniquely named library file written by `CircleLibrary::from_bytes` or `reload`,
+/// deleted when dropped.
+pub(crate) struct TempLibraryFile {
+    pub(crate) path: PathBuf,
+}
+
+impl TempLibraryFile {
+    pub(crate) fn create(data: &[u8], dir: &Path) -> Result<Self, LibraryError> {
+        let path = Self::unique_path(dir);
+        fs::write(&path, data)?;
+        Ok(TempLibraryFile { path })
+    }
+
+    /// Copies the library at `source` into `dir`.
+    ///
+    /// The system loader returns the already loaded instance when a path is opened again,
+    /// even if the file has since been replaced, so `reload` loads from a fresh copy.
+    pub(crate) fn copy(source: &Path, dir: &Path) -> Result<Self, LibraryError> {
+        let path = Self::unique_path(dir);
+        fs::copy(source, &path)?;
+        Ok(TempLibraryFile { path })
+    }
+
+    fn unique_path(dir: &Path) -> PathBuf {
+        static NEXT_FILE_ID: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "go_rust_ffi_{}_{}",
+            std::process::id(),
+            NEXT_FILE_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        dir.join(library_file_name(&name))
+    }
+}
+
+impl Drop for TempLibraryFile {
+    fn drop(&mut self) {
+        // Nothing sensible can be done if the file cannot be removed.
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Returns the platform-specific file name for a library base name, e.g. `libcircle.so`.
+fn library_file_name(name: &str) -> String {
+    format!("{}{}.{}", DLL_PREFIX, name, DLL_EXTENSION)
+}
+
+/// Returns the candidate paths `CircleLibrary::open` tries, in search order.
+pub(crate) fn library_search_paths(name: &str) -> Vec<PathBuf> {
+    let file_name = library_file_name(name);
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    let cwd = env::current_dir().ok();
+    exe_dir
+        .into_iter()
+        .chain(cwd)
+        .map(|dir| dir.join(&file_name))
+        .collect()
+}
+
+/// Looks up a symbol by name, reporting which symbol was missing on failure.
+///
+/// # Safety
+/// The caller must ensure that `T` matches the actual type of the exported symbol.
+pub(crate) unsafe fn get_symbol<'lib, T>(
+    lib: &'lib Library,
+    name: &str,
+) -> Result<Symbol<'lib, T>, LibraryError> {
+    lib.get(name.as_bytes())
+        .map_err(|source| LibraryError::SymbolMissing {
+            name: name.to_string(),
+            source: Some(source),
+        })
+}
+
+/// Looks up a symbol that may be absent when `optional` is set.
+///
+/// # Safety
+/// The caller must ensure that `T` matches the actual type of the exported symbol.
+pub(crate) unsafe fn get_optional_symbol<'lib, T>(
+    lib: &'lib Library,
+    name: &str,
+    optional: bool,
+) -> Result<Option<Symbol<'lib, T>>, LibraryError> {
+    match get_symbol(lib, name) {
+        Ok(symbol) => Ok(Some(symbol)),
+        Err(LibraryError::SymbolMissing { .. }) if optional => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns the resolved function pointer of an optional symbol, or a `SymbolMissing`
+/// error naming it if the library did not export it.
+pub(crate) fn require_symbol<T>(symbol: Option<T>, name: &str) -> Result<T, LibraryError> {
+    symbol.ok_or_else(|| LibraryError::SymbolMissing {
+        name: name.to_string(),
+        source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn library_file_name_on_linux() {
+        assert_eq!(library_file_name("circle"), "libcircle.so");
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn library_file_name_on_macos() {
+        assert_eq!(library_file_name("circle"), "libcircle.dylib");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn library_file_name_on_windows() {
+        assert_eq!(library_file_name("circle"), "circle.dll");
+    }
+
+    #[test]
+    fn search_paths_end_with_the_file_name() {
+        let paths = library_search_paths("circle");
+        assert!(!paths.is_empty());
+        for path in paths {
+            assert_eq!(
+                path.file_name().unwrap().to_str().unwrap(),
+                library_file_name("circle")
+            );
+        }
+    }
+}
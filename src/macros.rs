@@ -0,0 +1,134 @@
+/// Generates the plumbing that lets a Rust closure stand in for a C callback.
+///
+/// Two forms are supported.
+///
+/// The thread-local form is for callbacks that Go invokes synchronously on the calling
+/// thread, without a user data pointer. It generates a function taking the closure and a
+/// `call` closure: the closure is pushed onto a thread-local stack, `call` receives an
+/// `extern "C"` trampoline that forwards to the innermost closure, and the closure is
+/// popped again once `call` returns or unwinds. Nested and concurrent calls are therefore
+/// independent. For example, binding a Go `CallIntCallback(int, int_callback_t) int`:
+///
+/// ```ignore
+/// ffi_callback! {
+///     /// Runs `call` with a trampoline forwarding `c_int` callbacks to `callback`.
+///     fn with_int_callback(val: c_int) -> c_int = 0;
+/// }
+///
+/// let result = with_int_callback(|x| x * 2, |trampoline| unsafe {
+///     call_int_callback(21, trampoline)
+/// });
+/// ```
+///
+/// The user data form is for callbacks that receive the closure through a trailing
+/// `*mut c_void` user data pointer, such as the async callbacks. It generates a generic
+/// trampoline that borrows the `UserData<F>` behind the pointer and calls the closure:
+///
+/// ```ignore
+/// ffi_callback! {
+///     /// Forwards each async result to the closure behind `user_data`.
+///     user_data fn async_trampoline_multi(result: c_double) -> u8 = 0;
+/// }
+///
+/// let on_result = |area: f64| u8::from(tx.send(area).is_ok());
+/// let user_data = UserData::new(on_result).into_raw();
+/// unsafe { start(radius, async_trampoline_multi::<F>, user_data) };
+/// ```
+///
+/// In both forms a panic in the closure is caught before it can unwind into Go and the
+/// given default value is returned to Go instead. The thread-local form resumes the panic
+/// once `call` has returned and control is back in Rust.
+///
+/// With the `tracing` feature enabled, each generated trampoline emits a `trace` event
+/// when it is invoked.
+#[macro_export]
+macro_rules! ffi_callback {
+    (
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty = $default:expr;
+    ) => {
+        $(#[$meta])*
+        $vis fn $name<F, R>(
+            callback: F,
+            call: impl FnOnce(unsafe extern "C" fn($($ty),*) -> $ret) -> R,
+        ) -> R
+        where
+            F: Fn($($ty),*) -> $ret + 'static,
+        {
+            type Stored = Box<dyn Fn($($ty),*) -> $ret>;
+
+            ::std::thread_local! {
+                static STACK: ::std::cell::RefCell<Vec<Stored>> =
+                    const { ::std::cell::RefCell::new(Vec::new()) };
+                static PANIC: ::std::cell::RefCell<Option<Box<dyn ::std::any::Any + Send>>> =
+                    const { ::std::cell::RefCell::new(None) };
+            }
+
+            extern "C" fn trampoline($($arg: $ty),*) -> $ret {
+                #[cfg(feature = "tracing")]
+                ::tracing::trace!(callback = stringify!($name), "callback invoked");
+                // Pop the closure while it runs so a nested call inside it can push its own
+                // closure without a conflicting borrow, then put it back afterwards.
+                match STACK.with(|stack| stack.borrow_mut().pop()) {
+                    Some(callback) => {
+                        let result = ::std::panic::catch_unwind(
+                            ::std::panic::AssertUnwindSafe(|| callback($($arg),*)),
+                        )
+                        .unwrap_or_else(|payload| {
+                            PANIC.with(|slot| *slot.borrow_mut() = Some(payload));
+                            $default
+                        });
+                        STACK.with(|stack| stack.borrow_mut().push(callback));
+                        result
+                    }
+                    None => $default,
+                }
+            }
+
+            // Pushes the closure on construction and pops it on drop, so the stack is
+            // restored even if `call` unwinds.
+            struct CallbackGuard;
+
+            impl CallbackGuard {
+                fn new(callback: Stored) -> Self {
+                    STACK.with(|stack| stack.borrow_mut().push(callback));
+                    CallbackGuard
+                }
+            }
+
+            impl Drop for CallbackGuard {
+                fn drop(&mut self) {
+                    STACK.with(|stack| stack.borrow_mut().pop());
+                }
+            }
+
+            let guard = CallbackGuard::new(Box::new(callback));
+            let result = call(trampoline);
+            drop(guard);
+            // Re-raise a panic from the closure now that we are back on the Rust side.
+            if let Some(payload) = PANIC.with(|slot| slot.borrow_mut().take()) {
+                ::std::panic::resume_unwind(payload);
+            }
+            result
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis user_data fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty = $default:expr;
+    ) => {
+        $(#[$meta])*
+        $vis unsafe extern "C" fn $name<F>(
+            $($arg: $ty,)*
+            user_data: *mut ::std::os::raw::c_void,
+        ) -> $ret
+        where
+            F: FnMut($($ty),*) -> $ret,
+        {
+            #[cfg(feature = "tracing")]
+            ::tracing::trace!(callback = stringify!($name), "callback invoked");
+            let callback = $crate::user_data::UserData::<F>::get_mut(user_data);
+            ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| callback($($arg),*)))
+                .unwrap_or($default)
+        }
+    };
+}
@@ -1,350 +1,123 @@
-use lazy_static::lazy_static;
-use libloading::{Library, Symbol};
-use std::ffi::CStr;
-use std::os::raw::{c_char, c_double, c_int, c_void};
-use std::sync::{Arc, Mutex};
-use tokio::sync::{mpsc, oneshot};
-
-/// Type alias for the callback function pointer that the shared library expects.
-/// (This matches the Go-exported callback type.)
-pub type CallbackType = unsafe extern "C" fn(c_double) -> c_double;
-/// Callback type expected by the asynchronous function.
-type AsyncCallback = unsafe extern "C" fn(c_double, *mut c_void) -> bool;
-
-// Global storage for the callback closure.
-// This global variable is protected by a Mutex and allows the trampoline function
-// to retrieve the user-provided closure.
-
-lazy_static! {
-    static ref CALLBACK_STORE: Mutex<Option<Callback>> = Mutex::new(None);
-}
-type Callback = Box<dyn Fn(f64) -> f64 + Send>;
-
-/// Enum representing different shape types, matching the C enum.
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub enum ShapeType {
-    Circle = 0,
-    Square = 1,
-    Triangle = 2,
-}
-
-/// A shape struct with C layout that can represent different shapes.
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct Shape {
-    pub shape_type: ShapeType,
-    pub dimension1: c_double, // radius for circle, side for square, base for triangle
-    pub dimension2: c_double, // unused for circle/square, height for triangle
-}
-
-/// Define a Rust struct with C layout representing a circle.
-/// Deriving Copy and Clone allows us to pass the struct by value.
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct Circle {
-    pub radius: c_double,
-}
-
-/// A safe wrapper around the Go circle library that includes callback support.
-///
-/// This struct loads the shared library and exposes safe methods for calculating
-/// the circle area, formatting circle info, and invoking a callback. The `call_callback_with`
-/// method allows a Rust closure (e.g. `|x| x * x`) to be used as the callback, hiding all
-/// unsafe FFI and pointer operations.
-pub struct CircleLibrary {
-    // We store the leaked library reference to ensure that the symbols remain valid.
-    // Keep the loaded library alive for the lifetime of the wrapper.
-    _lib: &'static Library,
-    calculate_circle_area: unsafe extern "C" fn(c_double) -> c_double,
-    calculate_struct_area: unsafe extern "C" fn(Circle) -> c_double,
-    format_circle_info: unsafe extern "C" fn(c_double) -> *mut c_char,
-    free_string: unsafe extern "C" fn(*mut c_char),
-    call_callback: unsafe extern "C" fn(c_double, CallbackType) -> c_double,
-    // Pointer to the asynchronous function.
-    calculate_circle_area_async: unsafe extern "C" fn(c_double, AsyncCallback, *mut c_void),
-    calculate_circle_area_async_multiple:
-        unsafe extern "C" fn(c_double, AsyncCallback, *mut c_void),
-    calculate_shape_area: unsafe extern "C" fn(Shape) -> c_double,
-}
+use go_rust_ffi::backend::{CircleBackend, MockBackend};
+use go_rust_ffi::error::{CallbackError, LibraryError};
+use go_rust_ffi::*;
+use std::os::raw::c_double;
+use std::time::Duration;
+use tokio_stream::StreamExt;
 
-impl CircleLibrary {
-    /// Loads the shared library from the given path.
-    ///
-    /// # Arguments
-    /// * `path` - The file path to the shared library (e.g., "lib.dll).
-    ///
-    /// # Errors
-    /// Returns an error if the library or any symbol fails to load.
-    pub fn new(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        // Load the library.
-        let lib = unsafe { Library::new(path) }?;
-        // Leak the library to obtain a 'static lifetime reference; this is acceptable when the
-        // library is intended to remain loaded for the duration of the program.
-        let lib: &'static Library = Box::leak(Box::new(lib));
-
-        unsafe {
-            // Load the function symbols.
-            let calculate_circle_area: Symbol<unsafe extern "C" fn(c_double) -> c_double> =
-                lib.get(b"CalculateCircleArea")?;
-            // Retrieve the symbol for CalculateCircleArea.
-            let calculate_struct_area: libloading::Symbol<
-                unsafe extern "C" fn(Circle) -> c_double,
-            > = lib.get(b"CalculateCircleStructArea")?;
-            let format_circle_info: Symbol<unsafe extern "C" fn(c_double) -> *mut c_char> =
-                lib.get(b"FormatCircleInfo")?;
-            let free_string: Symbol<unsafe extern "C" fn(*mut c_char)> = lib.get(b"FreeString")?;
-            let call_callback: Symbol<unsafe extern "C" fn(c_double, CallbackType) -> c_double> =
-                lib.get(b"CallCallback")?;
-            let calculate_circle_area_async: Symbol<
-                unsafe extern "C" fn(c_double, AsyncCallback, *mut c_void),
-            > = lib.get(b"CalculateCircleAreaAsync")?;
-
-            let calculate_circle_area_async_multiple: Symbol<
-                unsafe extern "C" fn(c_double, AsyncCallback, *mut c_void),
-            > = lib.get(b"CalculateCircleAreaAsyncMultiple")?;
-
-            let calculate_shape_area: Symbol<unsafe extern "C" fn(Shape) -> c_double> =
-                lib.get(b"CalculateShapeArea")?;
-
-            Ok(CircleLibrary {
-                _lib: lib,
-                // Dereference the symbols to store the function pointers.
-                calculate_circle_area: *calculate_circle_area,
-                calculate_struct_area: *calculate_struct_area,
-                format_circle_info: *format_circle_info,
-                free_string: *free_string,
-                call_callback: *call_callback,
-                calculate_circle_area_async: *calculate_circle_area_async,
-                calculate_circle_area_async_multiple: *calculate_circle_area_async_multiple,
-                calculate_shape_area: *calculate_shape_area,
-            })
-        }
-    }
-
-    /// Calculates the area of a circle given the radius.
-    ///
-    /// # Arguments
-    /// * `radius` - The circle's radius.
-    ///
-    /// # Returns
-    /// The computed area as an `f64`.
-    pub fn calculate_circle_area(&self, radius: f64) -> f64 {
-        unsafe { (self.calculate_circle_area)(radius) }
-    }
-
-    /// A safe method that accepts a reference to a Circle and returns its area.
-    pub fn calculate_circle_struct_area(&self, circle: &Circle) -> f64 {
-        // The external function expects the struct by value.
-        unsafe { (self.calculate_struct_area)(*circle) }
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if !CircleLibrary::is_supported() {
+        println!(
+            "Dynamic loading is unsupported here; pure Rust area: {}",
+            Circle { radius: 10.0 }.area()
+        );
+        return Ok(());
     }
 
-    /// Returns a formatted string with circle information.
-    ///
-    /// This method handles pointer conversion and memory management internally.
-    ///
-    /// # Arguments
-    /// * `radius` - The circle's radius.
-    ///
-    /// # Returns
-    /// A safe `String` containing the formatted message.
-    pub fn format_circle_info(&self, radius: f64) -> Result<String, Box<dyn std::error::Error>> {
-        unsafe {
-            let c_ptr = (self.format_circle_info)(radius);
-            if c_ptr.is_null() {
-                return Err("Received null pointer from format_circle_info".into());
-            }
-            // Convert the C string into a Rust String.
-            let c_str = CStr::from_ptr(c_ptr);
-            let result = c_str.to_string_lossy().into_owned();
-            // Free the allocated string in the Go library.
-            (self.free_string)(c_ptr);
-            Ok(result)
-        }
-    }
+    // Make sure that "lib.dll" is in the same directory as the binary or adjust the path accordingly.
+    let circle_lib = CircleLibrary::new_async("lib.dll").await?;
 
-    /// Calls a callback function using the Go library.
-    ///
-    /// The callback is provided as an extern "C" function pointer.
-    pub fn call_callback(&self, val: f64, callback: CallbackType) -> f64 {
-        unsafe { (self.call_callback)(val, callback) }
-    }
+    println!("Loaded {}", circle_lib);
+    println!("Library capabilities: {:?}", circle_lib.capabilities());
 
-    /// Calls the shared library’s callback function.
-    ///
-    /// Instead of forcing the user to provide an `extern "C" fn`, this method accepts
-    /// any Rust closure with signature `Fn(f64) -> f64`. Internally, the closure is stored
-    /// in a global mutex and an `extern "C"` trampoline is passed to the FFI call.
-    ///
-    /// This design hides all unsafe details and pointer manipulations from the user.
-    /// (This method uses a global Mutex for state storage.)
-    pub fn call_callback_with<F>(&self, val: f64, callback: F) -> f64
-    where
-        F: Fn(f64) -> f64 + Send + 'static,
+    // Resolve every symbol up front, failing now rather than on first use.
+    #[cfg(unix)]
     {
-        // Store the provided closure in a global mutex.
-        {
-            let mut store = CALLBACK_STORE.lock().unwrap();
-            *store = Some(Box::new(callback));
-        }
-        // Call the FFI function with our trampoline as the callback.
-        let result = unsafe { (self.call_callback)(val, trampoline) };
-        // Clear the global storage after the callback returns.
-        {
-            let mut store = CALLBACK_STORE.lock().unwrap();
-            *store = None;
-        }
-        result
-    }
-
-    /// Asynchronously calculates the area of a circle.
-    ///
-    /// This method wraps the Go asynchronous function and returns a Future that resolves
-    /// to the computed area. Internally, it creates a oneshot channel and passes a boxed sender
-    /// as user data to the Go function.
-    pub async fn calculate_circle_area_async(&self, radius: f64) -> f64 {
-        let (sender, receiver) = oneshot::channel::<f64>();
-        let boxed_sender = Box::new(sender);
-        let user_data = Box::into_raw(boxed_sender) as *mut c_void;
-        unsafe {
-            (self.calculate_circle_area_async)(radius, async_trampoline, user_data);
-        }
-        // Await the result; if the channel is dropped, return 0.0.
-        receiver.await.unwrap_or(0.0)
+        let eager = CircleLibrary::new_with_flags("lib.dll", LoadFlags::new().now())?;
+        println!("Loaded {} with RTLD_NOW", eager.path().display());
     }
 
-    /// Calls the asynchronous function which produces multiple callback invocations.
-    /// Returns an mpsc::UnboundedReceiver that yields each result.
-    pub fn calculate_circle_area_async_multi(&self, radius: f64) -> mpsc::UnboundedReceiver<f64> {
-        // Create an unbounded channel.
-        let (tx, rx) = mpsc::unbounded_channel();
-        // Create a new sender for each callback
-        let tx = Arc::new(Mutex::new(tx));
-        // Convert the Arc into a raw pointer.
-        let user_data = Box::into_raw(Box::new(tx)) as *mut c_void;
-        unsafe {
-            (self.calculate_circle_area_async_multiple)(radius, async_trampoline_multi, user_data);
-        }
-        rx
-    }
+    println!("Area metadata: {:?}", circle_lib.area_metadata());
+    circle_lib.on_error(|message| eprintln!("Go reported an error: {}", message))?;
 
-    /// Calculate the area of any shape using the shape enum
-    pub fn calculate_shape_area(&self, shape: &Shape) -> f64 {
-        unsafe { (self.calculate_shape_area)(*shape) }
-    }
-}
+    let radius = 10.0;
+    let area = circle_lib.calculate_circle_area(radius)?;
+    println!("Synchronous area: {}", area);
+    println!(
+        "Area of the unit circle matches the library's pi: {}",
+        circle_lib.calculate_circle_area(1.0)? == circle_lib.pi()
+    );
+    println!(
+        "Negative radius is rejected: {:?}",
+        circle_lib.calculate_circle_area(-5.0)
+    );
 
-/// Extern "C" trampoline function that matches the expected callback signature.
-/// It locks the global storage to retrieve the user’s closure and calls it.
-extern "C" fn trampoline(val: c_double) -> c_double {
-    let callback_opt = CALLBACK_STORE.lock().unwrap();
-    if let Some(ref cb) = *callback_opt {
-        cb(val)
-    } else {
-        0.0 // Default return value if no callback is set.
-    }
-}
+    let (timed_area, elapsed) = circle_lib.timed_calculate_circle_area(radius)?;
+    println!("Area {} computed by Go in {:?}", timed_area, elapsed);
+    let (details, elapsed) = circle_lib.timed(|lib| lib.get_circle_details(radius));
+    println!("Details {:?} computed by Go in {:?}", details?, elapsed);
 
-/// Extern "C" trampoline for asynchronous callbacks that supports multiple shots.
-/// It recovers the Arc-wrapped sender and sends each callback result.
-/// Returns true to continue receiving callbacks, false when done.
-unsafe extern "C" fn async_trampoline_multi(result: c_double, user_data: *mut c_void) -> bool {
-    // Convert the raw pointer back to a reference.
-    let tx = &*(user_data as *const Arc<Mutex<mpsc::UnboundedSender<f64>>>);
-    // Attempt to send the result (ignore errors if the receiver is dropped).
-    if let Ok(tx) = tx.lock() {
-        match tx.send(result) {
-            Ok(_) => (),
-            Err(e) => println!("Rust: Failed to send result: {}", e),
-        }
-    }
-    // Return false on the last callback (we know there will be 3 callbacks)
-    static mut CALLBACK_COUNT: u32 = 0;
-    CALLBACK_COUNT += 1;
-    
-    CALLBACK_COUNT < 3
-}
+    let circle = circle_lib.circle(radius);
+    println!(
+        "Fluent circle: area {}, circumference {}, async area {}",
+        circle.area()?,
+        circle.circumference(),
+        circle.area_async().await?
+    );
 
-/// Extern "C" trampoline for asynchronous callbacks.
-/// This function recovers the boxed oneshot sender from the user data and sends the result.
-unsafe extern "C" fn async_trampoline(result: c_double, user_data: *mut c_void) -> bool {
-    let boxed_sender: Box<oneshot::Sender<f64>> = Box::from_raw(user_data as *mut _);
-    let _ = boxed_sender.send(result);
-    false // This is a one-shot callback, so we're done after sending
-}
+    let area_f32 = circle_lib.calculate_circle_area_f32(radius as f32)?;
+    println!("Single-precision area: {}", area_f32);
+    println!(
+        "Circumference: {}, diameter: {}",
+        circle_lib.circumference(radius),
+        circle_lib.diameter(radius)
+    );
 
-/// A safe wrapper around the Go number generator
-pub struct NumberGenerator<'lib> {
-    id: i64,
-    lib: &'lib Library,
-}
+    let batch_areas = circle_lib.calculate_circle_areas(&[1.0, 2.0, 3.0]);
+    println!("Batch areas: {:?}", batch_areas);
 
-// Implement Drop to ensure we clean up the Go resources
-impl<'lib> Drop for NumberGenerator<'lib> {
-    fn drop(&mut self) {
-        self.free_generator();
-    }
-}
+    println!(
+        "Sum of the batch areas: {}, of none: {}",
+        circle_lib.sum_areas(&batch_areas),
+        circle_lib.sum_areas(&[])
+    );
 
-impl<'lib> NumberGenerator<'lib> {
-    pub fn new(lib: &'lib Library) -> Result<Self, Box<dyn std::error::Error>> {
-        unsafe {
-            let create_generator: Symbol<unsafe extern "C" fn() -> i64> =
-                lib.get(b"CreateNumberGenerator")?;
-            let id = create_generator();
-            Ok(NumberGenerator { id, lib })
-        }
-    }
+    let total_area = (1..=3)
+        .map(f64::from)
+        .circle_areas(&circle_lib)
+        .sum::<Result<f64, _>>()?;
+    println!("Total area of radii 1 to 3: {}", total_area);
 
-    pub fn next(&self) -> Result<Option<i32>, Box<dyn std::error::Error>> {
-        unsafe {
-            let get_next: Symbol<unsafe extern "C" fn(i64) -> (c_int, bool)> =
-                self.lib.get(b"GetNextNumber")?;
-            let (num, ok) = get_next(self.id);
-            if ok {
-                Ok(Some(num))
-            } else {
-                Ok(None)
-            }
-        }
+    let cumulative_areas = circle_lib.map_radii(&[1.0, 2.0, 3.0])?;
+    println!("Cumulative areas: {:?}", cumulative_areas);
+    for info in circle_lib.format_circle_infos(&[1.0, 2.0, 3.0])? {
+        println!("Batch info: {}", info);
     }
-
-    pub fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
-        unsafe {
-            let stop_generator: Symbol<unsafe extern "C" fn(i64)> =
-                self.lib.get(b"StopNumberGenerator")?;
-            stop_generator(self.id);
-            Ok(())
-        }
+    for decimals in [0, 2, 10] {
+        println!(
+            "Info with {} decimals: {}",
+            decimals,
+            circle_lib.format_circle_info_prec(radius, decimals)?
+        );
     }
 
-    fn free_generator(&self) {
-        unsafe {
-            if let Ok(free_generator) = self
-                .lib
-                .get::<unsafe extern "C" fn(i64)>(b"FreeNumberGenerator")
-            {
-                free_generator(self.id);
-            }
-        }
-    }
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Make sure that "lib.dll" is in the same directory as the binary or adjust the path accordingly.
-    let circle_lib = CircleLibrary::new("lib.dll")?;
-
-    let radius = 10.0;
-    let area = circle_lib.calculate_circle_area(radius);
-    println!("Synchronous area: {}", area);
-
     let circle = Circle { radius };
-    let struct_area = circle_lib.calculate_circle_struct_area(&circle);
+    let circle_as_shape = Shape::from(circle);
+    println!(
+        "Circle as a shape: {:?}, and back: {:?}",
+        circle_as_shape,
+        Circle::try_from(circle_as_shape)?
+    );
+    let struct_area = circle_lib.calculate_circle_struct_area(&circle)?;
     println!("Struct-based area: {}", struct_area);
 
     let info = circle_lib.format_circle_info(radius)?;
     println!("{}", info);
+    let info_owned = circle_lib.format_circle_info_owned(radius)?;
+    println!("Borrowed from Go without copying: {:?}", info_owned);
+
+    let mut info_buf = String::new();
+    for r in [1.0, 2.0, 3.0] {
+        circle_lib.format_circle_info_into(r, &mut info_buf)?;
+        println!("Reused buffer: {}", info_buf);
+    }
+
+    let labeled_info = circle_lib.format_circle_info_labeled("Demo", radius)?;
+    println!("{}", labeled_info);
+
+    let details = circle_lib.get_circle_details(radius)?;
+    println!("Circle details: {:?}", details);
 
     let cb_result = circle_lib.call_callback(5.0, square_callback as CallbackType);
     println!("Callback result (square of 5.0): {}", cb_result);
@@ -355,13 +128,60 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         cb_result_closure
     );
 
+    let try_result = circle_lib.call_callback_try_with(-1.0, |x| {
+        if x < 0.0 {
+            Err(CallbackError::new(
+                "cannot take the square root of a negative number",
+            ))
+        } else {
+            Ok(x.sqrt())
+        }
+    });
+    println!("Fallible callback result (sqrt of -1.0): {:?}", try_result);
+
+    circle_lib.set_default_callback(|x| x + 1.0);
+    let default_results = (0..3)
+        .map(|i| circle_lib.call_callback_default(f64::from(i)))
+        .collect::<Result<Vec<_>, _>>()?;
+    println!("Default callback results (x + 1): {:?}", default_results);
+
+    println!("Checked addition (2 + 3): {}", circle_lib.add_ints(2, 3)?);
+    println!(
+        "Checked addition at the boundary (i32::MAX + 1): {:?}",
+        circle_lib.add_ints(i32::MAX, 1)
+    );
+
+    let int_cb_result = circle_lib.call_int_callback_with(21, |x| x * 2)?;
+    println!("Integer callback result (double of 21): {}", int_cb_result);
+
+    let mut sum = 0.0;
+    let context_result = circle_lib.call_callback_with_context(5.0, &mut sum, |sum, x| {
+        *sum += x;
+        x * x
+    })?;
+    println!(
+        "Callback result with context (square of 5.0): {}, inputs summed: {}",
+        context_result, sum
+    );
+
+    let invocations = std::sync::atomic::AtomicUsize::new(0);
+    let concurrent_results = circle_lib.call_callback_concurrent(&[1.0, 2.0, 3.0, 4.0], |x| {
+        invocations.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        x * x
+    })?;
+    println!(
+        "Squares from {} concurrent goroutines: {:?}",
+        invocations.into_inner(),
+        concurrent_results
+    );
+
     // Demonstrate the asynchronous function.
     println!("Calling asynchronous one-shot calculation...");
-    let async_area = circle_lib.calculate_circle_area_async(radius).await;
+    let async_area = circle_lib.calculate_circle_area_async(radius).await?;
     println!("Asynchronous area for radius {}: {}", radius, async_area);
 
     println!("Calling asynchronous multi-shot calculation...");
-    let mut rx = circle_lib.calculate_circle_area_async_multi(radius);
+    let mut rx = circle_lib.calculate_circle_area_async_multi(radius)?;
 
     // Create a shorter timeout for testing
     let timeout = tokio::time::sleep(tokio::time::Duration::from_secs(4));
@@ -390,30 +210,158 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     println!("Rust: Finished receiving results");
 
+    let (fan_in_tx, mut fan_in_rx) = tokio::sync::mpsc::unbounded_channel();
+    for r in [1.0, 2.0] {
+        circle_lib.calculate_circle_area_async_multi_into(r, fan_in_tx.clone())?;
+    }
+    // Drop our own sender so the channel closes once both calculations have finished.
+    drop(fan_in_tx);
+    let mut fanned_in = Vec::new();
+    while let Some(area) = fan_in_rx.recv().await {
+        fanned_in.push(area);
+    }
+    println!("Areas fanned in from two radii: {:?}", fanned_in);
+
+    println!("Collecting asynchronous multi-shot results from a stream...");
+    let areas: Vec<f64> = circle_lib
+        .calculate_circle_area_stream(radius)?
+        .take(3)
+        .collect()
+        .await;
+    println!("Streamed areas: {:?}", areas);
+
+    let polled: Vec<f64> = circle_lib
+        .calculate_circle_area_poll(radius)?
+        .take(2)
+        .collect()
+        .await;
+    println!("Areas produced on request: {:?}", polled);
+
+    let mut offloaded = tokio::task::JoinSet::new();
+    for r in 1..=4 {
+        offloaded.spawn(circle_lib.calculate_circle_area_offloaded(f64::from(r)));
+    }
+    let mut offloaded_areas = Vec::new();
+    while let Some(area) = offloaded.join_next().await {
+        offloaded_areas.push(area??);
+    }
+    println!(
+        "Offloaded areas (in completion order): {:?}",
+        offloaded_areas
+    );
+
+    let (progress, area) = circle_lib.calculate_circle_area_async_progress(radius)?;
+    let progress: Vec<f32> = progress.collect().await;
+    println!(
+        "Progress reports: {:?}, final area: {}",
+        progress,
+        area.await?
+    );
+
+    let (mut rx, dropped) = circle_lib.calculate_circle_area_async_multi_bounded(radius, 2)?;
+    let mut bounded = Vec::new();
+    while let Some(area) = rx.recv().await {
+        bounded.push(area);
+    }
+    println!("Bounded areas: {:?} ({} dropped)", bounded, dropped.count());
+
+    let (mut rx, handle) = circle_lib.calculate_circle_area_async_multi_cancellable(radius)?;
+    let mut received = Vec::new();
+    while let Some(area) = rx.recv().await {
+        received.push(area);
+        if received.len() == 2 {
+            handle.cancel();
+        }
+    }
+    println!("Areas received before cancelling: {:?}", received);
+
     // Example using the Shape enum and struct
-    let circle_shape = Shape {
-        shape_type: ShapeType::Circle,
-        dimension1: 5.0, // radius
-        dimension2: 0.0, // unused for circle
-    };
+    let circle_shape = Shape::circle(5.0)?;
     println!(
         "Circle area using Shape enum: {}",
-        circle_lib.calculate_shape_area(&circle_shape)
+        circle_lib.calculate_shape_area(&circle_shape)?
     );
 
-    let triangle_shape = Shape {
-        shape_type: ShapeType::Triangle,
-        dimension1: 4.0, // base
-        dimension2: 3.0, // height
-    };
+    let triangle_shape = Shape::triangle(4.0, 3.0)?;
     println!(
         "Triangle area using Shape enum: {}",
-        circle_lib.calculate_shape_area(&triangle_shape)
+        circle_lib.calculate_shape_area(&triangle_shape)?
+    );
+
+    let rectangle_shape = Shape::rectangle(4.0, 3.0)?;
+    println!(
+        "Rectangle area using Shape enum: {}",
+        circle_lib.calculate_shape_area(&rectangle_shape)?
+    );
+
+    let ellipse_shape = Shape::ellipse(4.0, 3.0)?;
+    println!(
+        "Ellipse area using Shape enum: {}",
+        circle_lib.calculate_shape_area(&ellipse_shape)?
+    );
+
+    let shape_areas = circle_lib.calculate_shape_areas(&[
+        circle_shape,
+        triangle_shape,
+        rectangle_shape,
+        ellipse_shape,
+    ])?;
+    println!("Batch shape areas: {:?}", shape_areas);
+
+    circle_lib.register_callback("square", |x| x * x);
+    circle_lib.register_callback("double", |x| x * 2.0);
+    println!(
+        "Named callbacks: square(3) = {}, double(3) = {}",
+        circle_lib.call_named_callback("square", 3.0)?,
+        circle_lib.call_named_callback("double", 3.0)?
+    );
+
+    let scaled_triangle = circle_lib.transform_shape_with(triangle_shape, |shape| Shape {
+        dimension1: shape.dimension1 * 2.0,
+        dimension2: shape.dimension2 * 2.0,
+        ..shape
+    })?;
+    println!("Triangle scaled in a callback: {:?}", scaled_triangle);
+
+    println!(
+        "Shapes generated by Go: {:?}",
+        circle_lib.generate_shapes(5)?
+    );
+
+    let built_triangle = ShapeBuilder::triangle().base(4.0).height(3.0).build()?;
+    println!("Triangle from the builder: {:?}", built_triangle);
+    println!(
+        "Circle with a height is rejected: {:?}",
+        ShapeBuilder::circle().radius(1.0).height(2.0).build()
+    );
+
+    println!(
+        "Degenerate triangle rejected by Go: {:?}",
+        circle_lib.calculate_shape_area_checked(&Shape::triangle(4.0, 0.0)?)
+    );
+
+    let mut unnormalized = [Shape {
+        shape_type: ShapeType::Rectangle,
+        dimension1: -4.0,
+        dimension2: 3.0,
+    }];
+    circle_lib.normalize_shapes(&mut unnormalized)?;
+    println!("Shapes normalized in place by Go: {:?}", unnormalized);
+
+    let untrusted_shape = Shape {
+        shape_type: ShapeType::Square,
+        dimension1: f64::NAN,
+        dimension2: 0.0,
+    };
+    println!(
+        "Untrusted shape is rejected: {:?}",
+        circle_lib.try_calculate_shape_area(&untrusted_shape)
     );
 
     // Example using Go channels through the number generator
     println!("\nTesting Go channels with number generator:");
-    let generator = NumberGenerator::new(circle_lib._lib)?;
+    let generator = circle_lib.number_generator()?;
+    println!("Created {:?}", generator);
 
     // Get the first 5 numbers
     for _ in 0..5 {
@@ -422,10 +370,90 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    println!(
+        "Number with a timeout: {:?}",
+        generator.next_timeout(Duration::from_secs(1))
+    );
+
+    // Pull the next few numbers through the iterator adapter
+    let numbers = generator.iter().take(3).collect::<Result<Vec<_>, _>>()?;
+    println!("Received numbers via iterator: {:?}", numbers);
+
     // Stop the generator
     generator.stop()?;
     println!("Number generator stopped");
+    println!(
+        "Generator state after stopping: {:?}",
+        generator.next_state()
+    );
+
+    let streamed_numbers: Vec<i32> = circle_lib
+        .number_generator()?
+        .into_stream()
+        .take(3)
+        .collect()
+        .await;
+    println!("Received numbers via stream: {:?}", streamed_numbers);
+
+    // The same code can run against the real library or the in-process mock backend.
+    println!("\nSwapping backends:");
+    describe_circle("Go library", &circle_lib, radius)?;
+    let mock = MockBackend::new().with_area(radius, 100.0);
+    describe_circle("Mock backend", &mock, radius)?;
+    println!(
+        "Mock calculate_circle_area calls: {}",
+        mock.call_count("calculate_circle_area")
+    );
+
+    // Services can hold any backend behind a trait object.
+    let apis: Vec<Box<dyn go_rust_ffi::backend::CircleApi>> =
+        vec![Box::new(circle_lib.clone()), Box::new(MockBackend::new())];
+    for api in &apis {
+        println!(
+            "Area through a trait object: {}",
+            api.calculate_circle_area_async(radius).await?
+        );
+    }
+
+    #[cfg(feature = "cache")]
+    {
+        let cached = CachedCircleLibrary::new(MockBackend::new()).with_cache_capacity(8);
+        for _ in 0..3 {
+            cached.calculate_circle_area(radius)?;
+        }
+        println!(
+            "Cached backend calls for three identical radii: {}",
+            cached.backend().call_count("calculate_circle_area")
+        );
+    }
+
+    #[cfg(feature = "metrics")]
+    println!("Call statistics: {:?}", circle_lib.stats());
 
+    // Shut down with a call still in flight; FlushAsync lets it complete right away.
+    let in_flight = tokio::spawn({
+        let lib = circle_lib.clone();
+        async move { lib.calculate_circle_area_async(radius).await }
+    });
+    tokio::task::yield_now().await;
+    circle_lib.shutdown().await;
+    println!("Area completed during shutdown: {}", in_flight.await??);
+
+    Ok(())
+}
+
+/// Prints circle information using any `CircleBackend`.
+fn describe_circle<B: CircleBackend>(
+    name: &str,
+    backend: &B,
+    radius: f64,
+) -> Result<(), LibraryError> {
+    println!(
+        "{}: area {} / {}",
+        name,
+        backend.calculate_circle_area(radius)?,
+        backend.format_circle_info(radius)?
+    );
     Ok(())
 }
 
@@ -0,0 +1,133 @@
+use crate::error::LibraryError;
+use crate::loader::open_library;
+use crate::{CircleLibrary, CircleLibraryBuilder, LoadFlags};
+use libloading::Library;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Loads shared libraries once and hands out shared handles to them.
+///
+/// Libraries are cached by path, so loading the same path twice returns the same
+/// underlying `Library`. Every `CircleLibrary` built by the manager holds a reference to
+/// its handle; `unload_unused` drops the handles nothing else refers to any more. As with
+/// `CircleLibrary`, the library itself stays loaded until the process exits.
+#[derive(Debug, Default)]
+pub struct LibraryManager {
+    libraries: Mutex<HashMap<PathBuf, Arc<Library>>>,
+}
+
+impl LibraryManager {
+    /// Creates a manager with no libraries loaded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the library at `path`, loading it on first use.
+    ///
+    /// Paths are canonicalized when possible so different spellings of the same file share
+    /// a handle; paths that cannot be canonicalized (e.g. bare names resolved by the
+    /// system loader) are cached as given.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::Unsupported` if the platform cannot load libraries dynamically,
+    /// or `LibraryError::LoadFailed` if the library cannot be loaded.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Arc<Library>, LibraryError> {
+        self.load_with_flags(path, LoadFlags::new())
+    }
+
+    /// Like `load`, but opens the library with `flags` on Unix if it is not cached yet.
+    ///
+    /// A library that is already cached is returned as is, whatever flags it was opened
+    /// with.
+    ///
+    /// # Errors
+    /// Returns any error `load` can return.
+    pub fn load_with_flags(
+        &self,
+        path: impl AsRef<Path>,
+        flags: LoadFlags,
+    ) -> Result<Arc<Library>, LibraryError> {
+        let path = path.as_ref();
+        let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut libraries = self
+            .libraries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(lib) = libraries.get(&key) {
+            return Ok(Arc::clone(lib));
+        }
+        let lib = Arc::new(open_library(path, flags)?);
+        libraries.insert(key, Arc::clone(&lib));
+        Ok(lib)
+    }
+
+    /// Returns a `CircleLibrary` bound to the cached library at `path`, loading it on
+    /// first use.
+    ///
+    /// # Errors
+    /// Returns any error `CircleLibrary::new` can return.
+    pub fn circle_library(&self, path: impl AsRef<Path>) -> Result<CircleLibrary, LibraryError> {
+        self.circle_library_with(path, &CircleLibraryBuilder::new())
+    }
+
+    /// Like `circle_library`, but resolves symbols as configured by `options`, and opens
+    /// the library with its load flags if it is not cached yet.
+    ///
+    /// # Errors
+    /// Returns any error `CircleLibraryBuilder::build` can return.
+    pub fn circle_library_with(
+        &self,
+        path: impl AsRef<Path>,
+        options: &CircleLibraryBuilder,
+    ) -> Result<CircleLibrary, LibraryError> {
+        let path = path.as_ref();
+        let lib = self.load_with_flags(path, options.load_flags)?;
+        CircleLibrary::from_library(lib, path, options)
+    }
+
+    /// Drops every cached library that is not referenced outside the manager and returns
+    /// how many were dropped.
+    pub fn unload_unused(&self) -> usize {
+        let mut libraries = self
+            .libraries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let before = libraries.len();
+        libraries.retain(|_, lib| Arc::strong_count(lib) > 1);
+        before - libraries.len()
+    }
+
+    /// Returns how many libraries are currently cached.
+    pub fn len(&self) -> usize {
+        self.libraries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Returns true if no libraries are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_from_a_poisoned_lock() {
+        let manager = LibraryManager::new();
+        std::thread::scope(|scope| {
+            let poisoner = scope.spawn(|| {
+                let _libraries = manager.libraries.lock().unwrap();
+                panic!("poison the cache");
+            });
+            assert!(poisoner.join().is_err());
+        });
+        assert!(manager.is_empty());
+        assert_eq!(manager.unload_unused(), 0);
+        assert!(manager.load("does-not-exist.so").is_err());
+    }
+}
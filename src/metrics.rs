@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How often one `CircleLibrary` method was called and the total time spent in it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MethodStats {
+    pub calls: u64,
+    pub total_time: Duration,
+}
+
+/// A snapshot of per-method call statistics, returned by `CircleLibrary::stats`.
+#[derive(Debug, Clone, Default)]
+pub struct CallStats {
+    methods: HashMap<&'static str, MethodStats>,
+}
+
+impl CallStats {
+    /// Returns the statistics of the named method, e.g. `"calculate_circle_area"`.
+    ///
+    /// Methods that have not been called report zero calls.
+    pub fn get(&self, method: &str) -> MethodStats {
+        self.methods.get(method).copied().unwrap_or_default()
+    }
+
+    /// Returns the statistics of every method that has been called at least once.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, MethodStats)> + '_ {
+        self.methods.iter().map(|(&method, &stats)| (method, stats))
+    }
+
+    /// Returns the total number of calls across all methods.
+    pub fn total_calls(&self) -> u64 {
+        self.methods.values().map(|stats| stats.calls).sum()
+    }
+}
+
+/// Per-method counters shared by a `CircleLibrary` and its clones.
+#[derive(Debug, Default)]
+pub(crate) struct CallMetrics {
+    methods: Mutex<HashMap<&'static str, MethodStats>>,
+}
+
+impl CallMetrics {
+    /// Starts timing a call to `method`; the call is recorded when the timer is dropped.
+    pub(crate) fn start(&self, method: &'static str) -> CallTimer<'_> {
+        CallTimer {
+            metrics: self,
+            method,
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> CallStats {
+        CallStats {
+            methods: self
+                .methods
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        }
+    }
+
+    fn record(&self, method: &'static str, elapsed: Duration) {
+        let mut methods = self.methods.lock().unwrap_or_else(PoisonError::into_inner);
+        let stats = methods.entry(method).or_default();
+        stats.calls += 1;
+        stats.total_time += elapsed;
+    }
+}
+
+/// Records one call of a method when dropped, created by `CallMetrics::start`.
+pub(crate) struct CallTimer<'a> {
+    metrics: &'a CallMetrics,
+    method: &'static str,
+    started: Instant,
+}
+
+impl Drop for CallTimer<'_> {
+    fn drop(&mut self) {
+        self.metrics.record(self.method, self.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_calls_after_the_lock_is_poisoned() {
+        let metrics = CallMetrics::default();
+        drop(metrics.start("calculate_circle_area"));
+        std::thread::scope(|scope| {
+            let poisoner = scope.spawn(|| {
+                let _methods = metrics.methods.lock().unwrap();
+                panic!("poison the counters");
+            });
+            assert!(poisoner.join().is_err());
+        });
+        drop(metrics.start("calculate_circle_area"));
+        let stats = metrics.snapshot();
+        assert_eq!(stats.get("calculate_circle_area").calls, 2);
+        assert_eq!(stats.total_calls(), 2);
+    }
+}
@@ -0,0 +1,126 @@
+//! Tracking of one-shot async calls whose Go callback has not fired yet.
+//!
+//! Instead of handing Go a `Box<oneshot::Sender<f64>>` that leaks if the callback never
+//! fires, each call registers its sender here and passes only its numeric id as user data.
+//! The callback looks the id up and removes the sender, so senders can be reclaimed at any
+//! time: a late or duplicate callback for a reclaimed id simply finds nothing to send to.
+//! Calls that report progress also keep their progress sender here, so the progress stream
+//! ends as soon as the call completes or is reclaimed.
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tokio::sync::{mpsc, oneshot};
+
+struct PendingCall {
+    // Id of the `CircleLibrary` that started the call.
+    owner: usize,
+    sender: oneshot::Sender<f64>,
+    progress: Option<mpsc::UnboundedSender<f32>>,
+}
+
+lazy_static! {
+    static ref PENDING_CALLS: Mutex<HashMap<usize, PendingCall>> = Mutex::new(HashMap::new());
+}
+
+/// Locks the pending calls, recovering from poisoning.
+///
+/// The map is never left half-updated, so a panic on another thread while it held the lock
+/// does not make the calls unusable.
+fn pending_calls() -> MutexGuard<'static, HashMap<usize, PendingCall>> {
+    PENDING_CALLS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Ids start at 1 so the user data pointer handed to Go is never null.
+static NEXT_CALL_ID: AtomicUsize = AtomicUsize::new(1);
+static NEXT_OWNER_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Returns a new id identifying a loaded library as the owner of pending calls.
+pub(crate) fn new_owner_id() -> usize {
+    NEXT_OWNER_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Registers `sender` as pending for `owner` and returns the id to pass to Go.
+pub(crate) fn register(owner: usize, sender: oneshot::Sender<f64>) -> usize {
+    insert(owner, sender, None)
+}
+
+/// Like `register`, but also keeps a sender for the progress reports of the call.
+pub(crate) fn register_with_progress(
+    owner: usize,
+    sender: oneshot::Sender<f64>,
+    progress: mpsc::UnboundedSender<f32>,
+) -> usize {
+    insert(owner, sender, Some(progress))
+}
+
+fn insert(
+    owner: usize,
+    sender: oneshot::Sender<f64>,
+    progress: Option<mpsc::UnboundedSender<f32>>,
+) -> usize {
+    let id = NEXT_CALL_ID.fetch_add(1, Ordering::Relaxed);
+    pending_calls().insert(
+        id,
+        PendingCall {
+            owner,
+            sender,
+            progress,
+        },
+    );
+    id
+}
+
+/// Forwards a progress report to the call with the given id.
+///
+/// Returns false if the id is unknown or the call does not report progress.
+pub(crate) fn report_progress(id: usize, fraction: f32) -> bool {
+    match pending_calls().get(&id) {
+        // The progress receiver may already be gone; the result is still awaited.
+        Some(PendingCall {
+            progress: Some(progress),
+            ..
+        }) => {
+            let _ = progress.send(fraction);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Delivers `result` to the call with the given id and removes it.
+///
+/// Returns false if the id is unknown, e.g. because it was already completed or reclaimed.
+pub(crate) fn complete(id: usize, result: f64) -> bool {
+    let call = pending_calls().remove(&id);
+    match call {
+        Some(call) => {
+            // The receiver may already be gone; there is nobody left to tell.
+            let _ = call.sender.send(result);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Removes the call with the given id without delivering a result.
+pub(crate) fn cancel(id: usize) {
+    pending_calls().remove(&id);
+}
+
+/// Removes every pending call started by `owner` and returns how many were reclaimed.
+pub(crate) fn reclaim(owner: usize) -> usize {
+    let mut calls = pending_calls();
+    let before = calls.len();
+    calls.retain(|_, call| call.owner != owner);
+    before - calls.len()
+}
+
+/// Returns how many calls started by `owner` are still waiting for their callback.
+pub(crate) fn count(owner: usize) -> usize {
+    pending_calls()
+        .values()
+        .filter(|call| call.owner == owner)
+        .count()
+}
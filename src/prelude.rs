@@ -0,0 +1,21 @@
+//! The types and traits needed for typical use of the crate.
+//!
+//! ```no_run
+//! use go_rust_ffi::prelude::*;
+//!
+//! # fn main() -> Result<(), LibraryError> {
+//! let circle_lib = CircleLibrary::new("lib.dll")?;
+//! let area = circle_lib.calculate_circle_area(2.0)?;
+//! let shape_area = circle_lib.calculate_shape_area(&Shape::rectangle(2.0, 3.0)?)?;
+//! let fallback = Circle { radius: 2.0 }.area();
+//! println!("{} {} {}", area, shape_area, fallback);
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::shapes::{Circle, Shape, ShapeArea, ShapeType};
+#[cfg(feature = "std")]
+pub use crate::{
+    backend::CircleBackend, error::LibraryError, iter::CircleAreaExt, CallbackType, CircleLibrary,
+    CircleLibraryBuilder, NumberGenerator,
+};
@@ -0,0 +1,247 @@
+use crate::error::LibraryError;
+use crate::{Shape, ShapeType};
+
+/// Builds a `Shape` from named dimensions, checking them against the shape type.
+///
+/// Each shape type accepts only its own dimensions: a circle has a `radius`, a square a
+/// `side`, a triangle a `base` and `height`, a rectangle a `width` and `height`, and an
+/// ellipse semi-axes `a` and `b`. Setting a dimension the type does not have, e.g. the
+/// `height` of a circle, or leaving out one it needs makes `build` fail, so a shape can
+/// never carry a stray value in an unused dimension field.
+///
+/// ```no_run
+/// use go_rust_ffi::ShapeBuilder;
+///
+/// let triangle = ShapeBuilder::triangle().base(4.0).height(3.0).build()?;
+/// assert!(ShapeBuilder::circle().radius(1.0).height(2.0).build().is_err());
+/// # Ok::<(), go_rust_ffi::error::LibraryError>(())
+/// ```
+#[derive(Debug)]
+pub struct ShapeBuilder {
+    shape_type: ShapeType,
+    dimensions: [Option<f64>; 2],
+    // The first dimension set that the shape type does not have, reported by `build`.
+    error: Option<LibraryError>,
+}
+
+impl ShapeBuilder {
+    /// Starts building a circle, which needs a `radius`.
+    pub fn circle() -> Self {
+        Self::new(ShapeType::Circle)
+    }
+
+    /// Starts building a square, which needs a `side`.
+    pub fn square() -> Self {
+        Self::new(ShapeType::Square)
+    }
+
+    /// Starts building a triangle, which needs a `base` and a `height`.
+    pub fn triangle() -> Self {
+        Self::new(ShapeType::Triangle)
+    }
+
+    /// Starts building a rectangle, which needs a `width` and a `height`.
+    pub fn rectangle() -> Self {
+        Self::new(ShapeType::Rectangle)
+    }
+
+    /// Starts building an ellipse, which needs semi-axes `a` and `b`.
+    pub fn ellipse() -> Self {
+        Self::new(ShapeType::Ellipse)
+    }
+
+    /// Sets the radius of a circle.
+    pub fn radius(self, radius: f64) -> Self {
+        self.set("radius", radius)
+    }
+
+    /// Sets the side length of a square.
+    pub fn side(self, side: f64) -> Self {
+        self.set("side", side)
+    }
+
+    /// Sets the base of a triangle.
+    pub fn base(self, base: f64) -> Self {
+        self.set("base", base)
+    }
+
+    /// Sets the height of a triangle or rectangle.
+    pub fn height(self, height: f64) -> Self {
+        self.set("height", height)
+    }
+
+    /// Sets the width of a rectangle.
+    pub fn width(self, width: f64) -> Self {
+        self.set("width", width)
+    }
+
+    /// Sets the semi-axis `a` of an ellipse.
+    pub fn a(self, a: f64) -> Self {
+        self.set("a", a)
+    }
+
+    /// Sets the semi-axis `b` of an ellipse.
+    pub fn b(self, b: f64) -> Self {
+        self.set("b", b)
+    }
+
+    /// Builds the shape.
+    ///
+    /// # Errors
+    /// Returns `LibraryError::InvalidInput` if a dimension the shape type does not have was
+    /// set, a dimension it needs is missing, or a dimension is negative, NaN or infinite.
+    pub fn build(self) -> Result<Shape, LibraryError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut values = [0.0; 2];
+        for (i, name) in dimension_names(self.shape_type).iter().enumerate() {
+            values[i] = self.dimensions[i].ok_or_else(|| {
+                LibraryError::InvalidInput(format!(
+                    "a {} requires a {}",
+                    type_name(self.shape_type),
+                    name
+                ))
+            })?;
+        }
+        let [d1, d2] = values;
+        match self.shape_type {
+            ShapeType::Circle => Shape::circle(d1),
+            ShapeType::Square => Shape::square(d1),
+            ShapeType::Triangle => Shape::triangle(d1, d2),
+            ShapeType::Rectangle => Shape::rectangle(d1, d2),
+            ShapeType::Ellipse => Shape::ellipse(d1, d2),
+        }
+    }
+
+    fn new(shape_type: ShapeType) -> Self {
+        ShapeBuilder {
+            shape_type,
+            dimensions: [None; 2],
+            error: None,
+        }
+    }
+
+    fn set(mut self, name: &str, value: f64) -> Self {
+        if self.error.is_none() {
+            match dimension_names(self.shape_type)
+                .iter()
+                .position(|n| *n == name)
+            {
+                Some(i) => self.dimensions[i] = Some(value),
+                None => {
+                    self.error = Some(LibraryError::InvalidInput(format!(
+                        "a {} has no {}",
+                        type_name(self.shape_type),
+                        name
+                    )))
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Returns the names of the dimensions of `shape_type`, in field order.
+fn dimension_names(shape_type: ShapeType) -> &'static [&'static str] {
+    match shape_type {
+        ShapeType::Circle => &["radius"],
+        ShapeType::Square => &["side"],
+        ShapeType::Triangle => &["base", "height"],
+        ShapeType::Rectangle => &["width", "height"],
+        ShapeType::Ellipse => &["a", "b"],
+    }
+}
+
+fn type_name(shape_type: ShapeType) -> &'static str {
+    match shape_type {
+        ShapeType::Circle => "circle",
+        ShapeType::Square => "square",
+        ShapeType::Triangle => "triangle",
+        ShapeType::Rectangle => "rectangle",
+        ShapeType::Ellipse => "ellipse",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_shape(shape: Shape, shape_type: ShapeType, dimension1: f64, dimension2: f64) {
+        assert_eq!(shape.shape_type.as_c_int(), shape_type.as_c_int());
+        assert_eq!(shape.dimension1, dimension1);
+        assert_eq!(shape.dimension2, dimension2);
+    }
+
+    #[test]
+    fn builds_each_shape() {
+        let circle = ShapeBuilder::circle().radius(2.0).build().unwrap();
+        assert_shape(circle, ShapeType::Circle, 2.0, 0.0);
+        let square = ShapeBuilder::square().side(3.0).build().unwrap();
+        assert_shape(square, ShapeType::Square, 3.0, 0.0);
+        let triangle = ShapeBuilder::triangle()
+            .base(4.0)
+            .height(3.0)
+            .build()
+            .unwrap();
+        assert_shape(triangle, ShapeType::Triangle, 4.0, 3.0);
+        // Dimensions may be set in any order.
+        let rectangle = ShapeBuilder::rectangle()
+            .height(2.0)
+            .width(5.0)
+            .build()
+            .unwrap();
+        assert_shape(rectangle, ShapeType::Rectangle, 5.0, 2.0);
+        let ellipse = ShapeBuilder::ellipse().a(3.0).b(2.0).build().unwrap();
+        assert_shape(ellipse, ShapeType::Ellipse, 3.0, 2.0);
+    }
+
+    #[test]
+    fn rejects_missing_dimensions() {
+        let missing = [
+            ShapeBuilder::circle(),
+            ShapeBuilder::square(),
+            ShapeBuilder::triangle().base(4.0),
+            ShapeBuilder::rectangle().height(2.0),
+            ShapeBuilder::ellipse().a(3.0),
+        ];
+        for builder in missing {
+            assert!(matches!(
+                builder.build(),
+                Err(LibraryError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_dimensions_the_shape_does_not_have() {
+        let stray = [
+            ShapeBuilder::circle().radius(1.0).height(2.0),
+            ShapeBuilder::square().side(1.0).width(2.0),
+            ShapeBuilder::triangle().base(1.0).height(1.0).a(2.0),
+            ShapeBuilder::rectangle().width(1.0).height(1.0).radius(2.0),
+            ShapeBuilder::ellipse().a(1.0).b(1.0).side(2.0),
+        ];
+        for builder in stray {
+            assert!(matches!(
+                builder.build(),
+                Err(LibraryError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_dimensions() {
+        assert!(ShapeBuilder::circle().radius(-1.0).build().is_err());
+        assert!(ShapeBuilder::triangle()
+            .base(4.0)
+            .height(f64::NAN)
+            .build()
+            .is_err());
+        assert!(ShapeBuilder::ellipse()
+            .a(f64::INFINITY)
+            .b(1.0)
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,392 @@
+//! The shape types shared with the Go library and their pure Rust area computations.
+//!
+//! Nothing in this module needs `std`, so it is also available when the crate is built
+//! without its default `std` feature, e.g. by embedded consumers that only need the struct
+//! definitions and the fallback math. The constructors that validate their input,
+//! `Shape::validate` and the conversion from `Shape` back to `Circle` report errors as
+//! `LibraryError`, so they are only built with `std`.
+
+#[cfg(feature = "std")]
+use crate::error::LibraryError;
+use core::ffi::{c_double, c_int};
+
+/// Enum representing different shape types, matching the C enum.
+///
+/// With the `serde` feature, shape types serialize to their lowercase name (`"circle"`)
+/// rather than the numeric discriminant.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ShapeType {
+    Circle = 0,
+    Square = 1,
+    Triangle = 2,
+    Rectangle = 3,
+    Ellipse = 4,
+}
+
+impl ShapeType {
+    /// Returns the shape type with the given C discriminant, or `None` if it is out of range.
+    ///
+    /// Use this for discriminants received from Go as a plain `c_int`; reading an unknown
+    /// value directly as a `ShapeType` would be undefined behavior.
+    pub fn from_c_int(value: c_int) -> Option<ShapeType> {
+        match value {
+            0 => Some(ShapeType::Circle),
+            1 => Some(ShapeType::Square),
+            2 => Some(ShapeType::Triangle),
+            3 => Some(ShapeType::Rectangle),
+            4 => Some(ShapeType::Ellipse),
+            _ => None,
+        }
+    }
+
+    /// Returns the C discriminant of the shape type.
+    pub fn as_c_int(self) -> c_int {
+        self as c_int
+    }
+}
+
+/// A shape struct with C layout that can represent different shapes.
+///
+/// Prefer the typed constructors (`Shape::circle`, `Shape::rectangle`, ...) over setting the
+/// raw dimension fields, since their meaning depends on the shape type.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shape {
+    pub shape_type: ShapeType,
+    /// Radius, side, base, width or semi-axis `a`, depending on `shape_type`.
+    pub dimension1: c_double,
+    /// Height or semi-axis `b`; unused for circles and squares.
+    pub dimension2: c_double,
+}
+
+impl Shape {
+    pub(crate) fn from_dimensions(shape_type: ShapeType, dimension1: f64, dimension2: f64) -> Self {
+        Shape {
+            shape_type,
+            dimension1,
+            dimension2,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Shape {
+    /// Creates a circle with the given radius.
+    pub fn circle(radius: f64) -> Result<Self, LibraryError> {
+        validate_dimension("radius", radius)?;
+        Ok(Self::from_dimensions(ShapeType::Circle, radius, 0.0))
+    }
+
+    /// Creates a square with the given side length.
+    pub fn square(side: f64) -> Result<Self, LibraryError> {
+        validate_dimension("side", side)?;
+        Ok(Self::from_dimensions(ShapeType::Square, side, 0.0))
+    }
+
+    /// Creates a triangle with the given base and height.
+    pub fn triangle(base: f64, height: f64) -> Result<Self, LibraryError> {
+        validate_dimension("base", base)?;
+        validate_dimension("height", height)?;
+        Ok(Self::from_dimensions(ShapeType::Triangle, base, height))
+    }
+
+    /// Creates a rectangle with the given width and height.
+    pub fn rectangle(width: f64, height: f64) -> Result<Self, LibraryError> {
+        validate_dimension("width", width)?;
+        validate_dimension("height", height)?;
+        Ok(Self::from_dimensions(ShapeType::Rectangle, width, height))
+    }
+
+    /// Creates an ellipse with the given semi-axes `a` and `b`.
+    pub fn ellipse(a: f64, b: f64) -> Result<Self, LibraryError> {
+        validate_dimension("a", a)?;
+        validate_dimension("b", b)?;
+        Ok(Self::from_dimensions(ShapeType::Ellipse, a, b))
+    }
+
+    /// Checks that the dimensions used by this shape's type are finite and non-negative.
+    ///
+    /// Shapes built with the typed constructors are always valid; this is for shapes whose
+    /// fields were set directly, e.g. after deserialization.
+    pub fn validate(&self) -> Result<(), LibraryError> {
+        match self.shape_type {
+            ShapeType::Circle => validate_dimension("radius", self.dimension1),
+            ShapeType::Square => validate_dimension("side", self.dimension1),
+            ShapeType::Triangle => {
+                validate_dimension("base", self.dimension1)?;
+                validate_dimension("height", self.dimension2)
+            }
+            ShapeType::Rectangle => {
+                validate_dimension("width", self.dimension1)?;
+                validate_dimension("height", self.dimension2)
+            }
+            ShapeType::Ellipse => {
+                validate_dimension("a", self.dimension1)?;
+                validate_dimension("b", self.dimension2)
+            }
+        }
+    }
+}
+
+/// Checks that a shape dimension or radius is a finite, non-negative number.
+#[cfg(feature = "std")]
+pub(crate) fn validate_dimension(name: &str, value: f64) -> Result<(), LibraryError> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(())
+    } else {
+        Err(LibraryError::InvalidInput(format!(
+            "{} must be a finite, non-negative number, got {}",
+            name, value
+        )))
+    }
+}
+
+/// Define a Rust struct with C layout representing a circle.
+/// Deriving Copy and Clone allows us to pass the struct by value.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Circle {
+    pub radius: c_double,
+}
+
+/// Computes the area of a shape in pure Rust, without the Go library.
+///
+/// Useful in tests or offline mode, and as the fallback of
+/// `CircleLibrary::calculate_shape_area_or_fallback`.
+pub trait ShapeArea {
+    /// Returns the area of the shape.
+    fn area(&self) -> f64;
+}
+
+impl ShapeArea for Circle {
+    fn area(&self) -> f64 {
+        core::f64::consts::PI * self.radius * self.radius
+    }
+}
+
+impl ShapeArea for Shape {
+    fn area(&self) -> f64 {
+        let (d1, d2) = (self.dimension1, self.dimension2);
+        match self.shape_type {
+            ShapeType::Circle => core::f64::consts::PI * d1 * d1,
+            ShapeType::Square => d1 * d1,
+            ShapeType::Triangle => 0.5 * d1 * d2,
+            ShapeType::Rectangle => d1 * d2,
+            ShapeType::Ellipse => core::f64::consts::PI * d1 * d2,
+        }
+    }
+}
+
+impl From<Circle> for Shape {
+    fn from(circle: Circle) -> Self {
+        Shape::from_dimensions(ShapeType::Circle, circle.radius, 0.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl TryFrom<Shape> for Circle {
+    type Error = LibraryError;
+
+    /// Converts a circle shape back into a `Circle`, using `dimension1` as the radius.
+    ///
+    /// Fails with `LibraryError::InvalidInput` if the shape is not a circle.
+    fn try_from(shape: Shape) -> Result<Self, Self::Error> {
+        match shape.shape_type {
+            ShapeType::Circle => Ok(Circle {
+                radius: shape.dimension1,
+            }),
+            other => Err(LibraryError::InvalidInput(format!(
+                "expected a circle shape, got {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ffi::c_int;
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn shapes_round_trip_through_json() {
+        let shapes = [
+            (ShapeType::Circle, "circle", 2.0, 0.0),
+            (ShapeType::Square, "square", 3.0, 0.0),
+            (ShapeType::Triangle, "triangle", 4.0, 3.0),
+            (ShapeType::Rectangle, "rectangle", 5.0, 2.0),
+            (ShapeType::Ellipse, "ellipse", 3.0, 2.0),
+        ];
+        for (shape_type, name, dimension1, dimension2) in shapes {
+            let shape = Shape::from_dimensions(shape_type, dimension1, dimension2);
+            let json = serde_json::to_string(&shape).unwrap();
+            assert!(
+                json.contains(&format!("\"shape_type\":\"{}\"", name)),
+                "{}",
+                json
+            );
+            let decoded: Shape = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded.shape_type.as_c_int(), shape_type.as_c_int());
+            assert_eq!(decoded.dimension1, dimension1);
+            assert_eq!(decoded.dimension2, dimension2);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn circle_round_trips_through_json() {
+        let json = serde_json::to_string(&Circle { radius: 1.5 }).unwrap();
+        assert_eq!(json, r#"{"radius":1.5}"#);
+        let decoded: Circle = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.radius, 1.5);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn unknown_shape_type_name_is_rejected() {
+        let json = r#"{"shape_type":"hexagon","dimension1":1.0,"dimension2":0.0}"#;
+        assert!(serde_json::from_str::<Shape>(json).is_err());
+    }
+
+    #[test]
+    fn serde_derives_keep_the_c_layout() {
+        assert_eq!(
+            core::mem::size_of::<ShapeType>(),
+            core::mem::size_of::<c_int>()
+        );
+        // The same fields as the C struct, with the shape type as a plain `int`.
+        #[repr(C)]
+        struct CShape {
+            _shape_type: c_int,
+            _dimension1: c_double,
+            _dimension2: c_double,
+        }
+        assert_eq!(
+            core::mem::size_of::<Shape>(),
+            core::mem::size_of::<CShape>()
+        );
+        assert_eq!(
+            core::mem::size_of::<Circle>(),
+            core::mem::size_of::<c_double>()
+        );
+    }
+
+    #[test]
+    fn shape_type_round_trips_through_c_int() {
+        let types = [
+            ShapeType::Circle,
+            ShapeType::Square,
+            ShapeType::Triangle,
+            ShapeType::Rectangle,
+            ShapeType::Ellipse,
+        ];
+        for (value, shape_type) in (0..).zip(types) {
+            assert_eq!(shape_type.as_c_int(), value);
+            let decoded = ShapeType::from_c_int(value).unwrap();
+            assert_eq!(decoded.as_c_int(), value);
+        }
+    }
+
+    #[test]
+    fn out_of_range_c_int_is_not_a_shape_type() {
+        for value in [-1, 5, c_int::MIN, c_int::MAX] {
+            assert!(ShapeType::from_c_int(value).is_none());
+        }
+    }
+
+    // Only built by `cargo test --lib --no-default-features`, where the crate is `no_std`.
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn areas_are_computed_without_std() {
+        let shape = Shape::from(Circle { radius: 1.0 });
+        assert_eq!(shape.area(), core::f64::consts::PI);
+        let rectangle = Shape::from_dimensions(ShapeType::Rectangle, 2.0, 3.0);
+        assert_eq!(rectangle.area(), 6.0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shape_constructors_set_type_and_dimensions() {
+        let cases = [
+            (Shape::circle(2.0), ShapeType::Circle, 2.0, 0.0),
+            (Shape::square(3.0), ShapeType::Square, 3.0, 0.0),
+            (Shape::triangle(4.0, 3.0), ShapeType::Triangle, 4.0, 3.0),
+            (Shape::rectangle(5.0, 2.0), ShapeType::Rectangle, 5.0, 2.0),
+            (Shape::ellipse(3.0, 2.0), ShapeType::Ellipse, 3.0, 2.0),
+        ];
+        for (shape, shape_type, dimension1, dimension2) in cases {
+            let shape = shape.unwrap();
+            assert_eq!(shape.shape_type.as_c_int(), shape_type.as_c_int());
+            assert_eq!(shape.dimension1, dimension1);
+            assert_eq!(shape.dimension2, dimension2);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shape_areas() {
+        let pi = std::f64::consts::PI;
+        assert_eq!(Shape::circle(2.0).unwrap().area(), pi * 4.0);
+        assert_eq!(Shape::square(3.0).unwrap().area(), 9.0);
+        assert_eq!(Shape::triangle(4.0, 3.0).unwrap().area(), 6.0);
+        assert_eq!(Shape::rectangle(5.0, 2.0).unwrap().area(), 10.0);
+        assert_eq!(Shape::ellipse(3.0, 2.0).unwrap().area(), pi * 6.0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shape_constructors_reject_invalid_dimensions() {
+        for value in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                Shape::circle(value),
+                Err(LibraryError::InvalidInput(_))
+            ));
+            assert!(Shape::square(value).is_err());
+            assert!(Shape::triangle(1.0, value).is_err());
+            assert!(Shape::rectangle(value, 1.0).is_err());
+            assert!(Shape::ellipse(1.0, value).is_err());
+        }
+        assert!(Shape::circle(0.0).is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn circle_converts_to_and_from_shape() {
+        let shape = Shape::from(Circle { radius: 2.5 });
+        assert!(matches!(shape.shape_type, ShapeType::Circle));
+        assert_eq!(shape.dimension1, 2.5);
+        assert_eq!(shape.dimension2, 0.0);
+        let circle = Circle::try_from(shape).unwrap();
+        assert_eq!(circle.radius, 2.5);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn non_circle_shape_does_not_convert_to_circle() {
+        let square = Shape::square(2.0).unwrap();
+        assert!(matches!(
+            Circle::try_from(square),
+            Err(LibraryError::InvalidInput(_))
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shape_with_non_finite_dimension_is_invalid() {
+        let mut shape = Shape::rectangle(1.0, 1.0).unwrap();
+        shape.dimension2 = f64::NAN;
+        assert!(matches!(
+            shape.validate(),
+            Err(LibraryError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,129 @@
+//! Ownership transfer of Rust values through the `*mut c_void` user data of Go callbacks.
+//!
+//! Go treats user data as an opaque pointer and hands it back to every callback of the
+//! call it was passed to. `UserData` keeps the `Box::into_raw`/`Box::from_raw` pairing in
+//! one place: a value is boxed and converted to a pointer with `into_raw`, the callbacks
+//! borrow it through `get`/`get_mut`, and the final callback (typically a "done"
+//! callback) reclaims and drops it with `from_raw`.
+//!
+//! ```ignore
+//! let user_data = UserData::new(state).into_raw();
+//! unsafe { start(radius, result_trampoline, done_trampoline, user_data) };
+//!
+//! // In `result_trampoline`, for every result:
+//! let state = unsafe { UserData::<State>::get(user_data) };
+//! // In `done_trampoline`, exactly once:
+//! drop(unsafe { UserData::<State>::from_raw(user_data) });
+//! ```
+
+use std::ops::{Deref, DerefMut};
+use std::os::raw::c_void;
+
+/// A boxed value whose ownership can be handed to Go as a user data pointer.
+#[derive(Debug)]
+pub struct UserData<T> {
+    value: Box<T>,
+}
+
+impl<T> UserData<T> {
+    /// Boxes `value` so it can be passed as user data.
+    pub fn new(value: T) -> Self {
+        UserData {
+            value: Box::new(value),
+        }
+    }
+
+    /// Releases ownership of the value and returns it as a non-null user data pointer.
+    ///
+    /// The value is leaked unless the pointer is passed to `from_raw` exactly once, after
+    /// the last callback borrowing it has returned.
+    pub fn into_raw(self) -> *mut c_void {
+        Box::into_raw(self.value) as *mut c_void
+    }
+
+    /// Takes back ownership of a value released by `into_raw`.
+    ///
+    /// # Safety
+    /// `ptr` must come from `UserData::<T>::into_raw` with the same `T`, must not have been
+    /// reclaimed already, and must not be used by Go or any callback after this call.
+    pub unsafe fn from_raw(ptr: *mut c_void) -> Self {
+        UserData {
+            value: Box::from_raw(ptr as *mut T),
+        }
+    }
+
+    /// Borrows the value behind a pointer released by `into_raw`, without taking ownership.
+    ///
+    /// # Safety
+    /// `ptr` must come from `UserData::<T>::into_raw` with the same `T` and must not be
+    /// reclaimed while the reference is alive. `T` must be `Sync` if callbacks may run on
+    /// several threads at once.
+    pub unsafe fn get<'a>(ptr: *mut c_void) -> &'a T {
+        &*(ptr as *const T)
+    }
+
+    /// Mutably borrows the value behind a pointer released by `into_raw`.
+    ///
+    /// # Safety
+    /// As for `get`, and additionally no other reference to the value may be alive, i.e.
+    /// Go must not invoke the callbacks of the call concurrently.
+    pub unsafe fn get_mut<'a>(ptr: *mut c_void) -> &'a mut T {
+        &mut *(ptr as *mut T)
+    }
+
+    /// Returns the value, consuming the box.
+    pub fn into_inner(self) -> T {
+        *self.value
+    }
+}
+
+impl<T> Deref for UserData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for UserData<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn value_round_trips_through_a_raw_pointer() {
+        let ptr = UserData::new(vec![1, 2, 3]).into_raw();
+        assert!(!ptr.is_null());
+        unsafe {
+            assert_eq!(UserData::<Vec<i32>>::get(ptr), &[1, 2, 3]);
+            UserData::<Vec<i32>>::get_mut(ptr).push(4);
+            assert_eq!(
+                UserData::<Vec<i32>>::from_raw(ptr).into_inner(),
+                [1, 2, 3, 4]
+            );
+        }
+    }
+
+    #[test]
+    fn from_raw_drops_the_value_exactly_once() {
+        let value = Rc::new(());
+        let ptr = UserData::new(Rc::clone(&value)).into_raw();
+        assert_eq!(Rc::strong_count(&value), 2);
+        drop(unsafe { UserData::<Rc<()>>::from_raw(ptr) });
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn derefs_to_the_value() {
+        let mut data = UserData::new(String::from("circle"));
+        data.push('s');
+        assert_eq!(data.len(), 7);
+        assert_eq!(data.into_inner(), "circles");
+    }
+}
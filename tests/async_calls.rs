@@ -0,0 +1,387 @@
+//! Exercises the asynchronous calls against the C fixture library.
+
+#![cfg(unix)]
+
+mod common;
+
+use go_rust_ffi::error::LibraryError;
+use go_rust_ffi::CircleLibrary;
+use std::f64::consts::PI;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+async fn collect(mut rx: mpsc::UnboundedReceiver<f64>) -> Vec<f64> {
+    let mut results = Vec::new();
+    while let Some(area) = rx.recv().await {
+        results.push(area);
+    }
+    results
+}
+
+#[tokio::test]
+async fn concurrent_multi_shot_calls_receive_their_own_results() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let small = circle_lib.calculate_circle_area_async_multi(1.0).unwrap();
+    let large = circle_lib.calculate_circle_area_async_multi(2.0).unwrap();
+    let (small, large) = tokio::join!(collect(small), collect(large));
+    assert_eq!(small, [PI; 3]);
+    assert_eq!(large, [PI * 4.0; 3]);
+}
+
+#[tokio::test]
+async fn multi_shot_rejects_invalid_radius() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert!(matches!(
+        circle_lib.calculate_circle_area_async_multi(-1.0),
+        Err(LibraryError::InvalidInput(_))
+    ));
+}
+
+#[tokio::test]
+async fn one_shot_call_completes() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let area = circle_lib.calculate_circle_area_async(2.0).await.unwrap();
+    assert_eq!(area, PI * 4.0);
+    assert_eq!(circle_lib.pending_async_calls(), 0);
+}
+
+#[tokio::test]
+async fn call_whose_callback_never_fires_times_out() {
+    let path = common::fixture_with("never_fires", &["FIXTURE_ASYNC_NEVER_FIRES"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let timeout = Duration::from_millis(50);
+    let result = circle_lib
+        .calculate_circle_area_async_timeout(1.0, timeout)
+        .await;
+    assert!(matches!(result, Err(LibraryError::Timeout(dur)) if dur == timeout));
+    // The timed out call no longer counts as pending.
+    assert_eq!(circle_lib.pending_async_calls(), 0);
+}
+
+#[tokio::test]
+async fn multi_shot_results_as_a_stream() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let stream = circle_lib.calculate_circle_area_stream(3.0).unwrap();
+    let areas = stream.take(3).collect::<Vec<_>>().await;
+    assert_eq!(areas, [PI * 9.0; 3]);
+}
+
+#[tokio::test]
+async fn reclaiming_calls_whose_callback_never_fires_frees_them() {
+    let path = common::fixture_with("never_fires", &["FIXTURE_ASYNC_NEVER_FIRES"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    for _ in 0..3 {
+        let calls: Vec<_> = (0..100)
+            .map(|_| {
+                let circle_lib = circle_lib.clone();
+                tokio::spawn(async move { circle_lib.calculate_circle_area_async(1.0).await })
+            })
+            .collect();
+        while circle_lib.pending_async_calls() < calls.len() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(circle_lib.reclaim_pending(), calls.len());
+        for call in calls {
+            assert!(matches!(
+                call.await.unwrap(),
+                Err(LibraryError::CallbackDropped)
+            ));
+        }
+        // Every round starts from nothing, so abandoned calls do not accumulate.
+        assert_eq!(circle_lib.pending_async_calls(), 0);
+    }
+}
+
+#[tokio::test]
+async fn cancelling_a_multi_shot_call_stops_the_go_side() {
+    let path = common::fixture_with("multi_shot_long", &["FIXTURE_MULTI_SHOT_COUNT=50"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let (mut rx, handle) = circle_lib
+        .calculate_circle_area_async_multi_cancellable(1.0)
+        .unwrap();
+    assert_eq!(rx.recv().await, Some(PI));
+    assert_eq!(rx.recv().await, Some(PI));
+    handle.cancel();
+    // The channel closes once Go has seen the cancellation and called its done callback.
+    assert_eq!(rx.recv().await, None);
+    // Go invoked the callback once more, was told to stop, and produced nothing after.
+    assert_eq!(common::counter(&path, "FixtureMultiShotCallbacks"), 3);
+}
+
+#[tokio::test]
+async fn async_call_rejects_invalid_radii() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    for radius in [-5.0, f64::NAN, f64::INFINITY] {
+        assert!(matches!(
+            circle_lib.calculate_circle_area_async(radius).await,
+            Err(LibraryError::InvalidInput(_))
+        ));
+    }
+    assert_eq!(circle_lib.pending_async_calls(), 0);
+}
+
+#[test]
+fn blocking_bridge_works_without_a_runtime() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area_blocking(2.0).unwrap(),
+        PI * 4.0
+    );
+    assert_eq!(circle_lib.pending_async_calls(), 0);
+}
+
+#[tokio::test]
+async fn duplicate_one_shot_callback_is_ignored() {
+    let path = common::fixture_with("fires_twice", &["FIXTURE_ASYNC_FIRES_TWICE"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    for radius in [1.0, 2.0, 3.0] {
+        let area = circle_lib
+            .calculate_circle_area_async(radius)
+            .await
+            .unwrap();
+        assert_eq!(area, PI * radius * radius);
+    }
+    // Wait for every second callback to have been delivered and ignored.
+    while common::counter(&path, "FixtureDuplicateCallbacks") < 3 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(circle_lib.pending_async_calls(), 0);
+    assert_eq!(
+        circle_lib.calculate_circle_area_async(1.0).await.unwrap(),
+        PI
+    );
+}
+
+#[tokio::test]
+async fn dropping_the_receiver_stops_the_go_side() {
+    let path = common::fixture_with("multi_shot_dropped", &["FIXTURE_MULTI_SHOT_COUNT=50"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let mut rx = circle_lib.calculate_circle_area_async_multi(1.0).unwrap();
+    assert_eq!(rx.recv().await, Some(PI));
+    drop(rx);
+    // Go would deliver a result every 10 ms; after the next one it is told to stop.
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(common::counter(&path, "FixtureMultiShotCallbacks"), 2);
+}
+
+#[test]
+fn runtime_shutting_down_mid_stream_stops_the_go_side() {
+    let path = common::fixture_with("multi_shot_shutdown", &["FIXTURE_MULTI_SHOT_COUNT=50"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut rx = circle_lib.calculate_circle_area_async_multi(1.0).unwrap();
+        assert_eq!(rx.recv().await, Some(PI));
+        // Hand the receiver to a task that is dropped, unfinished, with the runtime.
+        tokio::spawn(async move { while rx.recv().await.is_some() {} });
+    });
+    drop(runtime);
+    std::thread::sleep(Duration::from_millis(150));
+    assert_eq!(common::counter(&path, "FixtureMultiShotCallbacks"), 2);
+}
+
+#[tokio::test]
+async fn progress_increases_up_to_one_before_the_area_arrives() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let (progress, area) = circle_lib
+        .calculate_circle_area_async_progress(2.0)
+        .unwrap();
+    let (fractions, area) = tokio::join!(progress.collect::<Vec<f32>>(), area);
+    assert_eq!(area.unwrap(), PI * 4.0);
+    assert!(!fractions.is_empty());
+    assert!(
+        fractions.windows(2).all(|pair| pair[0] < pair[1]),
+        "{:?}",
+        fractions
+    );
+    assert!(fractions.iter().all(|f| (0.0..=1.0).contains(f)));
+    assert_eq!(fractions.last(), Some(&1.0));
+}
+
+#[tokio::test]
+async fn bounded_channel_drops_results_a_slow_consumer_has_no_room_for() {
+    let path = common::fixture_with("multi_shot_bounded", &["FIXTURE_MULTI_SHOT_COUNT=20"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let (mut rx, dropped) = circle_lib
+        .calculate_circle_area_async_multi_bounded(1.0, 2)
+        .unwrap();
+    // Let Go produce every result before reading any of them.
+    while common::counter(&path, "FixtureMultiShotCallbacks") < 20 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let mut received = Vec::new();
+    while let Some(area) = rx.recv().await {
+        received.push(area);
+    }
+    assert_eq!(received, [PI; 2]);
+    assert_eq!(dropped.count(), 18);
+}
+
+#[tokio::test]
+async fn many_offloaded_calls_complete_without_blocking_the_executor() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let mut calls = tokio::task::JoinSet::new();
+    for i in 0..200 {
+        let radius = f64::from(i);
+        let call = circle_lib.calculate_circle_area_offloaded(radius);
+        calls.spawn(async move { (radius, call.await) });
+    }
+    // The test runtime has a single executor thread, which stays free to run this task.
+    let ticker = tokio::spawn(async {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    });
+    let mut completed = 0;
+    while let Some(joined) = calls.join_next().await {
+        let (radius, area) = joined.unwrap();
+        assert_eq!(area.unwrap(), PI * radius * radius);
+        completed += 1;
+    }
+    assert_eq!(completed, 200);
+    ticker.await.unwrap();
+}
+
+#[test]
+fn offloaded_call_is_cancelled_if_the_runtime_shuts_down_first() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert!(matches!(
+        common::run_after_shutdown(circle_lib.calculate_circle_area_offloaded(1.0)),
+        Err(LibraryError::TaskCancelled)
+    ));
+}
+
+#[tokio::test]
+async fn dropped_sender_is_an_error_or_the_default() {
+    let path = common::fixture_with("never_fires", &["FIXTURE_ASYNC_NEVER_FIRES"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let strict = {
+        let circle_lib = circle_lib.clone();
+        tokio::spawn(async move { circle_lib.calculate_circle_area_async(1.0).await })
+    };
+    let lenient = {
+        let circle_lib = circle_lib.clone();
+        tokio::spawn(async move { circle_lib.calculate_circle_area_async_or_default(1.0).await })
+    };
+    while circle_lib.pending_async_calls() < 2 {
+        tokio::task::yield_now().await;
+    }
+    // Reclaiming drops the senders without a result, as a library losing them would.
+    assert_eq!(circle_lib.reclaim_pending(), 2);
+    assert!(matches!(
+        strict.await.unwrap(),
+        Err(LibraryError::CallbackDropped)
+    ));
+    assert_eq!(lenient.await.unwrap().unwrap(), 0.0);
+}
+
+#[tokio::test]
+async fn poll_stream_produces_only_on_request() {
+    let path = common::fixture_with("poll", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let mut stream = circle_lib.calculate_circle_area_poll(2.0).unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(common::counter(&path, "FixturePollValues"), 0);
+    for produced in 1..=3 {
+        assert_eq!(stream.next().await, Some(PI * 4.0));
+        // Go is blocked waiting for the next request instead of running ahead.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(common::counter(&path, "FixturePollValues"), produced);
+    }
+    drop(stream);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while common::counter(&path, "FixturePollsDone") < 1 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("Go did not stop after the stream was dropped");
+    assert_eq!(common::counter(&path, "FixturePollValues"), 3);
+}
+
+#[tokio::test]
+async fn multi_shot_calls_fan_into_one_channel() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let (tx, rx) = mpsc::unbounded_channel();
+    circle_lib
+        .calculate_circle_area_async_multi_into(1.0, tx.clone())
+        .unwrap();
+    circle_lib
+        .calculate_circle_area_async_multi_into(2.0, tx)
+        .unwrap();
+    // The channel closes once both calls have finished and dropped their senders.
+    let results = tokio::time::timeout(Duration::from_secs(5), collect(rx))
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 6);
+    assert_eq!(results.iter().filter(|&&area| area == PI).count(), 3);
+    assert_eq!(results.iter().filter(|&&area| area == PI * 4.0).count(), 3);
+}
+
+#[tokio::test]
+async fn concurrent_async_calls_pass_distinct_request_ids() {
+    let path = common::fixture_with("request_ids", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let mut calls = tokio::task::JoinSet::new();
+    for i in 1..=8 {
+        let circle_lib = circle_lib.clone();
+        let radius = f64::from(i);
+        calls.spawn(async move { (radius, circle_lib.calculate_circle_area_async(radius).await) });
+    }
+    while let Some(joined) = calls.join_next().await {
+        let (radius, area) = joined.unwrap();
+        assert_eq!(area.unwrap(), PI * radius * radius);
+    }
+    // Each call hands Go its own request id as user data.
+    assert_eq!(common::counter(&path, "FixtureDistinctAsyncUserData"), 8);
+}
+
+#[tokio::test]
+async fn shutdown_flushes_pending_calls() {
+    // Without the flush, each call would take a minute.
+    let path = common::fixture_with("slow_async", &["FIXTURE_ASYNC_DELAY_MS=60000"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let probe = circle_lib.clone();
+    let mut calls = tokio::task::JoinSet::new();
+    for i in 1..=5 {
+        let circle_lib = circle_lib.clone();
+        let radius = f64::from(i);
+        calls.spawn(async move { (radius, circle_lib.calculate_circle_area_async(radius).await) });
+    }
+    while probe.pending_async_calls() < 5 {
+        tokio::task::yield_now().await;
+    }
+    tokio::time::timeout(Duration::from_secs(5), circle_lib.shutdown())
+        .await
+        .expect("shutdown did not flush the pending calls");
+    assert_eq!(probe.pending_async_calls(), 0);
+    while let Some(joined) = calls.join_next().await {
+        let (radius, area) = joined.unwrap();
+        assert_eq!(area.unwrap(), PI * radius * radius);
+    }
+}
+
+#[tokio::test]
+async fn shutdown_reclaims_calls_that_never_fire() {
+    let path = common::fixture_with("never_fires", &["FIXTURE_ASYNC_NEVER_FIRES"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let probe = circle_lib.clone();
+    let call = {
+        let circle_lib = circle_lib.clone();
+        tokio::spawn(async move { circle_lib.calculate_circle_area_async(1.0).await })
+    };
+    while probe.pending_async_calls() < 1 {
+        tokio::task::yield_now().await;
+    }
+    // Waits out `SHUTDOWN_TIMEOUT` before giving up on the call.
+    circle_lib.shutdown().await;
+    assert_eq!(probe.pending_async_calls(), 0);
+    assert!(matches!(
+        call.await.unwrap(),
+        Err(LibraryError::CallbackDropped)
+    ));
+}
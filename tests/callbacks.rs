@@ -0,0 +1,196 @@
+//! Exercises Rust closures called back from the C fixture library.
+
+#![cfg(unix)]
+
+mod common;
+
+use go_rust_ffi::error::CallbackError;
+use go_rust_ffi::generator::{GeneratorSymbols, NumberGenerator};
+use go_rust_ffi::{CircleLibrary, Shape, ShapeType};
+use libloading::Library;
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn panicking_closure_is_resumed_after_the_call_returns() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        circle_lib.call_callback_with(1.0, |_| panic!("closure failed"))
+    }));
+    let payload = outcome.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"closure failed"));
+    // The library is still usable after the panic crossed back into Rust.
+    assert_eq!(circle_lib.call_callback_with(2.0, |val| val * 3.0), 6.0);
+}
+
+#[test]
+fn panicking_context_closure_is_resumed_after_the_call_returns() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let mut calls = 0;
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        circle_lib.call_callback_with_context(1.0, &mut calls, |calls, _| {
+            *calls += 1;
+            panic!("closure failed")
+        })
+    }));
+    assert!(outcome.is_err());
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn panicking_concurrent_closure_is_resumed_after_every_call_returns() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        circle_lib.call_callback_concurrent(&[1.0, 2.0, 3.0], |val| {
+            if val == 2.0 {
+                panic!("closure failed");
+            }
+            val
+        })
+    }));
+    assert!(outcome.is_err());
+}
+
+#[test]
+fn context_closure_sums_into_local_state() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let mut sum = 0.0;
+    for val in [1.0, 2.5, 4.0] {
+        let result = circle_lib
+            .call_callback_with_context(val, &mut sum, |sum, val| {
+                *sum += val;
+                val * 2.0
+            })
+            .unwrap();
+        assert_eq!(result, val * 2.0);
+    }
+    assert_eq!(sum, 7.5);
+}
+
+#[test]
+fn int_closure_is_called_through_the_macro_trampoline() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let offset = 7;
+    assert_eq!(
+        circle_lib
+            .call_int_callback_with(6, move |val| val * val + offset)
+            .unwrap(),
+        43
+    );
+    assert_eq!(circle_lib.call_int_callback_with(-3, i32::abs).unwrap(), 3);
+}
+
+#[test]
+fn closure_error_is_surfaced_after_the_call_returns() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let error = circle_lib
+        .call_callback_try_with(-1.0, |val| {
+            if val < 0.0 {
+                Err(CallbackError::new("negative input"))
+            } else {
+                Ok(val.sqrt())
+            }
+        })
+        .unwrap_err();
+    assert_eq!(error.message(), "negative input");
+    // The failed closure was removed, so the next call uses only its own closure.
+    assert_eq!(
+        circle_lib
+            .call_callback_try_with(9.0, |val| Ok(val.sqrt()))
+            .unwrap(),
+        3.0
+    );
+}
+
+#[test]
+fn default_callback_coexists_with_per_call_closures() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    circle_lib.set_default_callback(|val| val * 2.0);
+    assert_eq!(circle_lib.call_callback_default(3.0).unwrap(), 6.0);
+    // A per-call closure takes precedence for its own call only.
+    assert_eq!(circle_lib.call_callback_with(3.0, |val| val + 1.0), 4.0);
+    assert_eq!(circle_lib.call_callback_default(4.0).unwrap(), 8.0);
+    circle_lib.clear_default_callback();
+    assert!(circle_lib.call_callback_default(4.0).is_err());
+}
+
+#[test]
+fn error_handler_receives_go_error_messages() {
+    let path = common::fixture_with("error_handler", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let received = Arc::clone(&messages);
+    circle_lib
+        .on_error(move |message| received.lock().unwrap().push(message.to_string()))
+        .unwrap();
+
+    let lib = Arc::new(unsafe { Library::new(&path) }.unwrap());
+    let symbols = GeneratorSymbols {
+        create: "CreateInvalidGenerator",
+        ..GeneratorSymbols::NUMBERS
+    };
+    let generator = NumberGenerator::with_symbols(&lib, symbols).unwrap();
+    assert_eq!(generator.next().unwrap(), None);
+    assert_eq!(
+        *messages.lock().unwrap(),
+        ["GetNextNumber2: unknown generator id 0"]
+    );
+}
+
+#[test]
+fn shape_closure_scales_a_triangle_passed_by_value() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let triangle = Shape::triangle(4.0, 3.0).unwrap();
+    let scaled = circle_lib
+        .transform_shape_with(triangle, |shape| Shape {
+            dimension1: shape.dimension1 * 2.0,
+            dimension2: shape.dimension2 * 3.0,
+            ..shape
+        })
+        .unwrap();
+    assert!(matches!(scaled.shape_type, ShapeType::Triangle));
+    assert_eq!((scaled.dimension1, scaled.dimension2), (8.0, 9.0));
+    assert_eq!(circle_lib.calculate_shape_area(&scaled).unwrap(), 36.0);
+}
+
+#[test]
+fn named_callbacks_are_dispatched_by_name() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    circle_lib.register_callback("square", |val| val * val);
+    circle_lib.register_callback("double", |val| val * 2.0);
+    assert_eq!(circle_lib.call_named_callback("square", 3.0).unwrap(), 9.0);
+    assert_eq!(circle_lib.call_named_callback("double", 3.0).unwrap(), 6.0);
+    assert!(circle_lib.call_named_callback("cube", 3.0).is_err());
+    assert!(circle_lib.unregister_callback("square"));
+    assert!(circle_lib.call_named_callback("square", 3.0).is_err());
+    assert_eq!(circle_lib.call_named_callback("double", 4.0).unwrap(), 8.0);
+}
+
+#[test]
+fn concurrent_callbacks_run_on_several_threads_at_once() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let arrived = AtomicUsize::new(0);
+    let threads = Mutex::new(HashSet::new());
+    let results = circle_lib
+        .call_callback_concurrent(&[1.0, 2.0, 3.0, 4.0], |val| {
+            threads.lock().unwrap().insert(thread::current().id());
+            // Each callback waits for the others, which only returns if all four are in
+            // flight together.
+            arrived.fetch_add(1, Ordering::SeqCst);
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while arrived.load(Ordering::SeqCst) < 4 {
+                assert!(Instant::now() < deadline, "callbacks did not overlap");
+                thread::yield_now();
+            }
+            val * 10.0
+        })
+        .unwrap();
+    assert_eq!(results, [10.0, 20.0, 30.0, 40.0]);
+    let threads = threads.into_inner().unwrap();
+    assert_eq!(threads.len(), 4);
+    assert!(!threads.contains(&thread::current().id()));
+}
@@ -0,0 +1,562 @@
+//! Exercises the public API against the C fixture library.
+
+#![cfg(unix)]
+
+mod common;
+
+use go_rust_ffi::backend::CircleApi;
+use go_rust_ffi::error::{GoErrorCode, LibraryError};
+use go_rust_ffi::{Circle, CircleLibrary, Shape, ShapeArea, ShapeType, StringOwnership};
+
+#[test]
+fn calculates_circle_areas() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let area = circle_lib.calculate_circle_area(2.0).unwrap();
+    assert_eq!(area, std::f64::consts::PI * 4.0);
+    let struct_area = circle_lib
+        .calculate_circle_struct_area(&Circle { radius: 2.0 })
+        .unwrap();
+    assert_eq!(struct_area, area);
+}
+
+#[test]
+fn formats_circle_info() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert_eq!(
+        circle_lib.format_circle_info(2.0).unwrap(),
+        "Circle with radius 2.00 has area 12.57"
+    );
+}
+
+#[test]
+fn calls_a_closure_through_go() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let offset = 1.5;
+    assert_eq!(
+        circle_lib.call_callback_with(2.0, move |val| val * val + offset),
+        5.5
+    );
+}
+
+#[test]
+fn calculates_shape_areas() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let triangle = Shape::triangle(4.0, 3.0).unwrap();
+    assert_eq!(circle_lib.calculate_shape_area(&triangle).unwrap(), 6.0);
+    let rectangle = Shape::rectangle(5.0, 2.0).unwrap();
+    assert_eq!(circle_lib.calculate_shape_area(&rectangle).unwrap(), 10.0);
+    let ellipse = Shape::ellipse(3.0, 2.0).unwrap();
+    assert_eq!(
+        circle_lib.calculate_shape_area(&ellipse).unwrap(),
+        std::f64::consts::PI * 6.0
+    );
+}
+
+#[test]
+fn rejects_a_missing_library() {
+    assert!(matches!(
+        CircleLibrary::new("does-not-exist.so"),
+        Err(LibraryError::LoadFailed(_))
+    ));
+}
+
+#[test]
+fn batch_areas_match_individual_calls() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let radii: Vec<f64> = (0..100).map(|i| f64::from(i) * 0.25).collect();
+    let batch = circle_lib.calculate_circle_areas(&radii);
+    assert_eq!(batch.len(), radii.len());
+    for (area, &radius) in batch.iter().zip(&radii) {
+        assert_eq!(*area, circle_lib.calculate_circle_area(radius).unwrap());
+    }
+    assert!(circle_lib.calculate_circle_areas(&[]).is_empty());
+}
+
+#[test]
+fn copies_circle_details_out_of_the_go_allocation() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let details = circle_lib.get_circle_details(3.0).unwrap();
+    assert_eq!(details.area, std::f64::consts::PI * 9.0);
+    assert_eq!(details.circumference, std::f64::consts::PI * 6.0);
+    assert_eq!(details.diameter, 6.0);
+}
+
+#[test]
+fn f32_area_is_close_to_the_f64_area() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    for radius in [0.5_f32, 2.0, 123.25] {
+        let narrow = circle_lib.calculate_circle_area_f32(radius).unwrap();
+        let wide = circle_lib.calculate_circle_area(f64::from(radius)).unwrap();
+        let relative_error = ((f64::from(narrow) - wide) / wide).abs();
+        assert!(
+            relative_error < 1e-6,
+            "{} vs {} for radius {}",
+            narrow,
+            wide,
+            radius
+        );
+    }
+}
+
+#[test]
+fn passes_labels_into_go() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert_eq!(
+        circle_lib.format_circle_info_labeled("", 2.0).unwrap(),
+        ": Circle with radius 2.00 has area 12.57"
+    );
+    assert_eq!(
+        circle_lib
+            .format_circle_info_labeled("Kreis ⭕ 円", 2.0)
+            .unwrap(),
+        "Kreis ⭕ 円: Circle with radius 2.00 has area 12.57"
+    );
+    assert!(matches!(
+        circle_lib.format_circle_info_labeled("before\0after", 2.0),
+        Err(LibraryError::InteriorNul(_))
+    ));
+}
+
+#[test]
+fn rejects_invalid_radii_before_calling_go() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    for radius in [-5.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(
+            matches!(
+                circle_lib.calculate_circle_area(radius),
+                Err(LibraryError::InvalidInput(_))
+            ),
+            "radius {} was accepted",
+            radius
+        );
+        assert!(matches!(
+            circle_lib.calculate_circle_struct_area(&Circle { radius }),
+            Err(LibraryError::InvalidInput(_))
+        ));
+    }
+    // The unchecked variant passes the radius straight to Go.
+    assert_eq!(
+        circle_lib.calculate_circle_area_unchecked(-5.0),
+        std::f64::consts::PI * 25.0
+    );
+    assert!(circle_lib
+        .calculate_circle_area_unchecked(f64::NAN)
+        .is_nan());
+}
+
+#[test]
+fn circumference_and_diameter_match_the_formulas() {
+    let full = CircleLibrary::new(&common::fixture()).unwrap();
+    // The minimal fixture does not export them, so they are computed in Rust.
+    let minimal = CircleLibrary::builder()
+        .optional_async()
+        .optional_shape()
+        .build(&common::fixture_with("minimal", &["FIXTURE_MINIMAL"]))
+        .unwrap();
+    assert!(full.capabilities().circumference && full.capabilities().diameter);
+    assert!(!minimal.capabilities().circumference && !minimal.capabilities().diameter);
+    for circle_lib in [&full, &minimal] {
+        for r in [0.0, 1.0, 2.5, 1e6] {
+            assert_eq!(circle_lib.circumference(r), 2.0 * std::f64::consts::PI * r);
+            assert_eq!(circle_lib.diameter(r), 2.0 * r);
+        }
+    }
+}
+
+#[test]
+fn maps_radii_to_running_area_totals() {
+    let path = common::fixture_with("map_radii", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    assert!(circle_lib.map_radii(&[]).unwrap().is_empty());
+    assert_eq!(
+        circle_lib.map_radii(&[2.0]).unwrap(),
+        [std::f64::consts::PI * 4.0]
+    );
+
+    let radii: Vec<f64> = (0..10_000).map(|i| f64::from(i % 7)).collect();
+    let mut total = 0.0;
+    let expected: Vec<f64> = radii
+        .iter()
+        .map(|r| {
+            total += std::f64::consts::PI * r * r;
+            total
+        })
+        .collect();
+    for _ in 0..20 {
+        assert_eq!(circle_lib.map_radii(&radii).unwrap(), expected);
+    }
+    // Every array Go allocated was handed back to `FreeDoubleArray`.
+    assert_eq!(common::counter(&path, "FixtureLiveDoubleArrays"), 0);
+}
+
+#[test]
+fn unit_circle_area_is_the_library_pi() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        circle_lib.pi()
+    );
+}
+
+#[test]
+fn batch_shape_areas_match_individual_calls() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let shapes = [
+        Shape::circle(1.5).unwrap(),
+        Shape::triangle(4.0, 3.0).unwrap(),
+        Shape::square(2.0).unwrap(),
+        Shape::circle(0.5).unwrap(),
+        Shape::triangle(1.0, 9.0).unwrap(),
+    ];
+    let areas = circle_lib.calculate_shape_areas(&shapes).unwrap();
+    assert_eq!(areas.len(), shapes.len());
+    for (area, shape) in areas.iter().zip(&shapes) {
+        assert_eq!(*area, circle_lib.calculate_shape_area(shape).unwrap());
+    }
+    assert!(circle_lib.calculate_shape_areas(&[]).unwrap().is_empty());
+
+    // One invalid shape rejects the whole batch.
+    let mut invalid = shapes;
+    invalid[3].dimension1 = f64::NAN;
+    assert!(matches!(
+        circle_lib.calculate_shape_areas(&invalid),
+        Err(LibraryError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn owned_go_string_is_freed_once_on_drop() {
+    let path = common::fixture_with("go_string", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let info = circle_lib.format_circle_info_owned(2.0).unwrap();
+    assert_eq!(
+        info.to_str().unwrap(),
+        "Circle with radius 2.00 has area 12.57"
+    );
+    assert_eq!(common::counter(&path, "FixtureStringFrees"), 0);
+    drop(info);
+    assert_eq!(common::counter(&path, "FixtureStringFrees"), 1);
+    assert_eq!(common::counter(&path, "FixtureLiveStrings"), 0);
+}
+
+#[test]
+fn integer_arithmetic_detects_wraparound_at_the_i32_boundary() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert_eq!(circle_lib.add_ints(i32::MAX - 1, 1).unwrap(), i32::MAX);
+    assert_eq!(circle_lib.add_ints(i32::MIN, 0).unwrap(), i32::MIN);
+    assert!(matches!(
+        circle_lib.add_ints(i32::MAX, 1),
+        Err(LibraryError::Overflow { .. })
+    ));
+    assert!(matches!(
+        circle_lib.add_ints(i32::MIN, -1),
+        Err(LibraryError::Overflow { .. })
+    ));
+    assert_eq!(
+        circle_lib.multiply_ints(46_340, 46_340).unwrap(),
+        2_147_395_600
+    );
+    assert_eq!(circle_lib.multiply_ints(6, -7).unwrap(), -42);
+    assert!(matches!(
+        circle_lib.multiply_ints(46_341, 46_341),
+        Err(LibraryError::Overflow { .. })
+    ));
+}
+
+#[test]
+fn go_and_rust_shape_areas_agree() {
+    let full = CircleLibrary::new(&common::fixture()).unwrap();
+    // The minimal fixture does not export `CalculateShapeArea`, so it falls back to Rust.
+    let minimal = CircleLibrary::builder()
+        .optional_async()
+        .optional_shape()
+        .build(&common::fixture_with("minimal", &["FIXTURE_MINIMAL"]))
+        .unwrap();
+    let shapes = [
+        Shape::circle(1.5).unwrap(),
+        Shape::square(2.0).unwrap(),
+        Shape::triangle(4.0, 3.0).unwrap(),
+        Shape::rectangle(2.5, 4.0).unwrap(),
+        Shape::ellipse(3.0, 0.5).unwrap(),
+    ];
+    for shape in &shapes {
+        let go = full.calculate_shape_area(shape).unwrap();
+        assert!((go - shape.area()).abs() < 1e-12, "{:?}", shape);
+        assert_eq!(full.calculate_shape_area_or_fallback(shape), go);
+        assert_eq!(
+            minimal.calculate_shape_area_or_fallback(shape),
+            shape.area()
+        );
+    }
+    let circle = Circle { radius: 1.5 };
+    assert!((full.calculate_circle_struct_area(&circle).unwrap() - circle.area()).abs() < 1e-12);
+}
+
+#[test]
+fn static_strings_are_not_freed() {
+    let path = common::fixture_with("static_strings", &["FIXTURE_STATIC_STRINGS"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    assert_eq!(circle_lib.string_ownership(), StringOwnership::Static);
+    assert_eq!(
+        circle_lib.format_circle_info(2.0).unwrap(),
+        "Circle with radius 2.00 has area 12.57"
+    );
+    assert_eq!(
+        circle_lib.format_circle_info_labeled("a", 1.0).unwrap(),
+        "a: Circle with radius 1.00 has area 3.14"
+    );
+    drop(circle_lib.format_circle_info_owned(3.0).unwrap());
+    assert_eq!(common::counter(&path, "FixtureStringFrees"), 0);
+
+    let owned = CircleLibrary::new(&common::fixture()).unwrap();
+    assert_eq!(owned.string_ownership(), StringOwnership::Owned);
+}
+
+#[test]
+fn generates_shapes_of_every_type() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert!(circle_lib.generate_shapes(0).unwrap().is_empty());
+    let shapes = circle_lib.generate_shapes(7).unwrap();
+    assert_eq!(shapes.len(), 7);
+    for shape in &shapes {
+        shape.validate().unwrap();
+        assert!(circle_lib.try_calculate_shape_area(shape).is_ok());
+    }
+    assert!(matches!(shapes[0].shape_type, ShapeType::Circle));
+    assert!(matches!(shapes[4].shape_type, ShapeType::Ellipse));
+    assert!(matches!(shapes[5].shape_type, ShapeType::Circle));
+    // The fixture produces at most 64 shapes, fewer than requested.
+    assert_eq!(circle_lib.generate_shapes(1000).unwrap().len(), 64);
+}
+
+#[test]
+fn reports_area_metadata_when_exported() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let meta = circle_lib.area_metadata().unwrap();
+    assert_eq!((meta.precision_bits, meta.unit), (64, 0));
+
+    let minimal = CircleLibrary::builder()
+        .optional_async()
+        .optional_shape()
+        .build(&common::fixture_with("minimal", &["FIXTURE_MINIMAL"]))
+        .unwrap();
+    assert!(minimal.area_metadata().is_none());
+}
+
+#[tokio::test]
+async fn live_circles_forward_to_the_library() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let circle = circle_lib.circle(3.0);
+    assert_eq!(circle.radius(), 3.0);
+    assert_eq!(circle.to_circle().radius, 3.0);
+    assert_eq!(
+        circle.area().unwrap(),
+        circle_lib.calculate_circle_area(3.0).unwrap()
+    );
+    assert_eq!(circle.circumference(), circle_lib.circumference(3.0));
+    assert_eq!(circle.diameter(), 6.0);
+    assert_eq!(
+        circle.info().unwrap(),
+        "Circle with radius 3.00 has area 28.27"
+    );
+    assert_eq!(
+        circle.area_async().await.unwrap(),
+        std::f64::consts::PI * 9.0
+    );
+    assert!(matches!(
+        circle_lib.circle(-1.0).area(),
+        Err(LibraryError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn formats_into_a_reused_buffer() {
+    let path = common::fixture_with("format_into", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let mut buf = String::from("stale contents");
+    for r in 1..=20 {
+        let radius = r as f64;
+        circle_lib
+            .format_circle_info_into(radius, &mut buf)
+            .unwrap();
+        assert_eq!(
+            buf,
+            format!(
+                "Circle with radius {:.2} has area {:.2}",
+                radius,
+                std::f64::consts::PI * radius * radius
+            )
+        );
+    }
+    assert_eq!(common::counter(&path, "FixtureStringFrees"), 20);
+    assert_eq!(common::counter(&path, "FixtureLiveStrings"), 0);
+}
+
+#[test]
+fn sums_areas_in_go() {
+    let path = common::fixture_with("sum_areas", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    // Empty input never reaches Go.
+    assert_eq!(circle_lib.sum_areas(&[]), 0.0);
+    assert_eq!(common::counter(&path, "FixtureSumAreasCalls"), 0);
+    assert_eq!(circle_lib.sum_areas(&[2.5]), 2.5);
+    assert_eq!(common::counter(&path, "FixtureSumAreasCalls"), 1);
+    let areas: Vec<f64> = (1..=100).map(f64::from).collect();
+    assert_eq!(circle_lib.sum_areas(&areas), 5050.0);
+    assert_eq!(common::counter(&path, "FixtureSumAreasCalls"), 2);
+}
+
+#[test]
+fn checked_shape_areas_surface_go_error_codes() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let triangle = Shape::triangle(4.0, 3.0).unwrap();
+    assert_eq!(
+        circle_lib.calculate_shape_area_checked(&triangle).unwrap(),
+        6.0
+    );
+    let degenerate = Shape::triangle(4.0, 0.0).unwrap();
+    assert!(matches!(
+        circle_lib.calculate_shape_area_checked(&degenerate),
+        Err(LibraryError::Go(GoErrorCode::DegenerateShape))
+    ));
+    // Built by hand, since the constructors reject it before Go could.
+    let negative = Shape {
+        shape_type: ShapeType::Triangle,
+        dimension1: -4.0,
+        dimension2: 3.0,
+    };
+    assert!(matches!(
+        circle_lib.calculate_shape_area_checked(&negative),
+        Err(LibraryError::Go(GoErrorCode::InvalidDimension))
+    ));
+}
+
+#[test]
+fn normalizes_shapes_in_place() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let mut shapes = [
+        Shape {
+            shape_type: ShapeType::Circle,
+            dimension1: -2.0,
+            dimension2: 7.0,
+        },
+        Shape {
+            shape_type: ShapeType::Triangle,
+            dimension1: 4.0,
+            dimension2: -3.0,
+        },
+    ];
+    circle_lib.normalize_shapes(&mut shapes).unwrap();
+    assert!(matches!(shapes[0].shape_type, ShapeType::Circle));
+    assert_eq!((shapes[0].dimension1, shapes[0].dimension2), (2.0, 0.0));
+    assert!(matches!(shapes[1].shape_type, ShapeType::Triangle));
+    assert_eq!((shapes[1].dimension1, shapes[1].dimension2), (4.0, 3.0));
+    circle_lib.normalize_shapes(&mut []).unwrap();
+}
+
+#[test]
+fn timed_calls_return_the_untimed_result() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let start = std::time::Instant::now();
+    let (area, elapsed) = circle_lib.timed_calculate_circle_area(3.0).unwrap();
+    // The measured time covers only part of what the caller observed.
+    assert!(elapsed <= start.elapsed());
+    assert_eq!(area, circle_lib.calculate_circle_area(3.0).unwrap());
+    assert!(matches!(
+        circle_lib.timed_calculate_circle_area(-3.0),
+        Err(LibraryError::InvalidInput(_))
+    ));
+
+    let (info, _) = circle_lib.timed(|lib| lib.format_circle_info(2.0));
+    assert_eq!(info.unwrap(), "Circle with radius 2.00 has area 12.57");
+}
+
+#[tokio::test]
+async fn the_library_works_as_a_trait_object() {
+    let api: Box<dyn CircleApi> = Box::new(CircleLibrary::new(&common::fixture()).unwrap());
+    assert_eq!(
+        api.calculate_circle_area(2.0).unwrap(),
+        std::f64::consts::PI * 4.0
+    );
+    assert_eq!(
+        api.calculate_circle_struct_area(&Circle { radius: 2.0 })
+            .unwrap(),
+        std::f64::consts::PI * 4.0
+    );
+    assert_eq!(
+        api.format_circle_info(2.0).unwrap(),
+        "Circle with radius 2.00 has area 12.57"
+    );
+    assert_eq!(
+        api.call_callback_with(2.0, Box::new(|val| val * 10.0)),
+        20.0
+    );
+    assert_eq!(
+        api.calculate_shape_area(&Shape::triangle(4.0, 3.0).unwrap())
+            .unwrap(),
+        6.0
+    );
+    assert_eq!(
+        api.calculate_circle_area_async(1.0).await.unwrap(),
+        std::f64::consts::PI
+    );
+    let mut rx = api.calculate_circle_area_async_multi(1.0).unwrap();
+    let mut results = 0;
+    while let Some(area) = rx.recv().await {
+        assert_eq!(area, std::f64::consts::PI);
+        results += 1;
+    }
+    assert_eq!(results, 3);
+}
+
+#[test]
+fn formats_several_radii_and_frees_every_string() {
+    let path = common::fixture_with("format_infos", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    assert_eq!(
+        circle_lib.format_circle_infos(&[1.0, 2.0, 3.0]).unwrap(),
+        [
+            "Circle with radius 1.00 has area 3.14",
+            "Circle with radius 2.00 has area 12.57",
+            "Circle with radius 3.00 has area 28.27",
+        ]
+    );
+    assert_eq!(common::counter(&path, "FixtureLiveStrings"), 0);
+    assert_eq!(common::counter(&path, "FixtureLiveStringArrays"), 0);
+    assert!(circle_lib.format_circle_infos(&[]).unwrap().is_empty());
+}
+
+#[test]
+fn a_null_info_entry_fails_but_frees_the_array() {
+    let path = common::fixture_with("null_info_entry", &["FIXTURE_NULL_INFO_ENTRY"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    assert!(matches!(
+        circle_lib.format_circle_infos(&[1.0, 2.0, 3.0]),
+        Err(LibraryError::NullPointer { .. })
+    ));
+    assert_eq!(common::counter(&path, "FixtureLiveStrings"), 0);
+    assert_eq!(common::counter(&path, "FixtureLiveStringArrays"), 0);
+}
+
+#[test]
+fn formats_circle_info_with_the_requested_precision() {
+    let path = common::fixture_with("precision", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    assert_eq!(
+        circle_lib.format_circle_info_prec(2.0, 0).unwrap(),
+        "Circle with radius 2 has area 13"
+    );
+    assert_eq!(
+        circle_lib.format_circle_info_prec(2.0, 2).unwrap(),
+        circle_lib.format_circle_info(2.0).unwrap()
+    );
+    assert_eq!(
+        circle_lib.format_circle_info_prec(2.0, 10).unwrap(),
+        "Circle with radius 2.0000000000 has area 12.5663706144"
+    );
+    assert!(matches!(
+        circle_lib.format_circle_info_prec(2.0, usize::MAX),
+        Err(LibraryError::InvalidInput(_))
+    ));
+    assert_eq!(common::counter(&path, "FixtureLiveStrings"), 0);
+}
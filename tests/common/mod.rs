@@ -0,0 +1,96 @@
+//! Builds the C fixture library that stands in for the Go library in integration tests.
+
+#![allow(dead_code)]
+
+use libloading::Library;
+use std::collections::HashMap;
+use std::env::consts::{DLL_EXTENSION, DLL_PREFIX};
+use std::future::Future;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::process::Command;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+/// Returns the path of the default fixture library, building it on first use.
+pub fn fixture() -> String {
+    fixture_with("circle", &[])
+}
+
+/// Returns the path of a fixture library built with the given preprocessor `defines`,
+/// e.g. `["FIXTURE_ABI_VERSION=1"]`, building it on first use.
+///
+/// Each `name` is built once per test binary and loaded as a library of its own, so tests
+/// that inspect the fixture's global state should use a name no other test in the same
+/// file uses.
+pub fn fixture_with(name: &str, defines: &[&str]) -> String {
+    static BUILT: OnceLock<Mutex<HashMap<String, PathBuf>>> = OnceLock::new();
+    let mut built = BUILT
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let path = built
+        .entry(name.to_string())
+        .or_insert_with(|| build(name, defines));
+    path.to_str().unwrap().to_string()
+}
+
+fn build(name: &str, defines: &[&str]) -> PathBuf {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let source = manifest_dir.join("tests/fixtures/circle.c");
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fixtures");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    // Prefix the test binary's name, so every binary builds its own copies.
+    let name = format!("{}_{}", env!("CARGO_CRATE_NAME"), name);
+    let path = out_dir.join(format!("{}{}.{}", DLL_PREFIX, name, DLL_EXTENSION));
+    // Build to a file of our own and rename it into place, so test binaries running at
+    // the same time never load a half-written library.
+    let partial = out_dir.join(format!("{}.{}.partial", name, std::process::id()));
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let mut command = Command::new(&compiler);
+    command
+        .args(["-shared", "-fPIC", "-pthread", "-O1", "-o"])
+        .arg(&partial)
+        .arg(&source);
+    for define in defines {
+        command.arg(format!("-D{}", define));
+    }
+    let status = command
+        .status()
+        .unwrap_or_else(|e| panic!("failed to run `{}`: {}", compiler, e));
+    assert!(status.success(), "failed to build fixture `{}`", name);
+    std::fs::rename(&partial, &path).unwrap();
+    path
+}
+
+/// Calls one of the fixture's `Fixture*` test hooks, which report its internal counters.
+///
+/// Opening a library that is already loaded returns the same instance, so the counters
+/// are those seen by every `CircleLibrary` loaded from `path`.
+pub fn counter(path: &str, hook: &str) -> c_int {
+    unsafe {
+        let lib = Library::new(path).unwrap();
+        let hook = lib
+            .get::<unsafe extern "C" fn() -> c_int>(hook.as_bytes())
+            .unwrap();
+        hook()
+    }
+}
+
+/// Polls `future` to completion inside the context of a Tokio runtime that has already
+/// shut down, so any blocking task it spawns is cancelled instead of run.
+pub fn run_after_shutdown<F: Future>(future: F) -> F::Output {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let handle = runtime.handle().clone();
+    runtime.shutdown_background();
+    let _guard = handle.enter();
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::yield_now();
+    }
+}
@@ -0,0 +1,26 @@
+//! Lists the exports of the C fixture library with the `diagnostics` feature.
+
+#![cfg(all(unix, feature = "diagnostics"))]
+
+mod common;
+
+use go_rust_ffi::error::LibraryError;
+use go_rust_ffi::CircleLibrary;
+
+#[test]
+fn lists_the_exported_symbols() {
+    let symbols = CircleLibrary::diagnose(&common::fixture()).unwrap();
+    assert!(symbols.iter().any(|name| name == "CalculateCircleArea"));
+    assert!(symbols.iter().any(|name| name == "FreeString"));
+    // Static helpers are not exported.
+    assert!(!symbols.iter().any(|name| name == "format_string"));
+}
+
+#[test]
+fn rejects_a_file_that_is_not_a_library() {
+    let source = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/circle.c");
+    assert!(matches!(
+        CircleLibrary::diagnose(source),
+        Err(LibraryError::InvalidInput(_))
+    ));
+}
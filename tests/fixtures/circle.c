@@ -0,0 +1,1054 @@
+// A C stand-in for the Go circle library, used by the integration tests.
+//
+// It exports the same functions with the same C signatures and behavior as main.go, so the
+// tests can exercise the FFI paths without a Go toolchain. Goroutines become detached
+// pthreads, and the Go delays are shortened to keep the tests fast.
+
+#include <pthread.h>
+#include <stdarg.h>
+#include <inttypes.h>
+#include <stddef.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+#define PI 3.14159265358979323846
+
+// Variants are selected with preprocessor defines:
+//
+// FIXTURE_ASYNC_NEVER_FIRES  CalculateCircleAreaAsync never calls its callback.
+// FIXTURE_ASYNC_FIRES_TWICE  CalculateCircleAreaAsync calls its callback a second time.
+// FIXTURE_ASYNC_DELAY_MS=n   Async calculations wait n milliseconds before each callback
+//                            instead of 10, unless FlushAsync is called.
+// FIXTURE_ABI_VERSION=n      LibraryAbiVersion reports n instead of the current version.
+// FIXTURE_AREA_SCALE=n       CalculateCircleArea returns n times the area, like a library
+//                            with a different formula.
+// FIXTURE_GENERATOR_LIMIT=n  Number generators are exhausted after producing n numbers.
+// FIXTURE_GENERATOR_BLOCKS   Number generators produce nothing and block until stopped.
+// FIXTURE_GENERATOR_DELAY=n  Number generators wait n milliseconds before each number.
+// FIXTURE_FOREIGN_BYTE_ORDER NativeByteOrder reports the opposite of the host byte order,
+//                            like a library cross-compiled for another architecture.
+// FIXTURE_NULL_INFO_ENTRY    FormatCircleInfos leaves the second string null, like a
+//                            library that failed to format one of them.
+// FIXTURE_UNRESOLVED_SYMBOL  Links against a function no library defines, so loading
+//                            only succeeds while symbols are bound lazily.
+// FIXTURE_SHAPE_SIZE=n       SizeOfShape reports n instead of the size of Shape.
+// FIXTURE_TRUE_BYTE=n        Generators report a produced number with the byte n instead of 1.
+// FIXTURE_MULTI_SHOT_COUNT=n CalculateCircleAreaAsyncMultiple produces n results instead of 3.
+// FIXTURE_STATIC_STRINGS     Single strings are returned from a static buffer that must not
+//                            be freed, and StringIsOwned reports 0.
+// FIXTURE_MINIMAL            Functions marked OPTIONAL_EXPORT are not exported, like an
+//                            older library that only has the core functions.
+
+#ifdef FIXTURE_MINIMAL
+#define OPTIONAL_EXPORT __attribute__((visibility("hidden")))
+#else
+#define OPTIONAL_EXPORT
+#endif
+
+#ifndef FIXTURE_AREA_SCALE
+#define FIXTURE_AREA_SCALE 1
+#endif
+
+#ifndef FIXTURE_TRUE_BYTE
+#define FIXTURE_TRUE_BYTE 1
+#endif
+
+#ifndef FIXTURE_MULTI_SHOT_COUNT
+#define FIXTURE_MULTI_SHOT_COUNT 3
+#endif
+
+// How long an async calculation waits before each callback, unless flushed.
+#ifndef FIXTURE_ASYNC_DELAY_MS
+#define FIXTURE_ASYNC_DELAY_MS 10
+#endif
+
+typedef double (*callback_t)(double);
+typedef int (*int_callback_t)(int);
+typedef double (*named_callback_t)(double val, const char* name);
+typedef double (*context_callback_t)(double val, void* userData);
+typedef unsigned char (*async_callback_t)(double result, void* userData);
+typedef void (*done_callback_t)(void* userData);
+typedef void (*progress_callback_t)(float fraction, void* userData);
+typedef unsigned char (*request_callback_t)(void* userData);
+typedef void (*error_handler_t)(const char* message, void* userData);
+
+typedef struct {
+    double radius;
+} Circle;
+
+typedef struct {
+    double area;
+    double circumference;
+    double diameter;
+} CircleDetails;
+
+typedef struct {
+    int precision_bits;
+    int unit;
+} AreaMeta;
+
+typedef struct {
+    int value;
+    unsigned char ok;
+} NextResult;
+
+typedef struct {
+    double* data;
+    size_t len;
+} DoubleArray;
+
+typedef struct {
+    char** data;
+    size_t len;
+} StringArray;
+
+typedef enum {
+    SHAPE_CIRCLE = 0,
+    SHAPE_SQUARE = 1,
+    SHAPE_TRIANGLE = 2,
+    SHAPE_RECTANGLE = 3,
+    SHAPE_ELLIPSE = 4
+} ShapeType;
+
+typedef struct {
+    ShapeType shape_type;
+    double dimension1;
+    double dimension2;
+} Shape;
+
+typedef Shape (*shape_callback_t)(Shape shape);
+
+// Lengths are Go ints, which cgo exports as a pointer-sized GoInt, and generator ids are
+// Go uintptrs, exported as GoUintptr.
+typedef ptrdiff_t GoInt;
+typedef uintptr_t GoUintptr;
+
+#ifndef FIXTURE_ABI_VERSION
+#define FIXTURE_ABI_VERSION 2
+#endif
+
+// Error handler.
+
+static pthread_mutex_t error_handler_mutex = PTHREAD_MUTEX_INITIALIZER;
+static error_handler_t error_handler;
+static void* error_handler_data;
+
+void SetErrorHandler(error_handler_t cb, void* userData) {
+    pthread_mutex_lock(&error_handler_mutex);
+    error_handler = cb;
+    error_handler_data = userData;
+    pthread_mutex_unlock(&error_handler_mutex);
+}
+
+static void report_error(const char* format, ...) {
+    pthread_mutex_lock(&error_handler_mutex);
+    error_handler_t cb = error_handler;
+    void* userData = error_handler_data;
+    pthread_mutex_unlock(&error_handler_mutex);
+    if (cb == NULL) {
+        return;
+    }
+    char msg[256];
+    va_list args;
+    va_start(args, format);
+    vsnprintf(msg, sizeof msg, format, args);
+    va_end(args);
+    cb(msg, userData);
+}
+
+// Runtime initialization and version checks.
+
+static void stop_all_generators(void);
+
+static pthread_mutex_t runtime_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int runtime_users;
+static int init_calls;
+static int shutdown_calls;
+
+void InitLibrary(void) {
+    pthread_mutex_lock(&runtime_mutex);
+    init_calls++;
+    runtime_users++;
+    pthread_mutex_unlock(&runtime_mutex);
+}
+
+void ShutdownLibrary(void) {
+    pthread_mutex_lock(&runtime_mutex);
+    shutdown_calls++;
+    if (runtime_users > 0) {
+        runtime_users--;
+    }
+    int last = runtime_users == 0;
+    pthread_mutex_unlock(&runtime_mutex);
+    if (last) {
+        stop_all_generators();
+    }
+}
+
+OPTIONAL_EXPORT int LibraryAbiVersion(void) {
+    return FIXTURE_ABI_VERSION;
+}
+
+int NativeByteOrder(void) {
+    unsigned short probe = 1;
+    int big = *(unsigned char*)&probe != 1;
+#ifdef FIXTURE_FOREIGN_BYTE_ORDER
+    big = !big;
+#endif
+    return big;
+}
+
+#ifdef FIXTURE_SHAPE_SIZE
+size_t SizeOfShape(void) { return FIXTURE_SHAPE_SIZE; }
+#else
+size_t SizeOfShape(void) { return sizeof(Shape); }
+#endif
+size_t SizeOfCircle(void) { return sizeof(Circle); }
+size_t SizeOfCircleDetails(void) { return sizeof(CircleDetails); }
+size_t SizeOfAreaMeta(void) { return sizeof(AreaMeta); }
+size_t SizeOfNextResult(void) { return sizeof(NextResult); }
+
+double LibraryPi(void) {
+    return PI;
+}
+
+OPTIONAL_EXPORT AreaMeta AreaMetadata(void) {
+    AreaMeta meta = {64, 0};
+    return meta;
+}
+
+// Arithmetic.
+
+double CalculateCircleArea(double radius) {
+    return FIXTURE_AREA_SCALE * PI * radius * radius;
+}
+
+OPTIONAL_EXPORT float CalculateCircleAreaF32(float radius) {
+    return (float)PI * radius * radius;
+}
+
+OPTIONAL_EXPORT double CalculateCircumference(double radius) {
+    return 2 * PI * radius;
+}
+
+OPTIONAL_EXPORT double CalculateDiameter(double radius) {
+    return 2 * radius;
+}
+
+int AddInts(int a, int b) {
+    // Wrap around like Go's int32 arithmetic, without signed overflow.
+    return (int)((unsigned)a + (unsigned)b);
+}
+
+int MultiplyInts(int a, int b) {
+    return (int)((unsigned)a * (unsigned)b);
+}
+
+void CalculateCircleAreasBatch(const double* radii, GoInt n, double* out) {
+    for (GoInt i = 0; i < n; i++) {
+        out[i] = CalculateCircleArea(radii[i]);
+    }
+}
+
+static pthread_mutex_t sum_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int sum_calls;
+
+double SumAreas(const double* areas, int n) {
+    pthread_mutex_lock(&sum_mutex);
+    sum_calls++;
+    pthread_mutex_unlock(&sum_mutex);
+    double sum = 0;
+    for (int i = 0; i < n; i++) {
+        sum += areas[i];
+    }
+    return sum;
+}
+
+double CalculateCircleStructArea(Circle c) {
+    return PI * c.radius * c.radius;
+}
+
+// Strings.
+
+static pthread_mutex_t strings_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int live_strings;
+static int string_frees;
+static int live_string_arrays;
+
+static void count_strings(int delta) {
+    pthread_mutex_lock(&strings_mutex);
+    live_strings += delta;
+    pthread_mutex_unlock(&strings_mutex);
+}
+
+static void count_string_arrays(int delta) {
+    pthread_mutex_lock(&strings_mutex);
+    live_string_arrays += delta;
+    pthread_mutex_unlock(&strings_mutex);
+}
+
+static char* format_string(const char* format, ...) {
+    va_list args;
+    va_start(args, format);
+    int len = vsnprintf(NULL, 0, format, args);
+    va_end(args);
+    char* str = malloc((size_t)len + 1);
+    if (str == NULL) {
+        return NULL;
+    }
+    va_start(args, format);
+    vsnprintf(str, (size_t)len + 1, format, args);
+    va_end(args);
+    count_strings(1);
+    return str;
+}
+
+// Hands a formatted string to the caller, moving it into a static buffer if strings are
+// static.
+static char* return_string(char* str) {
+#ifdef FIXTURE_STATIC_STRINGS
+    static __thread char buffer[512];
+    if (str == NULL) {
+        return NULL;
+    }
+    snprintf(buffer, sizeof buffer, "%s", str);
+    count_strings(-1);
+    free(str);
+    return buffer;
+#else
+    return str;
+#endif
+}
+
+static char* circle_info(double radius) {
+    return format_string("Circle with radius %.2f has area %.2f", radius,
+                         CalculateCircleArea(radius));
+}
+
+char* FormatCircleInfo(double radius) {
+    return return_string(circle_info(radius));
+}
+
+char* FormatCircleInfoPrec(double radius, int decimals) {
+    int prec = decimals < 0 ? 0 : decimals;
+    return return_string(format_string("Circle with radius %.*f has area %.*f", prec, radius,
+                                       prec, CalculateCircleArea(radius)));
+}
+
+char* FormatCircleInfoLabeled(const char* label, double radius) {
+    return return_string(format_string("%s: Circle with radius %.2f has area %.2f", label,
+                                       radius, CalculateCircleArea(radius)));
+}
+
+unsigned char StringIsOwned(void) {
+#ifdef FIXTURE_STATIC_STRINGS
+    return 0;
+#else
+    return 1;
+#endif
+}
+
+void FreeString(char* str) {
+    pthread_mutex_lock(&strings_mutex);
+    string_frees++;
+    pthread_mutex_unlock(&strings_mutex);
+    count_strings(-1);
+    free(str);
+}
+
+OPTIONAL_EXPORT CircleDetails* GetCircleDetails(double radius) {
+    CircleDetails* details = malloc(sizeof(CircleDetails));
+    if (details == NULL) {
+        return NULL;
+    }
+    details->area = PI * radius * radius;
+    details->circumference = 2 * PI * radius;
+    details->diameter = 2 * radius;
+    return details;
+}
+
+void FreeCircleDetails(CircleDetails* details) {
+    free(details);
+}
+
+static pthread_mutex_t arrays_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int live_double_arrays;
+
+static void count_double_arrays(int delta) {
+    pthread_mutex_lock(&arrays_mutex);
+    live_double_arrays += delta;
+    pthread_mutex_unlock(&arrays_mutex);
+}
+
+DoubleArray* MapRadii(const double* radii, GoInt n) {
+    DoubleArray* arr = malloc(sizeof(DoubleArray));
+    if (arr == NULL) {
+        return NULL;
+    }
+    arr->data = NULL;
+    arr->len = 0;
+    if (n <= 0) {
+        count_double_arrays(1);
+        return arr;
+    }
+    arr->data = malloc((size_t)n * sizeof(double));
+    if (arr->data == NULL) {
+        free(arr);
+        return NULL;
+    }
+    arr->len = (size_t)n;
+    // Map each radius to the running total of the circle areas seen so far.
+    double total = 0;
+    for (GoInt i = 0; i < n; i++) {
+        total += PI * radii[i] * radii[i];
+        arr->data[i] = total;
+    }
+    count_double_arrays(1);
+    return arr;
+}
+
+void FreeDoubleArray(DoubleArray* arr) {
+    if (arr == NULL) {
+        return;
+    }
+    count_double_arrays(-1);
+    free(arr->data);
+    free(arr);
+}
+
+StringArray* FormatCircleInfos(const double* radii, GoInt n) {
+    StringArray* arr = malloc(sizeof(StringArray));
+    if (arr == NULL) {
+        return NULL;
+    }
+    arr->data = NULL;
+    arr->len = 0;
+    if (n <= 0) {
+        return arr;
+    }
+    arr->data = malloc((size_t)n * sizeof(char*));
+    if (arr->data == NULL) {
+        free(arr);
+        return NULL;
+    }
+    arr->len = (size_t)n;
+    for (GoInt i = 0; i < n; i++) {
+        arr->data[i] = circle_info(radii[i]);
+    }
+#ifdef FIXTURE_NULL_INFO_ENTRY
+    if (n > 1) {
+        count_strings(-1);
+        free(arr->data[1]);
+        arr->data[1] = NULL;
+    }
+#endif
+    count_string_arrays(1);
+    return arr;
+}
+
+void FreeStringArray(StringArray* arr) {
+    if (arr == NULL) {
+        return;
+    }
+    count_string_arrays(-1);
+    for (size_t i = 0; i < arr->len; i++) {
+        if (arr->data[i] != NULL) {
+            count_strings(-1);
+        }
+        free(arr->data[i]);
+    }
+    free(arr->data);
+    free(arr);
+}
+
+// Shapes.
+
+#define MAX_GENERATED_SHAPES 64
+#define SHAPE_TYPE_COUNT 5
+
+Shape* GenerateShapes(GoInt count, GoInt* produced) {
+    *produced = 0;
+    GoInt n = count > MAX_GENERATED_SHAPES ? MAX_GENERATED_SHAPES : count;
+    if (n <= 0) {
+        return NULL;
+    }
+    Shape* shapes = malloc((size_t)n * sizeof(Shape));
+    if (shapes == NULL) {
+        return NULL;
+    }
+    for (GoInt i = 0; i < n; i++) {
+        double size = (double)(i + 1);
+        shapes[i].shape_type = (ShapeType)(i % SHAPE_TYPE_COUNT);
+        shapes[i].dimension1 = size;
+        shapes[i].dimension2 = size / 2;
+    }
+    *produced = n;
+    return shapes;
+}
+
+void FreeShapes(Shape* shapes, GoInt n) {
+    (void)n;
+    free(shapes);
+}
+
+OPTIONAL_EXPORT double CalculateShapeArea(Shape shape) {
+    double d1 = shape.dimension1, d2 = shape.dimension2;
+    switch (shape.shape_type) {
+    case SHAPE_CIRCLE:
+        return PI * d1 * d1;
+    case SHAPE_SQUARE:
+        return d1 * d1;
+    case SHAPE_TRIANGLE:
+        return 0.5 * d1 * d2;
+    case SHAPE_RECTANGLE:
+        return d1 * d2;
+    case SHAPE_ELLIPSE:
+        return PI * d1 * d2;
+    default:
+        report_error("CalculateShapeArea: unknown shape type %d", (int)shape.shape_type);
+        return 0.0;
+    }
+}
+
+static int invalid_dimension(double d) {
+    return !(d >= 0 && d - d == 0);
+}
+
+double CalculateShapeAreaChecked(Shape shape, int* code) {
+    *code = 0;
+    int uses_two;
+    switch (shape.shape_type) {
+    case SHAPE_CIRCLE:
+    case SHAPE_SQUARE:
+        uses_two = 0;
+        break;
+    case SHAPE_TRIANGLE:
+    case SHAPE_RECTANGLE:
+    case SHAPE_ELLIPSE:
+        uses_two = 1;
+        break;
+    default:
+        *code = 1;
+        return 0.0;
+    }
+    double d1 = shape.dimension1, d2 = shape.dimension2;
+    if (invalid_dimension(d1) || (uses_two && invalid_dimension(d2))) {
+        *code = 2;
+        return 0.0;
+    }
+    if (d1 == 0 || (uses_two && d2 == 0)) {
+        *code = 3;
+        return 0.0;
+    }
+    return CalculateShapeArea(shape);
+}
+
+void NormalizeShapes(Shape* shapes, GoInt n) {
+    for (GoInt i = 0; i < n; i++) {
+        Shape* shape = &shapes[i];
+        shape->dimension1 = shape->dimension1 < 0 ? -shape->dimension1 : shape->dimension1;
+        shape->dimension2 = shape->dimension2 < 0 ? -shape->dimension2 : shape->dimension2;
+        if (shape->shape_type == SHAPE_CIRCLE || shape->shape_type == SHAPE_SQUARE) {
+            shape->dimension2 = 0;
+        }
+    }
+}
+
+void CalculateShapeAreasBatch(const Shape* shapes, GoInt n, double* out) {
+    for (GoInt i = 0; i < n; i++) {
+        out[i] = CalculateShapeArea(shapes[i]);
+    }
+}
+
+// Synchronous callbacks.
+
+double CallCallback(double val, callback_t cb) {
+    return cb(val);
+}
+
+int CallIntCallback(int val, int_callback_t cb) {
+    return cb(val);
+}
+
+Shape TransformShape(Shape shape, shape_callback_t cb) {
+    return cb(shape);
+}
+
+double CallNamedCallback(double val, const char* name, named_callback_t cb) {
+    return cb(val, name);
+}
+
+double CallCallbackWithContext(double val, context_callback_t cb, void* userData) {
+    return cb(val, userData);
+}
+
+typedef struct {
+    const double* in;
+    double* out;
+    GoInt index;
+    context_callback_t cb;
+    void* userData;
+} ConcurrentCall;
+
+static void* concurrent_call(void* arg) {
+    ConcurrentCall* call = arg;
+    call->out[call->index] = call->cb(call->in[call->index], call->userData);
+    return NULL;
+}
+
+void CallCallbackConcurrent(const double* vals, GoInt n, context_callback_t cb, void* userData,
+                            double* out) {
+    ConcurrentCall* calls = calloc(n, sizeof(ConcurrentCall));
+    pthread_t* threads = calloc(n, sizeof(pthread_t));
+    for (GoInt i = 0; i < n; i++) {
+        calls[i] = (ConcurrentCall){vals, out, i, cb, userData};
+        pthread_create(&threads[i], NULL, concurrent_call, &calls[i]);
+    }
+    for (GoInt i = 0; i < n; i++) {
+        pthread_join(threads[i], NULL);
+    }
+    free(threads);
+    free(calls);
+}
+
+// Asynchronous calculations.
+
+static pthread_mutex_t flush_mutex = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t flush_cond = PTHREAD_COND_INITIALIZER;
+static unsigned long flush_generation;
+
+// Returns the generation the next FlushAsync ends. Exports take it before spawning their
+// thread, so a flush also covers calls whose thread has not begun waiting yet.
+static unsigned long flush_signal(void) {
+    pthread_mutex_lock(&flush_mutex);
+    unsigned long generation = flush_generation;
+    pthread_mutex_unlock(&flush_mutex);
+    return generation;
+}
+
+// Waits for ms milliseconds, or less once the call's flush generation has ended.
+static void async_delay(long ms, unsigned long generation) {
+    struct timespec deadline;
+    clock_gettime(CLOCK_REALTIME, &deadline);
+    deadline.tv_sec += ms / 1000;
+    deadline.tv_nsec += (ms % 1000) * 1000000L;
+    if (deadline.tv_nsec >= 1000000000L) {
+        deadline.tv_sec++;
+        deadline.tv_nsec -= 1000000000L;
+    }
+    pthread_mutex_lock(&flush_mutex);
+    while (generation == flush_generation) {
+        if (pthread_cond_timedwait(&flush_cond, &flush_mutex, &deadline) != 0) {
+            break;
+        }
+    }
+    pthread_mutex_unlock(&flush_mutex);
+}
+
+void FlushAsync(void) {
+    pthread_mutex_lock(&flush_mutex);
+    flush_generation++;
+    pthread_cond_broadcast(&flush_cond);
+    pthread_mutex_unlock(&flush_mutex);
+}
+
+typedef struct {
+    double radius;
+    progress_callback_t progress;
+    request_callback_t request;
+    async_callback_t cb;
+    done_callback_t done;
+    void* userData;
+    unsigned long flush;
+} AsyncCall;
+
+static void spawn(void* (*run)(void*), AsyncCall call) {
+    AsyncCall* arg = malloc(sizeof(AsyncCall));
+    *arg = call;
+    arg->flush = flush_signal();
+    pthread_t thread;
+    pthread_create(&thread, NULL, run, arg);
+    pthread_detach(thread);
+}
+
+static pthread_mutex_t duplicates_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int duplicate_callbacks;
+
+static void* run_async(void* arg) {
+    AsyncCall call = *(AsyncCall*)arg;
+    free(arg);
+    async_delay(FIXTURE_ASYNC_DELAY_MS, call.flush);
+    call.cb(PI * call.radius * call.radius, call.userData);
+#ifdef FIXTURE_ASYNC_FIRES_TWICE
+    // Simulate a library that mistakenly delivers the result twice.
+    async_delay(FIXTURE_ASYNC_DELAY_MS, call.flush);
+    call.cb(PI * call.radius * call.radius, call.userData);
+    pthread_mutex_lock(&duplicates_mutex);
+    duplicate_callbacks++;
+    pthread_mutex_unlock(&duplicates_mutex);
+#endif
+    return NULL;
+}
+
+#define MAX_RECORDED_USER_DATA 64
+
+static pthread_mutex_t user_data_mutex = PTHREAD_MUTEX_INITIALIZER;
+static void* async_user_data[MAX_RECORDED_USER_DATA];
+static int async_user_data_count;
+
+OPTIONAL_EXPORT void CalculateCircleAreaAsync(double radius, async_callback_t cb,
+                                              void* userData) {
+    pthread_mutex_lock(&user_data_mutex);
+    if (async_user_data_count < MAX_RECORDED_USER_DATA) {
+        async_user_data[async_user_data_count++] = userData;
+    }
+    pthread_mutex_unlock(&user_data_mutex);
+#ifdef FIXTURE_ASYNC_NEVER_FIRES
+    // Simulate a library that loses the callback.
+    (void)radius;
+    (void)cb;
+    (void)userData;
+    return;
+#endif
+    spawn(run_async, (AsyncCall){.radius = radius, .cb = cb, .userData = userData});
+}
+
+#define PROGRESS_STEPS 4
+
+static void* run_async_progress(void* arg) {
+    AsyncCall call = *(AsyncCall*)arg;
+    free(arg);
+    for (int i = 1; i <= PROGRESS_STEPS; i++) {
+        async_delay(FIXTURE_ASYNC_DELAY_MS, call.flush);
+        call.progress((float)i / PROGRESS_STEPS, call.userData);
+    }
+    call.cb(PI * call.radius * call.radius, call.userData);
+    return NULL;
+}
+
+void CalculateCircleAreaAsyncProgress(double radius, progress_callback_t progress,
+                                      async_callback_t cb, void* userData) {
+    spawn(run_async_progress, (AsyncCall){.radius = radius,
+                                          .progress = progress,
+                                          .cb = cb,
+                                          .userData = userData});
+}
+
+static pthread_mutex_t multi_shot_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int multi_shot_callbacks;
+
+static void* run_async_multiple(void* arg) {
+    AsyncCall call = *(AsyncCall*)arg;
+    free(arg);
+    for (int i = 0; i < FIXTURE_MULTI_SHOT_COUNT; i++) {
+        async_delay(FIXTURE_ASYNC_DELAY_MS, call.flush);
+        pthread_mutex_lock(&multi_shot_mutex);
+        multi_shot_callbacks++;
+        pthread_mutex_unlock(&multi_shot_mutex);
+        if (call.cb(PI * call.radius * call.radius, call.userData) == 0) {
+            break;
+        }
+    }
+    call.done(call.userData);
+    return NULL;
+}
+
+OPTIONAL_EXPORT void CalculateCircleAreaAsyncMultiple(double radius, async_callback_t cb,
+                                                      done_callback_t done, void* userData) {
+    spawn(run_async_multiple,
+          (AsyncCall){.radius = radius, .cb = cb, .done = done, .userData = userData});
+}
+
+static pthread_mutex_t poll_mutex = PTHREAD_MUTEX_INITIALIZER;
+static int poll_values;
+static int polls_done;
+
+static void* run_poll(void* arg) {
+    AsyncCall call = *(AsyncCall*)arg;
+    free(arg);
+    while (call.request(call.userData) != 0) {
+        pthread_mutex_lock(&poll_mutex);
+        poll_values++;
+        pthread_mutex_unlock(&poll_mutex);
+        if (call.cb(PI * call.radius * call.radius, call.userData) == 0) {
+            break;
+        }
+    }
+    call.done(call.userData);
+    pthread_mutex_lock(&poll_mutex);
+    polls_done++;
+    pthread_mutex_unlock(&poll_mutex);
+    return NULL;
+}
+
+void CalculateCircleAreaPoll(double radius, request_callback_t request, async_callback_t cb,
+                             done_callback_t done, void* userData) {
+    spawn(run_poll, (AsyncCall){.radius = radius,
+                                .request = request,
+                                .cb = cb,
+                                .done = done,
+                                .userData = userData});
+}
+
+// Number generators.
+
+#define MAX_GENERATORS 1024
+
+typedef struct {
+    int used;
+    int stopped;
+    int freed;
+    int next;
+} Generator;
+
+static pthread_mutex_t gen_mutex = PTHREAD_MUTEX_INITIALIZER;
+static pthread_cond_t gen_stopped = PTHREAD_COND_INITIALIZER;
+static Generator generators[MAX_GENERATORS];
+static GoUintptr next_id = 1;
+static int generator_frees;
+static int generator_double_frees;
+static int blocked_pulls;
+
+static void stop_all_generators(void) {
+    pthread_mutex_lock(&gen_mutex);
+    for (GoUintptr id = 1; id < next_id; id++) {
+        generators[id].stopped = 1;
+        generators[id].used = 0;
+    }
+    pthread_cond_broadcast(&gen_stopped);
+    pthread_mutex_unlock(&gen_mutex);
+}
+
+GoUintptr CreateNumberGenerator(void) {
+    pthread_mutex_lock(&gen_mutex);
+    GoUintptr id = next_id < MAX_GENERATORS ? next_id++ : 0;
+    generators[id] = (Generator){.used = 1};
+    pthread_mutex_unlock(&gen_mutex);
+    return id;
+}
+
+// Produces the next number of generator id, reporting 0 once it has been stopped or if
+// the id is unknown. Unlike the Go generator, numbers are produced without a delay unless
+// FIXTURE_GENERATOR_DELAY is set.
+static int next_number(const char* caller, GoUintptr id, int* out) {
+#ifdef FIXTURE_GENERATOR_DELAY
+    struct timespec delay = {FIXTURE_GENERATOR_DELAY / 1000, FIXTURE_GENERATOR_DELAY % 1000 * 1000000L};
+    nanosleep(&delay, NULL);
+#endif
+    pthread_mutex_lock(&gen_mutex);
+    if (id == 0 || id >= MAX_GENERATORS || !generators[id].used) {
+        pthread_mutex_unlock(&gen_mutex);
+        report_error("%s: unknown generator id %" PRIuPTR, caller, id);
+        return 0;
+    }
+    Generator* gen = &generators[id];
+#ifdef FIXTURE_GENERATOR_BLOCKS
+    // Like receiving from a Go channel nobody sends on.
+    blocked_pulls++;
+    while (!gen->stopped) {
+        pthread_cond_wait(&gen_stopped, &gen_mutex);
+    }
+    blocked_pulls--;
+#endif
+    int ok = !gen->stopped;
+#ifdef FIXTURE_GENERATOR_LIMIT
+    ok = ok && gen->next < FIXTURE_GENERATOR_LIMIT;
+#endif
+    if (ok) {
+        *out = gen->next++;
+    }
+    pthread_mutex_unlock(&gen_mutex);
+    return ok;
+}
+
+unsigned char GetNextNumber(GoUintptr id, int* out) {
+    int num;
+    if (!next_number("GetNextNumber", id, &num)) {
+        return 0;
+    }
+    *out = num;
+    return FIXTURE_TRUE_BYTE;
+}
+
+NextResult GetNextNumber2(GoUintptr id) {
+    NextResult result = {0, 0};
+    int num;
+    if (next_number("GetNextNumber2", id, &num)) {
+        result.value = num;
+        result.ok = FIXTURE_TRUE_BYTE;
+    }
+    return result;
+}
+
+// Pulls from a number generator as a double generator would, producing half of each
+// number. Not part of the Go library; it lets tests bind a generator of another type.
+unsigned char GetNextHalf(GoUintptr id, double* out) {
+    int num;
+    if (!next_number("GetNextHalf", id, &num)) {
+        return 0;
+    }
+    *out = num * 0.5;
+    return 1;
+}
+
+void StopNumberGenerator(GoUintptr id) {
+    pthread_mutex_lock(&gen_mutex);
+    if (id > 0 && id < MAX_GENERATORS && generators[id].used) {
+        generators[id].stopped = 1;
+    }
+    pthread_cond_broadcast(&gen_stopped);
+    pthread_mutex_unlock(&gen_mutex);
+}
+
+void FreeNumberGenerator(GoUintptr id) {
+    pthread_mutex_lock(&gen_mutex);
+    if (id > 0 && id < next_id) {
+        if (generators[id].freed) {
+            generator_double_frees++;
+        } else {
+            generator_frees++;
+        }
+        generators[id].stopped = 1;
+        generators[id].used = 0;
+        generators[id].freed = 1;
+    }
+    pthread_cond_broadcast(&gen_stopped);
+    pthread_mutex_unlock(&gen_mutex);
+}
+
+// Test hooks, not part of the Go library.
+
+// Returns an id no generator has, so pulling from it reports an error. Ids start at 1.
+GoUintptr CreateInvalidGenerator(void) {
+    return 0;
+}
+
+#ifdef FIXTURE_UNRESOLVED_SYMBOL
+extern void FixtureUndefinedFunction(void);
+
+// Never called; it only makes the library depend on the undefined function.
+void FixtureCallUndefinedFunction(void) {
+    FixtureUndefinedFunction();
+}
+#endif
+
+// Returns the id it was given, so tests can check ids survive the trip at full width.
+GoUintptr FixtureEchoId(GoUintptr id) {
+    return id;
+}
+
+int FixtureInitCalls(void) {
+    pthread_mutex_lock(&runtime_mutex);
+    int calls = init_calls;
+    pthread_mutex_unlock(&runtime_mutex);
+    return calls;
+}
+
+int FixtureShutdownCalls(void) {
+    pthread_mutex_lock(&runtime_mutex);
+    int calls = shutdown_calls;
+    pthread_mutex_unlock(&runtime_mutex);
+    return calls;
+}
+
+int FixtureSumAreasCalls(void) {
+    pthread_mutex_lock(&sum_mutex);
+    int calls = sum_calls;
+    pthread_mutex_unlock(&sum_mutex);
+    return calls;
+}
+
+// Counts the distinct user data values CalculateCircleAreaAsync has been called with.
+int FixtureDistinctAsyncUserData(void) {
+    pthread_mutex_lock(&user_data_mutex);
+    int distinct = 0;
+    for (int i = 0; i < async_user_data_count; i++) {
+        int seen = 0;
+        for (int j = 0; j < i; j++) {
+            seen |= async_user_data[j] == async_user_data[i];
+        }
+        distinct += !seen;
+    }
+    pthread_mutex_unlock(&user_data_mutex);
+    return distinct;
+}
+
+int FixtureGeneratorFrees(void) {
+    pthread_mutex_lock(&gen_mutex);
+    int frees = generator_frees;
+    pthread_mutex_unlock(&gen_mutex);
+    return frees;
+}
+
+// Returns how many pulls are currently blocked on a generator that produces nothing.
+int FixtureBlockedPulls(void) {
+    pthread_mutex_lock(&gen_mutex);
+    int pulls = blocked_pulls;
+    pthread_mutex_unlock(&gen_mutex);
+    return pulls;
+}
+
+int FixtureGeneratorDoubleFrees(void) {
+    pthread_mutex_lock(&gen_mutex);
+    int double_frees = generator_double_frees;
+    pthread_mutex_unlock(&gen_mutex);
+    return double_frees;
+}
+
+int FixtureMultiShotCallbacks(void) {
+    pthread_mutex_lock(&multi_shot_mutex);
+    int callbacks = multi_shot_callbacks;
+    pthread_mutex_unlock(&multi_shot_mutex);
+    return callbacks;
+}
+
+int FixturePollValues(void) {
+    pthread_mutex_lock(&poll_mutex);
+    int values = poll_values;
+    pthread_mutex_unlock(&poll_mutex);
+    return values;
+}
+
+int FixturePollsDone(void) {
+    pthread_mutex_lock(&poll_mutex);
+    int done = polls_done;
+    pthread_mutex_unlock(&poll_mutex);
+    return done;
+}
+
+int FixtureLiveDoubleArrays(void) {
+    pthread_mutex_lock(&arrays_mutex);
+    int live = live_double_arrays;
+    pthread_mutex_unlock(&arrays_mutex);
+    return live;
+}
+
+int FixtureStringFrees(void) {
+    pthread_mutex_lock(&strings_mutex);
+    int frees = string_frees;
+    pthread_mutex_unlock(&strings_mutex);
+    return frees;
+}
+
+int FixtureLiveStringArrays(void) {
+    pthread_mutex_lock(&strings_mutex);
+    int live = live_string_arrays;
+    pthread_mutex_unlock(&strings_mutex);
+    return live;
+}
+
+int FixtureLiveStrings(void) {
+    pthread_mutex_lock(&strings_mutex);
+    int live = live_strings;
+    pthread_mutex_unlock(&strings_mutex);
+    return live;
+}
+
+int FixtureDuplicateCallbacks(void) {
+    pthread_mutex_lock(&duplicates_mutex);
+    int duplicates = duplicate_callbacks;
+    pthread_mutex_unlock(&duplicates_mutex);
+    return duplicates;
+}
@@ -0,0 +1,307 @@
+//! Exercises the number generators of the C fixture library.
+
+#![cfg(unix)]
+
+mod common;
+
+use go_rust_ffi::error::LibraryError;
+use go_rust_ffi::generator::{
+    GeneratorId, GeneratorSymbols, GoGenerator, NextResult, NumberGenerator,
+};
+use go_rust_ffi::{CircleLibrary, GeneratorState};
+use libloading::Library;
+use std::collections::HashSet;
+use std::os::raw::c_int;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio_stream::StreamExt;
+
+fn limited_fixture() -> String {
+    common::fixture_with("limited", &["FIXTURE_GENERATOR_LIMIT=5"])
+}
+
+#[test]
+fn iterator_stops_when_the_generator_is_exhausted() {
+    let circle_lib = CircleLibrary::new(&limited_fixture()).unwrap();
+    let generator = circle_lib.number_generator().unwrap();
+    let numbers = generator.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(numbers, [0, 1, 2, 3, 4]);
+    // The iterator is fused, and the generator stays exhausted.
+    assert!(generator.iter().next().is_none());
+    assert_eq!(generator.next().unwrap(), None);
+}
+
+#[test]
+fn stopped_generator_ends_the_iterator() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let generator = circle_lib.number_generator().unwrap();
+    let first = generator
+        .iter()
+        .take(3)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(first, [0, 1, 2]);
+    generator.stop().unwrap();
+    assert_eq!(generator.iter().count(), 0);
+}
+
+#[test]
+fn concurrent_generators_are_each_freed_once() {
+    let path = common::fixture_with("concurrent", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let generator = circle_lib.number_generator().unwrap();
+            thread::spawn(move || {
+                let numbers = generator.iter().take(50).collect::<Result<Vec<_>, _>>();
+                (generator.to_string(), numbers.unwrap())
+            })
+        })
+        .collect();
+    let mut names = HashSet::new();
+    for handle in handles {
+        let (name, numbers) = handle.join().unwrap();
+        // Each generator counts on its own, unaffected by the others.
+        assert_eq!(numbers, (0..50).collect::<Vec<_>>());
+        names.insert(name);
+    }
+    assert_eq!(names.len(), 8, "generator ids must be distinct");
+    assert_eq!(common::counter(&path, "FixtureGeneratorFrees"), 8);
+    assert_eq!(common::counter(&path, "FixtureGeneratorDoubleFrees"), 0);
+}
+
+#[test]
+fn next_state_reports_exhaustion() {
+    let circle_lib = CircleLibrary::new(&limited_fixture()).unwrap();
+    let generator = circle_lib.number_generator().unwrap();
+    for expected in 0..5 {
+        assert!(matches!(generator.next_state(), GeneratorState::Value(n) if n == expected));
+    }
+    assert!(matches!(generator.next_state(), GeneratorState::Exhausted));
+    assert!(matches!(generator.next_state(), GeneratorState::Exhausted));
+}
+
+#[test]
+fn next_state_reports_a_missing_symbol_as_an_error() {
+    let lib = Arc::new(unsafe { Library::new(common::fixture()) }.unwrap());
+    let symbols = GeneratorSymbols {
+        next_result: None,
+        next: "GetNextNumberMissing",
+        ..GeneratorSymbols::NUMBERS
+    };
+    let generator = NumberGenerator::with_symbols(&lib, symbols).unwrap();
+    match generator.next_state() {
+        GeneratorState::Error(LibraryError::SymbolMissing { name, .. }) => {
+            assert_eq!(name, "GetNextNumberMissing")
+        }
+        other => panic!("expected a missing symbol, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn stream_yields_numbers_and_frees_the_generator_when_dropped() {
+    let path = common::fixture_with("stream", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let stream = circle_lib.number_generator().unwrap().into_stream();
+    let numbers: Vec<i32> = stream.take(10).collect().await;
+    assert_eq!(numbers, (0..10).collect::<Vec<_>>());
+    // The blocking task notices the dropped stream after at most one more pull.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while common::counter(&path, "FixtureGeneratorFrees") == 0 {
+        assert!(Instant::now() < deadline, "generator was not freed");
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(common::counter(&path, "FixtureGeneratorFrees"), 1);
+}
+
+#[tokio::test]
+async fn stream_ends_when_the_generator_is_exhausted() {
+    let circle_lib = CircleLibrary::new(&limited_fixture()).unwrap();
+    let stream = circle_lib.number_generator().unwrap().into_stream();
+    assert_eq!(stream.collect::<Vec<_>>().await, [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn out_parameter_export_yields_values_then_exhaustion() {
+    let lib = Arc::new(unsafe { Library::new(limited_fixture()) }.unwrap());
+    // Without `next_result`, values are pulled through `GetNextNumber`'s out-parameter.
+    let symbols = GeneratorSymbols {
+        next_result: None,
+        ..GeneratorSymbols::NUMBERS
+    };
+    let generator = NumberGenerator::with_symbols(&lib, symbols).unwrap();
+    for expected in 0..5 {
+        assert_eq!(generator.next().unwrap(), Some(expected));
+    }
+    assert_eq!(generator.next().unwrap(), None);
+}
+
+#[test]
+fn double_generator_converts_through_from_ffi() {
+    let lib = Arc::new(unsafe { Library::new(limited_fixture()) }.unwrap());
+    let symbols = GeneratorSymbols {
+        next_result: None,
+        next: "GetNextHalf",
+        ..GeneratorSymbols::NUMBERS
+    };
+    let generator = GoGenerator::<f64>::with_symbols(&lib, symbols).unwrap();
+    let values = generator.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, [0.0, 0.5, 1.0, 1.5, 2.0]);
+}
+
+#[test]
+fn debug_output_names_the_path_and_generator_id() {
+    let path = common::fixture_with("debug", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let debug = format!("{:?}", circle_lib);
+    assert!(debug.contains(&path), "{}", debug);
+    let generator = circle_lib.number_generator().unwrap();
+    // The first generator created by this fixture has id 1.
+    assert!(format!("{:?}", generator).contains("id: 1"));
+    assert_eq!(
+        generator.to_string(),
+        "generator 1 from `CreateNumberGenerator`"
+    );
+}
+
+fn make_generator(path: &str) -> NumberGenerator {
+    let circle_lib = CircleLibrary::new(path).unwrap();
+    circle_lib.number_generator().unwrap()
+    // `circle_lib` is dropped here; the generator keeps the library loaded.
+}
+
+#[test]
+fn generator_outlives_the_library_it_came_from() {
+    let generator = make_generator(&common::fixture());
+    let numbers = generator
+        .iter()
+        .take(4)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(numbers, [0, 1, 2, 3]);
+    let moved = thread::spawn(move || generator.next().unwrap());
+    assert_eq!(moved.join().unwrap(), Some(4));
+}
+
+#[test]
+fn any_non_zero_byte_means_a_number_was_produced() {
+    let path = common::fixture_with(
+        "true_byte",
+        &["FIXTURE_TRUE_BYTE=2", "FIXTURE_GENERATOR_LIMIT=3"],
+    );
+    let lib = Arc::new(unsafe { Library::new(&path) }.unwrap());
+    let out_param = GeneratorSymbols {
+        next_result: None,
+        ..GeneratorSymbols::NUMBERS
+    };
+    for symbols in [GeneratorSymbols::NUMBERS, out_param] {
+        let generator = NumberGenerator::with_symbols(&lib, symbols).unwrap();
+        let numbers = generator.iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(numbers, [0, 1, 2], "{:?}", symbols);
+    }
+}
+
+#[test]
+fn next_timeout_gives_up_on_a_blocked_generator() {
+    let path = common::fixture_with("blocking", &["FIXTURE_GENERATOR_BLOCKS"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let generator = circle_lib.number_generator().unwrap();
+    let timeout = Duration::from_millis(50);
+    let start = Instant::now();
+    assert!(matches!(
+        generator.next_timeout(timeout),
+        Err(LibraryError::Timeout(dur)) if dur == timeout
+    ));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    // The pull left behind keeps the generator alive until Go returns from it.
+    generator.stop().unwrap();
+    drop(generator);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while common::counter(&path, "FixtureGeneratorFrees") < 1 {
+        assert!(Instant::now() < deadline, "generator was not freed");
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn repeated_timeouts_leave_a_single_pull_blocked() {
+    let path = common::fixture_with("blocking", &["FIXTURE_GENERATOR_BLOCKS"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let generator = circle_lib.number_generator().unwrap();
+    for _ in 0..5 {
+        assert!(matches!(
+            generator.next_timeout(Duration::from_millis(10)),
+            Err(LibraryError::Timeout(_))
+        ));
+    }
+    assert_eq!(common::counter(&path, "FixtureBlockedPulls"), 1);
+    generator.stop().unwrap();
+}
+
+#[test]
+fn value_produced_after_a_timeout_is_returned_by_the_next_call() {
+    let path = common::fixture_with("slow", &["FIXTURE_GENERATOR_DELAY=200"]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    let generator = circle_lib.number_generator().unwrap();
+    assert!(matches!(
+        generator.next_timeout(Duration::from_millis(20)),
+        Err(LibraryError::Timeout(_))
+    ));
+    let patient = Duration::from_secs(5);
+    assert_eq!(generator.next_timeout(patient).unwrap(), Some(0));
+    assert_eq!(generator.next_timeout(patient).unwrap(), Some(1));
+}
+
+#[test]
+fn next_result_fields_are_read_from_the_returned_struct() {
+    let lib = unsafe { Library::new(limited_fixture()) }.unwrap();
+    unsafe {
+        let create = lib
+            .get::<unsafe extern "C" fn() -> GeneratorId>(b"CreateNumberGenerator")
+            .unwrap();
+        let next = lib
+            .get::<unsafe extern "C" fn(GeneratorId) -> NextResult<c_int>>(b"GetNextNumber2")
+            .unwrap();
+        let free = lib
+            .get::<unsafe extern "C" fn(GeneratorId)>(b"FreeNumberGenerator")
+            .unwrap();
+        let id = create();
+        for value in 0..5 {
+            assert_eq!(next(id), NextResult { value, ok: 1 });
+        }
+        assert_eq!(next(id), NextResult { value: 0, ok: 0 });
+        free(id);
+    }
+}
+
+#[test]
+fn generator_ids_round_trip_at_pointer_width() {
+    let path = common::fixture_with("generator_ids", &["FIXTURE_GENERATOR_LIMIT=2"]);
+    let lib = Arc::new(unsafe { Library::new(&path) }.unwrap());
+    // Extreme values keep all their bits, which they would not if `GeneratorId` were
+    // narrower than Go's `uintptr` on this target.
+    let echo = unsafe {
+        lib.get::<unsafe extern "C" fn(GeneratorId) -> GeneratorId>(b"FixtureEchoId")
+            .unwrap()
+    };
+    for id in [0, 1, GeneratorId::MAX / 2 + 1, GeneratorId::MAX] {
+        assert_eq!(unsafe { echo(id) }, id);
+    }
+    assert_eq!(
+        std::mem::size_of::<GeneratorId>(),
+        std::mem::size_of::<*const ()>()
+    );
+
+    // Ids handed out by Go reach `next`, `stop` and `free` unchanged.
+    for expected_id in 1..=3 {
+        let generator = NumberGenerator::new(&lib).unwrap();
+        assert_eq!(
+            generator.to_string(),
+            format!("generator {} from `CreateNumberGenerator`", expected_id)
+        );
+        let numbers = generator.iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(numbers, [0, 1]);
+    }
+    assert_eq!(common::counter(&path, "FixtureGeneratorFrees"), 3);
+}
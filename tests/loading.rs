@@ -0,0 +1,345 @@
+//! Exercises loading and releasing the C fixture library.
+
+#![cfg(unix)]
+
+mod common;
+
+use go_rust_ffi::error::LibraryError;
+use go_rust_ffi::layout::ByteOrder;
+use go_rust_ffi::{CircleLibrary, LoadFlags, EXPECTED_ABI_VERSION};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn dropping_the_last_handle_shuts_down_without_unloading() {
+    let path = common::fixture_with("never_unload", &[]);
+    for round in 1..=3 {
+        let circle_lib = CircleLibrary::new(&path).unwrap();
+        assert!(circle_lib.calculate_circle_area(1.0).is_ok());
+        drop(circle_lib);
+        // The fixture's counters would start from zero again had it been unloaded.
+        assert_eq!(common::counter(&path, "FixtureInitCalls"), round);
+        assert_eq!(common::counter(&path, "FixtureShutdownCalls"), round);
+    }
+}
+
+#[test]
+fn reports_the_functions_a_subset_library_exports() {
+    let path = common::fixture_with("minimal", &["FIXTURE_MINIMAL"]);
+    match CircleLibrary::new(&path) {
+        Err(LibraryError::SymbolMissing { name, .. }) => {
+            assert_eq!(name, "CalculateCircleAreaAsync")
+        }
+        other => panic!(
+            "expected a missing async symbol, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+
+    let circle_lib = CircleLibrary::builder()
+        .require_core()
+        .optional_async()
+        .optional_shape()
+        .build(&path)
+        .unwrap();
+    assert!(circle_lib.has_symbol("CalculateCircleArea"));
+    assert!(circle_lib.has_symbol("CallIntCallback"));
+    assert!(!circle_lib.has_symbol("CalculateCircleAreaAsync"));
+    assert!(!circle_lib.has_symbol("CalculateShapeArea"));
+    assert!(!circle_lib.has_symbol("NotAnExport"));
+
+    let capabilities = circle_lib.capabilities();
+    assert!(!capabilities.circle_area_async);
+    assert!(!capabilities.circle_area_async_multi);
+    assert!(!capabilities.shape_area);
+    assert!(!capabilities.circle_area_f32);
+    assert!(!capabilities.circle_details);
+    assert!(capabilities.circle_area_poll);
+    assert!(capabilities.int_callback);
+    assert!(capabilities.labeled_info);
+
+    // The core functions still work, and the missing ones fail at call time.
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        std::f64::consts::PI
+    );
+    assert!(matches!(
+        circle_lib.calculate_circle_area_f32(1.0),
+        Err(LibraryError::SymbolMissing { .. })
+    ));
+}
+
+#[test]
+fn rejects_a_library_with_a_different_abi_version() {
+    let path = common::fixture_with("abi_version", &["FIXTURE_ABI_VERSION=1"]);
+    match CircleLibrary::new(&path) {
+        Err(LibraryError::AbiMismatch { expected, found }) => {
+            assert_eq!(expected, EXPECTED_ABI_VERSION);
+            assert_eq!(found, 1);
+        }
+        other => panic!("expected an ABI mismatch, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn accepts_a_library_without_an_abi_version() {
+    // The minimal fixture does not export `LibraryAbiVersion`.
+    let path = common::fixture_with("minimal", &["FIXTURE_MINIMAL"]);
+    let circle_lib = CircleLibrary::builder()
+        .optional_async()
+        .optional_shape()
+        .build(&path)
+        .unwrap();
+    assert!(!circle_lib.has_symbol("LibraryAbiVersion"));
+}
+
+#[test]
+fn retries_until_the_library_appears() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let path = dir.join(format!("retry-{}.so", std::process::id()));
+    let partial = dir.join(format!("retry-{}.so.partial", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let source = common::fixture();
+    let writer = {
+        let path = path.clone();
+        thread::spawn(move || {
+            // Let the first few attempts fail, then move the library into place at once.
+            thread::sleep(Duration::from_millis(100));
+            std::fs::copy(source, &partial).unwrap();
+            std::fs::rename(&partial, &path).unwrap();
+        })
+    };
+    let circle_lib =
+        CircleLibrary::new_with_retry(path.to_str().unwrap(), 100, Duration::from_millis(20))
+            .unwrap();
+    writer.join().unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        std::f64::consts::PI
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn retry_returns_the_last_load_error() {
+    assert!(matches!(
+        CircleLibrary::new_with_retry("does-not-exist.so", 3, Duration::from_millis(1)),
+        Err(LibraryError::LoadFailed(_))
+    ));
+}
+
+#[test]
+fn rejects_a_library_with_a_different_shape_size() {
+    let path = common::fixture_with("shape_size", &["FIXTURE_SHAPE_SIZE=20"]);
+    match CircleLibrary::new(&path) {
+        Err(LibraryError::LayoutMismatch {
+            type_name,
+            expected,
+            found,
+        }) => {
+            assert_eq!(type_name, "Shape");
+            assert_eq!(expected, std::mem::size_of::<go_rust_ffi::Shape>());
+            assert_eq!(found, 20);
+        }
+        other => panic!("expected a layout mismatch, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn loads_a_library_from_its_bytes() {
+    // A real embedding would use `include_bytes!`, but the fixture is only built at runtime.
+    let bytes = std::fs::read(common::fixture()).unwrap();
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("bytes-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let files = || std::fs::read_dir(&dir).unwrap().count();
+
+    let circle_lib = CircleLibrary::from_bytes_in(&bytes, &dir).unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        std::f64::consts::PI
+    );
+    assert!(circle_lib.path().starts_with(&dir));
+    let clone = circle_lib.clone();
+    drop(circle_lib);
+    assert_eq!(files(), 1, "the file must outlive every clone");
+    drop(clone);
+    assert_eq!(files(), 0, "the file must be deleted with the last clone");
+    std::fs::remove_dir(&dir).unwrap();
+
+    let circle_lib = CircleLibrary::from_bytes(&bytes).unwrap();
+    assert_eq!(circle_lib.diameter(1.5), 3.0);
+}
+
+#[test]
+fn reload_swaps_in_the_new_library_only_if_it_loads() {
+    let single = common::fixture();
+    let double = common::fixture_with("double_area", &["FIXTURE_AREA_SCALE=2"]);
+    let mut circle_lib = CircleLibrary::new(&single).unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        std::f64::consts::PI
+    );
+
+    circle_lib.reload(&double).unwrap();
+    assert_eq!(circle_lib.path(), Path::new(&double));
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        2.0 * std::f64::consts::PI
+    );
+
+    // A failed reload keeps the current library.
+    assert!(circle_lib.reload("does-not-exist.so").is_err());
+    assert_eq!(circle_lib.path(), Path::new(&double));
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        2.0 * std::f64::consts::PI
+    );
+
+    circle_lib.reload(&single).unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        std::f64::consts::PI
+    );
+}
+
+#[test]
+fn reload_picks_up_a_library_rebuilt_at_the_same_path() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("libcircle_reload.so");
+    // Replace the file the way a linker does, so the mapped library is never modified.
+    let install = |fixture: &str| {
+        let partial = dir.join("libcircle_reload.so.partial");
+        std::fs::copy(fixture, &partial).unwrap();
+        std::fs::rename(&partial, &path).unwrap();
+    };
+    install(&common::fixture());
+    let path_str = path.to_str().unwrap();
+    let mut circle_lib = CircleLibrary::new(path_str).unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        std::f64::consts::PI
+    );
+
+    install(&common::fixture_with(
+        "double_area",
+        &["FIXTURE_AREA_SCALE=2"],
+    ));
+    circle_lib.reload(path_str).unwrap();
+    assert_eq!(circle_lib.path(), path);
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        2.0 * std::f64::consts::PI
+    );
+
+    install(&common::fixture());
+    circle_lib.reload(path_str).unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        std::f64::consts::PI
+    );
+    drop(circle_lib);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rejects_a_library_with_a_different_byte_order() {
+    let path = common::fixture_with("foreign_byte_order", &["FIXTURE_FOREIGN_BYTE_ORDER"]);
+    match CircleLibrary::new(&path) {
+        Err(LibraryError::ByteOrderMismatch { expected, found }) => {
+            assert_eq!(expected, ByteOrder::native());
+            assert_ne!(found, expected);
+        }
+        other => panic!(
+            "expected a byte order mismatch, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn initializes_and_shuts_down_the_runtime_once() {
+    let path = common::fixture_with("runtime_hooks", &[]);
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    assert_eq!(common::counter(&path, "FixtureInitCalls"), 1);
+    let clone = circle_lib.clone();
+    drop(circle_lib);
+    assert_eq!(common::counter(&path, "FixtureShutdownCalls"), 0);
+    drop(clone);
+    assert_eq!(common::counter(&path, "FixtureShutdownCalls"), 1);
+
+    // A generator keeps the runtime running after the library itself is dropped.
+    let circle_lib = CircleLibrary::new(&path).unwrap();
+    assert_eq!(common::counter(&path, "FixtureInitCalls"), 2);
+    let generator = circle_lib.number_generator().unwrap();
+    drop(circle_lib);
+    assert_eq!(common::counter(&path, "FixtureShutdownCalls"), 1);
+    drop(generator);
+    assert_eq!(common::counter(&path, "FixtureShutdownCalls"), 2);
+}
+
+#[test]
+fn missing_symbol_errors_name_the_symbol() {
+    let path = common::fixture_with("minimal", &["FIXTURE_MINIMAL"]);
+    let err = CircleLibrary::builder()
+        .optional_async()
+        .build(&path)
+        .expect_err("the minimal fixture does not export CalculateShapeArea");
+    assert!(
+        err.to_string().contains("CalculateShapeArea"),
+        "unexpected message: {}",
+        err
+    );
+    // The loader's own error is kept as the source.
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[tokio::test]
+async fn loads_a_library_from_async_code() {
+    let circle_lib = CircleLibrary::new_async(&common::fixture()).await.unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area_async(2.0).await.unwrap(),
+        std::f64::consts::PI * 4.0
+    );
+    // The future is `'static`, so it can also run as its own task.
+    let missing = tokio::spawn(CircleLibrary::new_async("does-not-exist.so"));
+    assert!(matches!(
+        missing.await.unwrap(),
+        Err(LibraryError::LoadFailed(_))
+    ));
+}
+
+#[test]
+fn async_load_is_cancelled_if_the_runtime_shuts_down_first() {
+    assert!(matches!(
+        common::run_after_shutdown(CircleLibrary::new_async(&common::fixture())),
+        Err(LibraryError::TaskCancelled)
+    ));
+}
+
+#[test]
+fn loads_with_now_binding() {
+    let circle_lib =
+        CircleLibrary::new_with_flags(&common::fixture(), LoadFlags::new().now().global()).unwrap();
+    assert_eq!(
+        circle_lib.calculate_circle_area(2.0).unwrap(),
+        std::f64::consts::PI * 4.0
+    );
+}
+
+// Other Unix linkers reject the undefined symbol when building the fixture.
+#[cfg(target_os = "linux")]
+#[test]
+fn now_binding_fails_fast_on_an_unresolved_symbol() {
+    let path = common::fixture_with("unresolved", &["FIXTURE_UNRESOLVED_SYMBOL"]);
+    // Use `RTLD_NOW` while the library is not loaded yet.
+    match CircleLibrary::new_with_flags(&path, LoadFlags::new().now()) {
+        Err(LibraryError::LoadFailed(e)) => {
+            assert!(e.to_string().contains("FixtureUndefinedFunction"), "{}", e)
+        }
+        other => panic!("expected a load failure, got {:?}", other.map(|_| ())),
+    }
+    // Bound lazily, the missing function only matters if it is ever called.
+    assert!(CircleLibrary::new(&path).is_ok());
+}
@@ -0,0 +1,47 @@
+//! Exercises the library cache of `LibraryManager` against the C fixture library.
+
+#![cfg(unix)]
+
+mod common;
+
+use go_rust_ffi::manager::LibraryManager;
+use std::path::Path;
+use std::sync::Arc;
+
+#[test]
+fn same_path_loaded_twice_shares_one_library() {
+    let path = common::fixture();
+    let manager = LibraryManager::new();
+    let first = manager.load(&path).unwrap();
+    let second = manager.load(&path).unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+
+    // Another spelling of the same file resolves to the same handle.
+    let file = Path::new(&path);
+    let respelled = file
+        .parent()
+        .unwrap()
+        .join(".")
+        .join(file.file_name().unwrap());
+    assert!(Arc::ptr_eq(&first, &manager.load(respelled).unwrap()));
+    assert_eq!(manager.len(), 1);
+}
+
+#[test]
+fn unload_unused_keeps_libraries_in_use() {
+    let manager = LibraryManager::new();
+    let circle_lib = manager.circle_library(common::fixture()).unwrap();
+    let unused = manager.load(common::fixture_with("unused", &[])).unwrap();
+    assert_eq!(manager.len(), 2);
+    drop(unused);
+    assert_eq!(manager.unload_unused(), 1);
+    // The `CircleLibrary` still refers to its handle, so it stays cached and usable.
+    assert_eq!(manager.len(), 1);
+    assert_eq!(
+        circle_lib.calculate_circle_area(1.0).unwrap(),
+        std::f64::consts::PI
+    );
+    drop(circle_lib);
+    assert_eq!(manager.unload_unused(), 1);
+    assert!(manager.is_empty());
+}
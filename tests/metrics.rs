@@ -0,0 +1,39 @@
+//! Checks the call statistics recorded with the `metrics` feature.
+
+#![cfg(all(unix, feature = "metrics"))]
+
+mod common;
+
+use go_rust_ffi::CircleLibrary;
+
+#[test]
+fn counts_calls_per_method_across_clones() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    let clone = circle_lib.clone();
+    for radius in [1.0, 2.0, 3.0] {
+        circle_lib.calculate_circle_area(radius).unwrap();
+    }
+    clone.format_circle_info(1.0).unwrap();
+    clone.format_circle_info(2.0).unwrap();
+    circle_lib.call_callback_with(1.0, |val| val);
+
+    let stats = circle_lib.stats();
+    assert_eq!(stats.get("calculate_circle_area").calls, 3);
+    assert_eq!(stats.get("format_circle_info").calls, 2);
+    assert_eq!(stats.get("call_callback_with").calls, 1);
+    assert_eq!(stats.get("calculate_shape_area").calls, 0);
+    assert!(stats.get("format_circle_info").total_time > std::time::Duration::ZERO);
+    assert_eq!(stats.total_calls(), 6);
+}
+
+#[test]
+fn wrapping_methods_are_counted_once() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    circle_lib.timed_calculate_circle_area(1.0).unwrap();
+    circle_lib.calculate_circle_areas(&[1.0, 2.0]);
+    let stats = circle_lib.stats();
+    assert_eq!(stats.get("timed_calculate_circle_area").calls, 1);
+    assert_eq!(stats.get("calculate_circle_areas").calls, 1);
+    assert_eq!(stats.get("calculate_circle_area_unchecked").calls, 0);
+    assert_eq!(stats.total_calls(), 2);
+}
@@ -0,0 +1,18 @@
+//! Checks the spans and events emitted with the `tracing` feature.
+
+#![cfg(all(unix, feature = "tracing"))]
+
+mod common;
+
+use go_rust_ffi::CircleLibrary;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[test]
+fn sync_call_runs_in_a_span_named_after_the_method() {
+    let circle_lib = CircleLibrary::new(&common::fixture()).unwrap();
+    assert_eq!(circle_lib.call_callback_with(2.0, |val| val + 1.0), 3.0);
+    // The trampoline's event is logged inside the span of the call, with its fields.
+    assert!(logs_contain("circle_lib.call_callback_with{val=2.0}"));
+    assert!(logs_contain("callback invoked"));
+}